@@ -0,0 +1,78 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures the overhead the facade adds to each log call.
+
+#![feature(test)]
+
+#[macro_use]
+extern crate log;
+extern crate test;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use log::{Log, LogLevelFilter, LogMetadata, LogRecord};
+use test::Bencher;
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        test::black_box(record.level());
+    }
+}
+
+static LOGGER: Logger = Logger;
+
+fn init() {
+    let _ = log::set_logger(|max_log_level| {
+        max_log_level.set(LogLevelFilter::Info);
+        &LOGGER
+    });
+}
+
+#[bench]
+fn disabled(b: &mut Bencher) {
+    init();
+    b.iter(|| debug!("hello {}", 1));
+}
+
+#[bench]
+fn uncontended(b: &mut Bencher) {
+    init();
+    b.iter(|| info!("hello {}", 1));
+}
+
+#[bench]
+fn contended(b: &mut Bencher) {
+    init();
+    let stop = Arc::new(AtomicBool::new(false));
+    let threads = (0..3).map(|_| {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                info!("background {}", 1);
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    b.iter(|| info!("hello {}", 1));
+
+    stop.store(true, Ordering::Relaxed);
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Reads LOG_STATIC_MAX_LEVEL and passes it on to __static_max_level and
+// __static_max_level_for.
+//
+// The variable is a comma separated list of directives. A bare level sets the
+// maximum level for every module, and is passed on as a cfg. A directive of
+// the form `path=level` sets the maximum level for the module `path` and the
+// modules inside it, and ends up in a table in static_max_levels.rs, sorted so
+// that the longest matching path comes first.
+
+use std::cmp::Reverse;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const LEVELS: [&str; 10] = ["off", "critical", "error", "warn", "notice", "info",
+                            "debug", "trace", "trace2", "trace3"];
+const VARIANTS: [&str; 10] = ["Off", "Critical", "Error", "Warn", "Notice", "Info",
+                              "Debug", "Trace", "Trace2", "Trace3"];
+
+fn parse_level(level: &str) -> usize {
+    let lower = level.trim().to_ascii_lowercase();
+    match LEVELS.iter().position(|&l| l == lower) {
+        Some(idx) => idx,
+        None => {
+            panic!("invalid level {:?} in LOG_STATIC_MAX_LEVEL, expected one of {:?}",
+                   level, LEVELS)
+        }
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=LOG_STATIC_MAX_LEVEL");
+    println!("cargo:rustc-check-cfg=cfg(log_static_max_level, values({}))",
+             LEVELS.iter().map(|l| format!("{:?}", l)).collect::<Vec<_>>().join(", "));
+
+    let spec = match env::var("LOG_STATIC_MAX_LEVEL") {
+        Ok(spec) => spec,
+        Err(env::VarError::NotPresent) => String::new(),
+        Err(env::VarError::NotUnicode(_)) => {
+            panic!("LOG_STATIC_MAX_LEVEL is not valid unicode")
+        }
+    };
+
+    let mut modules = Vec::new();
+    for directive in spec.split(',').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+        let mut parts = directive.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(level), None) => {
+                let level = LEVELS[parse_level(level)];
+                println!("cargo:rustc-cfg=log_static_max_level=\"{}\"", level);
+            }
+            (Some(path), Some(level)) => {
+                modules.push((path.trim().to_string(), parse_level(level)));
+            }
+            _ => unreachable!(),
+        }
+    }
+    modules.sort_by_key(|m| Reverse(m.0.len()));
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let path = Path::new(&out_dir).join("static_max_levels.rs");
+    let mut out = File::create(&path).unwrap();
+    writeln!(out, "const STATIC_MODULE_MAX_LEVELS: &[(&str, LogLevelFilter)] = &[").unwrap();
+    for &(ref path, level) in &modules {
+        writeln!(out, "    ({:?}, LogLevelFilter::{}),", path, VARIANTS[level]).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+}
@@ -90,8 +90,10 @@
 //! specified module will also have logging enabled.
 //!
 //! The actual `log_level` is optional to specify. If omitted, all logging will
-//! be enabled. If specified, it must be one of the strings `debug`, `error`,
-//! `info`, `warn`, or `trace`.
+//! be enabled. If specified, it must be one of the strings `off`, `critical`,
+//! `error`, `warn`, `notice`, `info`, `debug`, `trace`, `trace2` or `trace3`,
+//! or the number of one of those levels, from `0` for `off` to `9` for
+//! `trace3`. Note that `3` is `warn` and `5` is `info`.
 //!
 //! As the log level for a module is optional, the module to enable logging for
 //! is also optional. If only a `log_level` is provided, then the global log
@@ -262,7 +264,7 @@ impl LogBuilder {
             });
         }
 
-        log::set_logger(|max_level| {
+        log::set_boxed_logger(|max_level| {
             let level = {
                 let max = self.directives.iter().map(|d| d.level).max();
                 max.unwrap_or(LogLevelFilter::Off)
@@ -558,6 +560,17 @@ mod tests {
         assert!(filter.is_none());
     }
 
+    #[test]
+    fn parse_logging_spec_numeric_log_level() {
+        let (dirs, filter) = parse_logging_spec("3,crate2=5");
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(dirs[0].name, None);
+        assert_eq!(dirs[0].level, LogLevelFilter::Warn);
+        assert_eq!(dirs[1].name, Some("crate2".to_string()));
+        assert_eq!(dirs[1].level, LogLevelFilter::Info);
+        assert!(filter.is_none());
+    }
+
     #[test]
     fn parse_logging_spec_global() {
         // test parse_logging_spec with no crate
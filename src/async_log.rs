@@ -0,0 +1,533 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use {ControlMessage, Log, LogError, LogErrorKind, LogMetadata, LogRecord, OwnedLogRecord};
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+// records are by far the most common message, so they aren't boxed
+#[allow(clippy::large_enum_variant)]
+enum Message {
+    Record(OwnedLogRecord),
+    Control(ControlMessage),
+    // flushes the logger, then replies
+    Flush(SyncSender<()>),
+}
+
+/// A logger which hands records to a background thread.
+///
+/// Records are copied into `OwnedLogRecord`s and queued, and a dedicated
+/// thread passes them on to the wrapped logger in order. Logging threads only
+/// pay for the copy, and only wait for the wrapped logger when the queue is
+/// full. `try_log` doesn't wait, and instead fails with `LogErrorKind::Full`.
+/// It only reports whether the record was queued, not whether the wrapped
+/// logger went on to accept it.
+///
+/// `flush` waits until every record queued before it has been logged, and
+/// then flushes the wrapped logger. Other control messages are passed to the
+/// wrapped logger in order with the records. When the `AsyncLog` is dropped,
+/// for example after being returned by `shutdown_logger`, the remaining
+/// records are logged, the wrapped logger is flushed, and the thread exits.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{AsyncLog, NopLogger};
+///
+/// let logger = AsyncLog::with_capacity(NopLogger, 64);
+/// log::set_boxed_logger(|max_log_level| {
+///     max_log_level.set(log::LogLevelFilter::Info);
+///     Box::new(logger)
+/// }).unwrap();
+/// ```
+pub struct AsyncLog<L> {
+    logger: Arc<L>,
+    sender: Mutex<Option<SyncSender<Message>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<L: Log + 'static> AsyncLog<L> {
+    /// Creates a logger which passes records to `logger` on a background
+    /// thread, queueing up to 1024 of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread can't be spawned.
+    pub fn new(logger: L) -> AsyncLog<L> {
+        AsyncLog::with_capacity(logger, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a logger which passes records to `logger` on a background
+    /// thread, queueing up to `capacity` of them.
+    ///
+    /// A capacity of zero hands each record directly to the thread, waiting
+    /// until it is ready to take it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread can't be spawned.
+    pub fn with_capacity(logger: L, capacity: usize) -> AsyncLog<L> {
+        let logger = Arc::new(logger);
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let thread = {
+            let logger = logger.clone();
+            thread::Builder::new()
+                .name("log-async".into())
+                .spawn(move || run(&*logger, receiver))
+                .unwrap()
+        };
+
+        AsyncLog {
+            logger,
+            sender: Mutex::new(Some(sender)),
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns a reference to the wrapped logger.
+    pub fn get_ref(&self) -> &L {
+        &self.logger
+    }
+}
+
+impl<L> AsyncLog<L> {
+    fn send(&self, message: Message) {
+        let sender = lock(&self.sender).clone();
+        let message = match sender {
+            Some(sender) => match sender.send(message) {
+                Ok(()) => return,
+                Err(err) => err.0,
+            },
+            None => message,
+        };
+        // the background thread is gone
+        if let Message::Record(record) = message {
+            ::record_dropped(record.level());
+        }
+    }
+}
+
+fn run<L: Log>(logger: &L, receiver: Receiver<Message>) {
+    for message in receiver {
+        handle(logger, message);
+    }
+    logger.flush();
+}
+
+fn handle<L: Log>(logger: &L, message: Message) {
+    match message {
+        Message::Record(record) => record.with_record(|record| logger.log(record)),
+        Message::Control(msg) => logger.handle_control(msg),
+        Message::Flush(reply) => {
+            logger.flush();
+            let _ = reply.send(());
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(err) => err.into_inner(),
+    }
+}
+
+impl<L: Log + 'static> Log for AsyncLog<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if self.logger.enabled(record.metadata()) {
+            self.send(Message::Record(record.to_owned()));
+        }
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        if !self.logger.enabled(record.metadata()) {
+            return Ok(());
+        }
+
+        let sender = match *lock(&self.sender) {
+            Some(ref sender) => sender.clone(),
+            None => return Err(LogError::new(LogErrorKind::Closed)),
+        };
+        match sender.try_send(Message::Record(record.to_owned())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(LogError::new(LogErrorKind::Full)),
+            Err(TrySendError::Disconnected(_)) => Err(LogError::new(LogErrorKind::Closed)),
+        }
+    }
+
+    fn flush(&self) {
+        let (reply, done) = mpsc::sync_channel(1);
+        self.send(Message::Flush(reply));
+        let _ = done.recv();
+    }
+
+    fn reopen(&self) {
+        self.send(Message::Control(ControlMessage::Reopen));
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        match msg {
+            ControlMessage::Flush => self.flush(),
+            msg => self.send(Message::Control(msg)),
+        }
+    }
+}
+
+impl<L> Drop for AsyncLog<L> {
+    fn drop(&mut self) {
+        *lock(&self.sender) = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for AsyncLog<L> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("AsyncLog")
+           .field("logger", &self.logger)
+           .finish()
+    }
+}
+
+/// What a `RingLog` does with a record logged while its buffer is full.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Overflow {
+    /// Wait until the background thread makes room for the record.
+    Block,
+    /// Discard the new record.
+    DropNewest,
+    /// Discard the oldest record in the buffer to make room for the new one.
+    DropOldest,
+}
+
+/// A logger which hands records to a background thread through a bounded
+/// buffer, with a choice of what to do when the buffer is full.
+///
+/// Like `AsyncLog`, records are copied and passed on to the wrapped logger in
+/// order by a dedicated thread, and `flush`, control messages and dropping
+/// the `RingLog` behave the same way. Unlike `AsyncLog`, which always waits
+/// for room in its queue, a `RingLog` can be set up to discard records
+/// instead, so a slow disk never holds up the threads doing the logging.
+/// Discarded records are counted in `dropped_records`.
+/// Control messages don't count against the capacity and are never
+/// discarded. Whatever the overflow policy, `try_log` fails with
+/// `LogErrorKind::Full` rather than waiting or discarding anything.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{NopLogger, Overflow, RingLog};
+///
+/// let logger = RingLog::new(NopLogger, 4096, Overflow::DropOldest);
+/// # let _ = logger;
+/// ```
+pub struct RingLog<L> {
+    logger: Arc<L>,
+    ring: Arc<Ring>,
+    overflow: Overflow,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Ring {
+    state: Mutex<RingState>,
+    // signalled when a message is pushed, or the ring is closed
+    not_empty: Condvar,
+    // signalled when a record is popped
+    not_full: Condvar,
+    capacity: usize,
+}
+
+struct RingState {
+    messages: VecDeque<Message>,
+    records: usize,
+    closed: bool,
+}
+
+impl<L: Log + 'static> RingLog<L> {
+    /// Creates a logger which passes records to `logger` on a background
+    /// thread, buffering up to `capacity` of them and handling any more as
+    /// `overflow` says.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero, or if the thread can't be spawned.
+    pub fn new(logger: L, capacity: usize, overflow: Overflow) -> RingLog<L> {
+        assert!(capacity > 0, "RingLog capacity must be nonzero");
+
+        let logger = Arc::new(logger);
+        let ring = Arc::new(Ring {
+            state: Mutex::new(RingState {
+                messages: VecDeque::with_capacity(capacity),
+                records: 0,
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+        });
+        let thread = {
+            let logger = logger.clone();
+            let ring = ring.clone();
+            thread::Builder::new()
+                .name("log-ring".into())
+                .spawn(move || run_ring(&*logger, &ring))
+                .unwrap()
+        };
+
+        RingLog {
+            logger,
+            ring,
+            overflow,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns a reference to the wrapped logger.
+    pub fn get_ref(&self) -> &L {
+        &self.logger
+    }
+
+    /// Returns what is done with records logged while the buffer is full.
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+}
+
+impl<L> RingLog<L> {
+    fn push_record(&self, record: OwnedLogRecord) {
+        let mut state = lock(&self.ring.state);
+        while state.records == self.ring.capacity {
+            match self.overflow {
+                Overflow::Block => {
+                    state = match self.ring.not_full.wait(state) {
+                        Ok(state) => state,
+                        Err(err) => err.into_inner(),
+                    };
+                }
+                Overflow::DropNewest => {
+                    ::record_dropped(record.level());
+                    return;
+                }
+                Overflow::DropOldest => {
+                    let oldest = state.messages.iter().position(|message| {
+                        matches!(*message, Message::Record(_))
+                    });
+                    if let Some(Message::Record(oldest)) =
+                           oldest.and_then(|oldest| state.messages.remove(oldest)) {
+                        ::record_dropped(oldest.level());
+                        state.records -= 1;
+                    }
+                }
+            }
+        }
+        state.messages.push_back(Message::Record(record));
+        state.records += 1;
+        self.ring.not_empty.notify_one();
+    }
+
+    fn try_push_record(&self, record: OwnedLogRecord) -> Result<(), LogError> {
+        let mut state = lock(&self.ring.state);
+        if state.records == self.ring.capacity {
+            return Err(LogError::new(LogErrorKind::Full));
+        }
+        state.messages.push_back(Message::Record(record));
+        state.records += 1;
+        self.ring.not_empty.notify_one();
+        Ok(())
+    }
+
+    fn push(&self, message: Message) {
+        lock(&self.ring.state).messages.push_back(message);
+        self.ring.not_empty.notify_one();
+    }
+}
+
+fn run_ring<L: Log>(logger: &L, ring: &Ring) {
+    loop {
+        let message = {
+            let mut state = lock(&ring.state);
+            while state.messages.is_empty() {
+                if state.closed {
+                    drop(state);
+                    logger.flush();
+                    return;
+                }
+                state = match ring.not_empty.wait(state) {
+                    Ok(state) => state,
+                    Err(err) => err.into_inner(),
+                };
+            }
+            let message = state.messages.pop_front().unwrap();
+            if let Message::Record(_) = message {
+                state.records -= 1;
+                ring.not_full.notify_one();
+            }
+            message
+        };
+        handle(logger, message);
+    }
+}
+
+impl<L: Log + 'static> Log for RingLog<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if self.logger.enabled(record.metadata()) {
+            self.push_record(record.to_owned());
+        }
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        if self.logger.enabled(record.metadata()) {
+            self.try_push_record(record.to_owned())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&self) {
+        let (reply, done) = mpsc::sync_channel(1);
+        self.push(Message::Flush(reply));
+        let _ = done.recv();
+    }
+
+    fn reopen(&self) {
+        self.push(Message::Control(ControlMessage::Reopen));
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        match msg {
+            ControlMessage::Flush => self.flush(),
+            msg => self.push(Message::Control(msg)),
+        }
+    }
+}
+
+impl<L> Drop for RingLog<L> {
+    fn drop(&mut self) {
+        lock(&self.ring.state).closed = true;
+        self.ring.not_empty.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for RingLog<L> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RingLog")
+           .field("logger", &self.logger)
+           .field("overflow", &self.overflow)
+           .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::{String, ToString};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    use std::thread;
+
+    use {ControlMessage, Log, LogError, LogErrorKind, LogLevel, LogMetadata, LogRecord};
+    use super::{AsyncLog, Overflow, RingLog};
+
+    // the gate is held by the test to stall the background thread
+    struct Collect(Arc<Mutex<Vec<String>>>, Arc<Mutex<()>>);
+
+    impl Log for Collect {
+        fn enabled(&self, metadata: &LogMetadata) -> bool {
+            metadata.target() != "skip"
+        }
+
+        fn log(&self, record: &LogRecord) {
+            self.0.lock().unwrap().push(record.args().to_string());
+            if record.target() == "wait" {
+                drop(self.1.lock());
+            }
+        }
+
+        fn flush(&self) {
+            self.0.lock().unwrap().push("flush".to_string());
+        }
+
+        fn reopen(&self) {
+            self.0.lock().unwrap().push("reopen".to_string());
+        }
+    }
+
+    #[test]
+    fn test_async_log() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = AsyncLog::with_capacity(Collect(lines.clone(), Default::default()), 2);
+        for i in 0..5 {
+            info_to!(&logger, "{}", i);
+        }
+        info_to!(&logger, target: "skip", "x");
+        logger.handle_control(ControlMessage::Reopen);
+        logger.flush();
+        assert_eq!(vec!["0", "1", "2", "3", "4", "reopen", "flush"],
+                   *lines.lock().unwrap());
+
+        info_to!(&logger, "5");
+        drop(logger);
+        assert_eq!(vec!["5", "flush"], lines.lock().unwrap()[7..].to_vec());
+    }
+
+    fn ring_overflow(overflow: Overflow) -> Vec<String> {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let gate = Arc::new(Mutex::new(()));
+        let logger = RingLog::new(Collect(lines.clone(), gate.clone()), 2, overflow);
+        let guard = gate.lock().unwrap();
+        info_to!(&logger, target: "wait", "0");
+        while lines.lock().unwrap().is_empty() {
+            thread::yield_now();
+        }
+        for i in 1..4 {
+            info_to!(&logger, "{}", i);
+        }
+        let record = LogRecord::builder().level(LogLevel::Info).build();
+        assert_eq!(Err(LogError::new(LogErrorKind::Full)), logger.try_log(&record));
+        drop(guard);
+        logger.flush();
+        let lines = lines.lock().unwrap().clone();
+        lines
+    }
+
+    #[test]
+    fn test_ring_log() {
+        let dropped = ::dropped_records().level(LogLevel::Info);
+        assert_eq!(vec!["0", "1", "2", "flush"], ring_overflow(Overflow::DropNewest));
+        assert_eq!(vec!["0", "2", "3", "flush"], ring_overflow(Overflow::DropOldest));
+        assert!(::dropped_records().level(LogLevel::Info) >= dropped + 2);
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = RingLog::new(Collect(lines.clone(), Default::default()), 1, Overflow::Block);
+        for i in 0..3 {
+            info_to!(&logger, "{}", i);
+        }
+        drop(logger);
+        assert_eq!(vec!["0", "1", "2", "flush"], *lines.lock().unwrap());
+    }
+}
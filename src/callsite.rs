@@ -0,0 +1,466 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Per-callsite caching of whether the logger is interested in a log statement.
+//
+// Each log statement without an explicit target has a static Callsite, which
+// is added to a global linked list the first time the statement runs with a
+// logger installed. The callsite then remembers whether the logger and the
+// per-target levels enabled its level and target, so later runs of a disabled
+// statement return after a couple of plain loads.
+//
+// A cached answer is tagged with the value of EPOCH at the time it was worked
+// out, and is stale once EPOCH changes. Anything which may change the answer,
+// such as installing a logger or setting a per-target level, bumps EPOCH.
+// Tagging rather than resetting each callsite means a thread which is still
+// working out an answer when EPOCH is bumped can't store a stale one.
+//
+// Statements with an explicit target aren't cached, as the target may differ
+// from one run to the next. Neither are runs of a statement at a different
+// level than its first run, or runs while with_logger is in use anywhere.
+//
+// A callsite may also be forced on or muted through set_forced, which bumps
+// EPOCH after storing the override. Forced callsites are never cached, so
+// their runs always take the slow path, but there should only be a handful.
+
+use core::fmt;
+use core::sync::atomic::AtomicUsize;
+#[cfg(not(feature = "freestanding"))]
+use core::sync::atomic::Ordering;
+
+use {LogLevel, LogLocation, kv};
+
+#[cfg(not(feature = "freestanding"))]
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+// The most recently registered callsite, as a pointer.
+#[cfg(not(feature = "freestanding"))]
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+
+// The low bits of a callsite's interest, above which is the epoch. The bits
+// are 0 while the interest is unknown.
+#[cfg(not(feature = "freestanding"))]
+const DISABLED: usize = 1;
+#[cfg(not(feature = "freestanding"))]
+const ENABLED: usize = 2;
+#[cfg(not(feature = "freestanding"))]
+const STATE_BITS: usize = 2;
+#[cfg(not(feature = "freestanding"))]
+const STATE_MASK: usize = (1 << STATE_BITS) - 1;
+
+// The values of a callsite's override.
+#[cfg(not(feature = "freestanding"))]
+const FORCE_NONE: usize = 0;
+#[cfg(not(feature = "freestanding"))]
+const FORCE_ENABLED: usize = 1;
+#[cfg(not(feature = "freestanding"))]
+const FORCE_MUTED: usize = 2;
+
+/// A log statement.
+///
+/// Every log statement without an explicit target has a `Callsite`, which is
+/// listed by `callsites` once the statement has run with a logger installed.
+/// A single statement may then be turned on or off with `set_forced`, without
+/// changing the levels of anything else.
+///
+/// # Warning
+///
+/// The fields of this struct are public so that they may be initialized by the
+/// logging macros. They are subject to change at any time and should never be
+/// accessed directly.
+#[derive(Debug)]
+pub struct Callsite {
+    #[doc(hidden)]
+    pub __loc: LogLocation,
+    #[doc(hidden)]
+    pub __state: CallsiteState,
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[derive(Debug)]
+#[cfg_attr(feature = "freestanding", allow(dead_code))]
+pub struct CallsiteState {
+    interest: AtomicUsize,
+    // the level of the first run, or 0 until the callsite is registered
+    level: AtomicUsize,
+    // the next callsite in the list, as a pointer
+    next: AtomicUsize,
+    // one of the FORCE_ values
+    forced: AtomicUsize,
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[allow(clippy::declare_interior_mutable_const)]
+pub const __CALLSITE_STATE_INIT: CallsiteState = CallsiteState {
+    interest: AtomicUsize::new(0),
+    level: AtomicUsize::new(0),
+    next: AtomicUsize::new(0),
+    forced: AtomicUsize::new(0),
+};
+
+impl Callsite {
+    /// Returns the module path of the log statement.
+    pub fn module_path(&self) -> &'static str {
+        self.__loc.__module_path
+    }
+
+    /// Returns the file containing the log statement.
+    pub fn file(&self) -> &'static str {
+        self.__loc.__file
+    }
+
+    /// Returns the line of the log statement.
+    pub fn line(&self) -> u32 {
+        self.__loc.__line
+    }
+
+    /// Returns the level the log statement first ran at with a logger
+    /// installed, or `None` if it hasn't yet.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn level(&self) -> Option<LogLevel> {
+        LogLevel::from_usize(self.__state.level.load(Ordering::SeqCst))
+    }
+
+    /// Returns the override set with `set_forced`, if any.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn forced(&self) -> Option<bool> {
+        match self.__state.forced.load(Ordering::SeqCst) {
+            FORCE_ENABLED => Some(true),
+            FORCE_MUTED => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Forces the log statement on or off, regardless of its level.
+    ///
+    /// With `Some(true)`, the statement's records are passed to the logger
+    /// even if the maximum log level or a per-target level would disable
+    /// them. The logger still sees them, and one which checks `enabled` in
+    /// `log` may discard them, and statements above the static maximum level
+    /// are compiled out. With `Some(false)`, the statement logs nothing, even
+    /// through `with_logger`. `None` removes the override.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn set_forced(&self, forced: Option<bool>) {
+        let forced = match forced {
+            Some(true) => FORCE_ENABLED,
+            Some(false) => FORCE_MUTED,
+            None => FORCE_NONE,
+        };
+        self.__state.forced.store(forced, Ordering::SeqCst);
+        invalidate();
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Returns false if the statement is known to be disabled at level.
+    #[cfg(not(feature = "freestanding"))]
+    #[doc(hidden)]
+    #[inline]
+    pub fn __interested(&'static self, level: LogLevel) -> bool {
+        let interest = self.__state.interest.load(Ordering::Relaxed);
+        // the cached interest only holds for the level it was worked out at
+        let cached = interest >> STATE_BITS == epoch() &&
+                     level as usize == self.__state.level.load(Ordering::Relaxed);
+        match interest & STATE_MASK {
+            ENABLED if cached => level <= ::max_log_level(),
+            DISABLED if cached && !::scoped_loggers_active() => false,
+            _ => self.interest(level),
+        }
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    #[cold]
+    fn interest(&'static self, level: LogLevel) -> bool {
+        if let Some(forced) = self.forced() {
+            return forced;
+        }
+        if ::scoped_loggers_active() || !::logger_installed() {
+            return level <= ::max_log_level();
+        }
+
+        match self.__state.level.compare_exchange(0, level as usize,
+                                                  Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => self.register(),
+            Err(registered) if registered != level as usize => {
+                return level <= ::max_log_level();
+            }
+            Err(_) => {}
+        }
+
+        self.refresh(level) && level <= ::max_log_level()
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    fn refresh(&'static self, level: LogLevel) -> bool {
+        // the epoch is read first, so that a racing set_forced leaves the
+        // stored interest stale
+        let epoch = epoch();
+        if let Some(forced) = self.forced() {
+            return forced;
+        }
+        let enabled = ::__enabled(level, self.__loc.__module_path);
+        let state = if enabled { ENABLED } else { DISABLED };
+        self.__state.interest.store(epoch << STATE_BITS | state, Ordering::Relaxed);
+        enabled
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Registration needs compare-and-swap, and without per-target levels
+    // there is little to cache.
+    #[cfg(feature = "freestanding")]
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __interested(&'static self, level: LogLevel) -> bool {
+        level <= ::max_log_level()
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    #[doc(hidden)]
+    pub fn __log(&'static self, level: LogLevel, args: fmt::Arguments,
+                 key_values: &[(&str, kv::Value)]) {
+        if self.is_forced_enabled() {
+            ::log_enabled(level, self.__loc.__module_path, &self.__loc, args, key_values)
+        } else {
+            ::__log(level, self.__loc.__module_path, &self.__loc, args, key_values)
+        }
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    fn is_forced_enabled(&self) -> bool {
+        self.__state.forced.load(Ordering::Relaxed) == FORCE_ENABLED
+    }
+
+    #[cfg(feature = "freestanding")]
+    #[inline(always)]
+    fn is_forced_enabled(&self) -> bool {
+        false
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    fn register(&'static self) {
+        let ptr = self as *const Callsite as usize;
+        loop {
+            let head = HEAD.load(Ordering::SeqCst);
+            self.__state.next.store(head, Ordering::SeqCst);
+            if HEAD.compare_exchange(head, ptr, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns an iterator over the log statements which have run with a logger
+/// installed.
+///
+/// Only statements without an explicit target are listed. They are listed
+/// most recently registered first.
+///
+/// # Examples
+///
+/// ```rust
+/// // turn on the debug statements at line 120 of src/db.rs
+/// for callsite in log::callsites() {
+///     if callsite.file() == "src/db.rs" && callsite.line() == 120 {
+///         callsite.set_forced(Some(true));
+///     }
+/// }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+pub fn callsites() -> Callsites {
+    Callsites { next: HEAD.load(Ordering::SeqCst) }
+}
+
+/// An iterator over log statements, returned by `callsites`.
+#[cfg(not(feature = "freestanding"))]
+#[derive(Clone, Debug)]
+pub struct Callsites {
+    next: usize,
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl Iterator for Callsites {
+    type Item = &'static Callsite;
+
+    fn next(&mut self) -> Option<&'static Callsite> {
+        if self.next == 0 {
+            return None;
+        }
+
+        // callsites are statics, and are never removed from the list
+        let callsite = unsafe { &*(self.next as *const Callsite) };
+        self.next = callsite.__state.next.load(Ordering::SeqCst);
+        Some(callsite)
+    }
+}
+
+#[cfg(not(feature = "freestanding"))]
+fn epoch() -> usize {
+    EPOCH.load(Ordering::Relaxed) & (!0 >> STATE_BITS)
+}
+
+// Marks every cached interest as stale.
+#[cfg(not(feature = "freestanding"))]
+pub fn invalidate() {
+    EPOCH.fetch_add(1, Ordering::SeqCst);
+}
+
+#[cfg(feature = "freestanding")]
+#[inline(always)]
+pub fn invalidate() {}
+
+/// Asks the logger again whether each log statement is enabled.
+///
+/// The logging macros remember whether the logger is interested in each log
+/// statement, as described for `Log::enabled`. A logger which changes its
+/// filtering while it is installed must call this afterwards, or statements
+/// it disabled before the change will stay disabled.
+///
+/// Reloading a `Filter` made global with `Filter::global`, and `log::control`
+/// with `ControlMessage::SetLevel`, call this themselves.
+///
+/// # Examples
+///
+/// ```rust
+/// // after changing the installed logger's configuration
+/// log::rebuild_interest_cache();
+/// ```
+#[cfg(not(feature = "freestanding"))]
+pub fn rebuild_interest_cache() {
+    invalidate();
+    // A scoped logger on another thread doesn't matter here, since the
+    // statements check for those themselves before using a DISABLED entry.
+    if ::scoped_logger().is_some() || !::logger_installed() {
+        // worked out again on the next run of each statement instead
+        return;
+    }
+
+    for callsite in callsites() {
+        if let Some(level) = callsite.level() {
+            callsite.refresh(level);
+        }
+    }
+}
+
+/// Asks the logger again whether each log statement is enabled.
+///
+/// Log statements aren't cached when freestanding, so this does nothing.
+#[cfg(feature = "freestanding")]
+pub fn rebuild_interest_cache() {}
+
+#[cfg(all(test, not(feature = "freestanding")))]
+mod tests {
+    use core::sync::atomic::Ordering;
+
+    use {LogLevel, LogLevelFilter};
+    use super::{Callsite, DISABLED, ENABLED, STATE_MASK};
+
+    fn state(callsite: &Callsite) -> usize {
+        callsite.__state.interest.load(Ordering::Relaxed) & STATE_MASK
+    }
+
+    #[test]
+    fn test_interest() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        static CALLSITE: Callsite = Callsite {
+            __loc: ::LogLocation {
+                __module_path: "log::callsite::test_interest",
+                __file: file!(),
+                __line: line!(),
+            },
+            __state: super::__CALLSITE_STATE_INIT,
+        };
+        ::set_target_max_level(CALLSITE.__loc.__module_path, LogLevelFilter::Warn);
+        assert!(!CALLSITE.__interested(LogLevel::Info));
+        assert_eq!(DISABLED, state(&CALLSITE));
+        assert!(!CALLSITE.__interested(LogLevel::Info));
+        // not cached for other levels
+        assert!(CALLSITE.__interested(LogLevel::Warn));
+
+        ::remove_target_max_level(CALLSITE.__loc.__module_path);
+        assert!(CALLSITE.__interested(LogLevel::Info));
+        assert_eq!(ENABLED, state(&CALLSITE));
+    }
+
+    #[test]
+    fn test_rebuild_interest_cache() {
+        ::test::init().unwrap();
+
+        static CALLSITE: Callsite = Callsite {
+            __loc: ::LogLocation {
+                __module_path: "log::callsite::test_rebuild_interest_cache",
+                __file: file!(),
+                __line: line!(),
+            },
+            __state: super::__CALLSITE_STATE_INIT,
+        };
+        assert!(CALLSITE.__interested(LogLevel::Debug));
+        assert_eq!(ENABLED, state(&CALLSITE));
+
+        // refreshed eagerly, not on the next run
+        ::set_target_max_level(CALLSITE.__loc.__module_path, LogLevelFilter::Info);
+        super::rebuild_interest_cache();
+        ::remove_target_max_level(CALLSITE.__loc.__module_path);
+        assert_eq!(DISABLED, state(&CALLSITE));
+
+        // and enabled again once the per-target level is gone
+        super::rebuild_interest_cache();
+        assert_eq!(ENABLED, state(&CALLSITE));
+        assert!(CALLSITE.__interested(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_forced() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        static CALLSITE: Callsite = Callsite {
+            __loc: ::LogLocation {
+                __module_path: "log::callsite::test_forced",
+                __file: file!(),
+                __line: line!(),
+            },
+            __state: super::__CALLSITE_STATE_INIT,
+        };
+        ::set_target_max_level(CALLSITE.module_path(), LogLevelFilter::Warn);
+        assert!(!CALLSITE.__interested(LogLevel::Info));
+        assert_eq!(Some(LogLevel::Info), CALLSITE.level());
+        let listed = super::callsites().find(|c| c.module_path() == CALLSITE.module_path());
+        assert_eq!(Some(CALLSITE.line()), listed.map(|c| c.line()));
+
+        CALLSITE.set_forced(Some(true));
+        assert_eq!(Some(true), CALLSITE.forced());
+        assert!(CALLSITE.__interested(LogLevel::Info));
+        CALLSITE.__log(LogLevel::Info, format_args!("forced"), &[]);
+        assert_eq!(1, ::test::drain().len());
+
+        CALLSITE.set_forced(Some(false));
+        ::remove_target_max_level(CALLSITE.module_path());
+        assert!(!CALLSITE.__interested(LogLevel::Info));
+        assert!(!CALLSITE.__interested(LogLevel::Error));
+
+        CALLSITE.set_forced(None);
+        assert!(CALLSITE.__interested(LogLevel::Info));
+    }
+}
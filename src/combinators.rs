@@ -0,0 +1,703 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Loggers built out of other loggers.
+//!
+//! The types in this module implement `Log` by forwarding records to one or
+//! more inner loggers, so that a logging setup can be assembled from small
+//! pieces rather than written as a single custom `Log` implementation.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use log::NopLogger;
+//! use log::combinators::Tee;
+//!
+//! // every record goes to both loggers
+//! let logger = Tee(NopLogger, NopLogger);
+//! # let _ = logger;
+//! ```
+
+use {ControlMessage, Log, LogError, LogLevel, LogMetadata, LogRecord};
+#[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+use filter::Filter;
+#[cfg(not(feature = "freestanding"))]
+use OwnedLogRecord;
+#[cfg(not(feature = "freestanding"))]
+use std::sync::{Mutex, MutexGuard};
+#[cfg(not(feature = "freestanding"))]
+use std::time::{Duration, Instant};
+
+/// Creates a logger which calls `f` with every record.
+///
+/// Every record is enabled, so `f` should do any filtering itself.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// fn main() {
+///     let logger = log::from_fn(|record| println!("{}: {}", record.level(), record.args()));
+///     info_to!(&logger, "hello");
+/// }
+/// ```
+pub fn from_fn<F>(f: F) -> FromFn<F>
+    where F: Fn(&LogRecord) + Sync + Send
+{
+    FromFn(f)
+}
+
+/// A logger which calls a function with every record.
+///
+/// This is returned by `from_fn`.
+#[derive(Copy, Clone, Debug)]
+pub struct FromFn<F>(F);
+
+impl<F> Log for FromFn<F>
+    where F: Fn(&LogRecord) + Sync + Send
+{
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        (self.0)(record)
+    }
+}
+
+/// A logger which forwards every record to two loggers.
+///
+/// A record is enabled if it is enabled for either logger. Each logger still
+/// does its own filtering, so a record enabled for only one of them is only
+/// logged by that one. Longer chains can be built by nesting, as in
+/// `Tee(a, Tee(b, c))`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tee<A, B>(pub A, pub B);
+
+impl<A: Log, B: Log> Log for Tee<A, B> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.0.enabled(metadata) || self.1.enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        self.0.log(record);
+        self.1.log(record);
+    }
+
+    // the record goes to both loggers even if the first fails
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        let first = self.0.try_log(record);
+        let second = self.1.try_log(record);
+        first.and(second)
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+        self.1.flush();
+    }
+
+    fn reopen(&self) {
+        self.0.reopen();
+        self.1.reopen();
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        self.0.handle_control(msg);
+        self.1.handle_control(msg);
+    }
+}
+
+/// A logger which only forwards the records accepted by a `Filter`.
+///
+/// Wrapping one branch of a `Tee` in a `Filtered` sends that logger a subset
+/// of the records, for example only errors to a paging service while every
+/// record goes to a file. A record is enabled if the filter accepts it and
+/// the wrapped logger has it enabled.
+///
+/// This is available wherever the `filter` module is.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::NopLogger;
+/// use log::combinators::{Filtered, Tee};
+///
+/// let pager = Filtered::new("error,my_app::billing=warn".parse().unwrap(), NopLogger);
+/// let logger = Tee(pager, NopLogger);
+/// # let _ = logger;
+/// ```
+#[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+#[derive(Clone, Debug)]
+pub struct Filtered<L> {
+    filter: Filter,
+    logger: L,
+}
+
+#[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+impl<L: Log> Filtered<L> {
+    /// Creates a logger which forwards the records accepted by `filter` to
+    /// `logger`.
+    pub fn new(filter: Filter, logger: L) -> Filtered<L> {
+        Filtered {
+            filter,
+            logger,
+        }
+    }
+
+    /// Returns a reference to the filter.
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Returns a reference to the wrapped logger.
+    pub fn get_ref(&self) -> &L {
+        &self.logger
+    }
+
+    /// Unwraps this `Filtered`, returning the wrapped logger.
+    pub fn into_inner(self) -> L {
+        self.logger
+    }
+}
+
+#[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+impl<L: Log> Log for Filtered<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.filter.enabled(metadata) && self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if self.filter.matches(record) {
+            self.logger.log(record);
+        }
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        if self.filter.matches(record) {
+            self.logger.try_log(record)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+
+    fn reopen(&self) {
+        self.logger.reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        self.logger.handle_control(msg)
+    }
+}
+
+/// A logger which sends records to one of two loggers depending on their
+/// level.
+///
+/// Records at the split level or more severe go to the first logger, and the
+/// rest to the second. Routing more than two ranges of levels is done by
+/// nesting, as in `LevelRouter::new(Warn, a, LevelRouter::new(Debug, b, c))`.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{LogLevel, NopLogger};
+/// use log::combinators::LevelRouter;
+///
+/// // errors and warnings go to one logger, everything else to the other
+/// let logger = LevelRouter::new(LogLevel::Warn, NopLogger, NopLogger);
+/// # let _ = logger;
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct LevelRouter<A, B> {
+    split: LogLevel,
+    severe: A,
+    rest: B,
+}
+
+impl<A: Log, B: Log> LevelRouter<A, B> {
+    /// Creates a logger which sends records at `split` or more severe to
+    /// `severe`, and all others to `rest`.
+    pub fn new(split: LogLevel, severe: A, rest: B) -> LevelRouter<A, B> {
+        LevelRouter {
+            split,
+            severe,
+            rest,
+        }
+    }
+
+    /// Returns the least severe level sent to the first logger.
+    pub fn split(&self) -> LogLevel {
+        self.split
+    }
+}
+
+impl<A: Log, B: Log> Log for LevelRouter<A, B> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        if metadata.level() <= self.split {
+            self.severe.enabled(metadata)
+        } else {
+            self.rest.enabled(metadata)
+        }
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if record.level() <= self.split {
+            self.severe.log(record)
+        } else {
+            self.rest.log(record)
+        }
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        if record.level() <= self.split {
+            self.severe.try_log(record)
+        } else {
+            self.rest.try_log(record)
+        }
+    }
+
+    fn flush(&self) {
+        self.severe.flush();
+        self.rest.flush();
+    }
+
+    fn reopen(&self) {
+        self.severe.reopen();
+        self.rest.reopen();
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        self.severe.handle_control(msg);
+        self.rest.handle_control(msg);
+    }
+}
+
+/// A logger which rewrites records before passing them on.
+///
+/// Each record is copied into an `OwnedLogRecord`, which the function may
+/// change in place, for example to rename or prefix its target, or to add
+/// key-value pairs, before it is logged by the wrapped logger. This is useful
+/// for fitting the records of a third-party crate into an application's own
+/// naming scheme.
+///
+/// Records are only rewritten when they are logged, since the function needs
+/// an owned record to change. `enabled` therefore always returns true, and
+/// it is the wrapped logger which filters the rewritten records in `log`.
+///
+/// This is only available with the standard library.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::NopLogger;
+/// use log::combinators::Map;
+///
+/// let logger = Map::new(NopLogger, |record| {
+///     if record.target().starts_with("hyper") {
+///         let target = format!("vendor::{}", record.target());
+///         record.set_target(&target);
+///     }
+///     record.key_values_mut().push(("service".to_string(), "billing".to_string()));
+/// });
+/// # let _ = logger;
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[derive(Clone, Debug)]
+pub struct Map<L, F> {
+    logger: L,
+    f: F,
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl<L, F> Map<L, F>
+    where L: Log,
+          F: Fn(&mut OwnedLogRecord) + Sync + Send
+{
+    /// Creates a logger which rewrites records with `f` before passing them
+    /// to `logger`.
+    pub fn new(logger: L, f: F) -> Map<L, F> {
+        Map {
+            logger,
+            f,
+        }
+    }
+
+    /// Returns a reference to the wrapped logger.
+    pub fn get_ref(&self) -> &L {
+        &self.logger
+    }
+
+    /// Unwraps this `Map`, returning the wrapped logger.
+    pub fn into_inner(self) -> L {
+        self.logger
+    }
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl<L, F> Log for Map<L, F>
+    where L: Log,
+          F: Fn(&mut OwnedLogRecord) + Sync + Send
+{
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let mut record = record.to_owned();
+        (self.f)(&mut record);
+        record.with_record(|record| self.logger.log(record));
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        let mut record = record.to_owned();
+        (self.f)(&mut record);
+        record.with_record(|record| self.logger.try_log(record))
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+
+    fn reopen(&self) {
+        self.logger.reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        self.logger.handle_control(msg)
+    }
+}
+
+/// A logger which collapses runs of identical records.
+///
+/// The first record of a run is passed to the wrapped logger as usual. Any
+/// records which follow it with the same level, target, message and
+/// key-value pairs are held back and counted, and replaced by a single record
+/// with the message `last message repeated N times`, in the manner of syslog.
+/// That record is logged when a different record arrives, when the logger is
+/// flushed, or, if the run goes on, with the first repeat after `timeout` has
+/// passed since the run started or was last reported.
+///
+/// The repeat record has the level, target and location of the repeated
+/// record, and the timestamp and thread of its last repeat, but no key-value
+/// pairs.
+///
+/// This is only available with the standard library.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use log::NopLogger;
+/// use log::combinators::Dedup;
+///
+/// let logger = Dedup::new(NopLogger, Duration::from_secs(30));
+/// # let _ = logger;
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[derive(Debug)]
+pub struct Dedup<L> {
+    logger: L,
+    timeout: Duration,
+    run: Mutex<Option<Run>>,
+}
+
+#[cfg(not(feature = "freestanding"))]
+#[derive(Debug)]
+struct Run {
+    first: OwnedLogRecord,
+    // the last repeat and the number of repeats, if there are any
+    repeats: Option<(OwnedLogRecord, usize)>,
+    since: Instant,
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl<L: Log> Dedup<L> {
+    /// Creates a logger which collapses runs of identical records before
+    /// passing them to `logger`, reporting long runs every `timeout`.
+    pub fn new(logger: L, timeout: Duration) -> Dedup<L> {
+        Dedup {
+            logger,
+            timeout,
+            run: Mutex::new(None),
+        }
+    }
+
+    /// Returns a reference to the wrapped logger.
+    pub fn get_ref(&self) -> &L {
+        &self.logger
+    }
+
+    /// Unwraps this `Dedup`, returning the wrapped logger.
+    ///
+    /// Any held back repeats are reported first.
+    pub fn into_inner(self) -> L {
+        self.report();
+        self.logger
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Run>> {
+        match self.run.lock() {
+            Ok(run) => run,
+            Err(err) => err.into_inner(),
+        }
+    }
+
+    // Adds record to the current run, returning the repeat record to log, if
+    // any, and the record itself if it starts a new run. The caller logs
+    // them, so that the lock isn't held while the wrapped logger runs.
+    fn dedup(&self, record: &LogRecord) -> (Option<OwnedLogRecord>, Option<OwnedLogRecord>) {
+        let record = record.to_owned();
+        let mut run = self.lock();
+
+        if let Some(ref mut run) = *run {
+            if same(&run.first, &record) {
+                let count = run.repeats.as_ref().map_or(0, |&(_, count)| count) + 1;
+                run.repeats = Some((record, count));
+                if run.since.elapsed() < self.timeout {
+                    return (None, None);
+                }
+                run.since = Instant::now();
+                return (run.repeats.take().map(repeated), None);
+            }
+        }
+
+        let previous = run.take();
+        *run = Some(Run {
+            first: record.clone(),
+            repeats: None,
+            since: Instant::now(),
+        });
+        (previous.and_then(|run| run.repeats).map(repeated), Some(record))
+    }
+
+    fn report(&self) {
+        let repeats = match *self.lock() {
+            Some(ref mut run) => {
+                run.since = Instant::now();
+                run.repeats.take()
+            }
+            None => None,
+        };
+        if let Some(repeats) = repeats {
+            repeated(repeats).with_record(|record| self.logger.log(record));
+        }
+    }
+}
+
+#[cfg(not(feature = "freestanding"))]
+fn same(a: &OwnedLogRecord, b: &OwnedLogRecord) -> bool {
+    a.level() == b.level() && a.target() == b.target() && a.message() == b.message() &&
+        a.key_values() == b.key_values()
+}
+
+#[cfg(not(feature = "freestanding"))]
+fn repeated((mut record, count): (OwnedLogRecord, usize)) -> OwnedLogRecord {
+    let message = format!("last message repeated {} times", count);
+    record.set_message(&message);
+    record.key_values_mut().clear();
+    record
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl<L: Log> Log for Dedup<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let (repeats, record) = self.dedup(record);
+        if let Some(repeats) = repeats {
+            repeats.with_record(|record| self.logger.log(record));
+        }
+        if let Some(record) = record {
+            record.with_record(|record| self.logger.log(record));
+        }
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        let (repeats, record) = self.dedup(record);
+        if let Some(repeats) = repeats {
+            repeats.with_record(|record| self.logger.log(record));
+        }
+        match record {
+            Some(record) => record.with_record(|record| self.logger.try_log(record)),
+            None => Ok(()),
+        }
+    }
+
+    fn flush(&self) {
+        self.report();
+        self.logger.flush()
+    }
+
+    fn reopen(&self) {
+        self.logger.reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        if msg == ControlMessage::Flush {
+            self.report();
+        }
+        self.logger.handle_control(msg)
+    }
+}
+
+#[cfg(all(test, not(feature = "freestanding")))]
+mod tests {
+    use std::string::{String, ToString};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use std::vec::Vec;
+
+    use {Log, LogLevel, LogMetadata, LogRecord};
+    use super::{from_fn, Dedup, Filtered, LevelRouter, Map, Tee};
+
+    #[derive(Clone)]
+    struct Collect(&'static str, LogLevel, Arc<Mutex<Vec<String>>>);
+
+    impl Log for Collect {
+        fn enabled(&self, metadata: &LogMetadata) -> bool {
+            metadata.level() <= self.1
+        }
+
+        fn log(&self, record: &LogRecord) {
+            if self.enabled(record.metadata()) {
+                self.2.lock().unwrap().push(format!("{} {}", self.0, record.args()));
+            }
+        }
+
+        fn flush(&self) {
+            self.2.lock().unwrap().push(format!("{} flush", self.0));
+        }
+    }
+
+    #[test]
+    fn test_tee() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = Tee(Collect("a", LogLevel::Error, lines.clone()),
+                         Collect("b", LogLevel::Info, lines.clone()));
+
+        let metadata = |level| LogMetadata::builder().level(level).target("t").build();
+        assert!(logger.enabled(&metadata(LogLevel::Info)));
+        assert!(!logger.enabled(&metadata(LogLevel::Debug)));
+
+        error_to!(&logger, "x");
+        info_to!(&logger, "y");
+        logger.flush();
+        assert_eq!(vec!["a x", "b x", "b y", "a flush", "b flush"]
+                       .into_iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                   *lines.lock().unwrap());
+    }
+
+    #[test]
+    fn test_filtered() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let filter = "warn,app::db=debug".parse().unwrap();
+        let logger = Filtered::new(filter, Collect("f", LogLevel::Trace, lines.clone()));
+
+        let metadata = |target| {
+            LogMetadata::builder().level(LogLevel::Info).target(target).build()
+        };
+        assert!(logger.enabled(&metadata("app::db")));
+        assert!(!logger.enabled(&metadata("app::net")));
+
+        info_to!(&logger, target: "app::db", "a");
+        info_to!(&logger, target: "app::net", "b");
+        warn_to!(&logger, target: "app::net", "c");
+        assert_eq!(vec!["f a".to_string(), "f c".to_string()], *lines.lock().unwrap());
+    }
+
+    #[test]
+    fn test_level_router() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = LevelRouter::new(LogLevel::Warn,
+                                      Collect("err", LogLevel::Trace, lines.clone()),
+                                      Collect("out", LogLevel::Info, lines.clone()));
+
+        let metadata = |level| LogMetadata::builder().level(level).target("t").build();
+        assert!(logger.enabled(&metadata(LogLevel::Error)));
+        assert!(logger.enabled(&metadata(LogLevel::Info)));
+        assert!(!logger.enabled(&metadata(LogLevel::Debug)));
+
+        error_to!(&logger, "a");
+        warn_to!(&logger, "b");
+        info_to!(&logger, "c");
+        debug_to!(&logger, "d");
+        assert_eq!(vec!["err a", "err b", "out c"]
+                       .into_iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                   *lines.lock().unwrap());
+    }
+
+    #[test]
+    fn test_map() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = Map::new(Filtered::new("vendor=info".parse().unwrap(),
+                                            Collect("m", LogLevel::Trace, lines.clone())),
+                              |record| {
+                                  let target = format!("vendor::{}", record.target());
+                                  record.set_target(&target);
+                                  let message = format!("{} [{}]", record.message(), target);
+                                  record.set_message(&message);
+                              });
+
+        let metadata = LogMetadata::builder().level(LogLevel::Debug).target("hyper").build();
+        assert!(logger.enabled(&metadata));
+
+        info_to!(&logger, target: "hyper", "a");
+        debug_to!(&logger, target: "hyper", "b");
+        assert_eq!(vec!["m a [vendor::hyper]".to_string()], *lines.lock().unwrap());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = Dedup::new(Collect("d", LogLevel::Trace, lines.clone()),
+                                Duration::from_secs(3600));
+
+        for _ in 0..3 {
+            warn_to!(&logger, "flapping");
+        }
+        warn_to!(&logger, "recovered");
+        warn_to!(&logger, "recovered");
+        logger.flush();
+        info_to!(&logger, "single");
+        info_to!(&logger, "other");
+        assert_eq!(vec!["d flapping", "d last message repeated 2 times", "d recovered",
+                        "d last message repeated 1 times", "d flush", "d single", "d other"]
+                       .into_iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                   *lines.lock().unwrap());
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logger = Dedup::new(Collect("d", LogLevel::Trace, lines.clone()),
+                                Duration::from_secs(0));
+        for _ in 0..3 {
+            warn_to!(&logger, "flapping");
+        }
+        assert_eq!(vec!["d flapping", "d last message repeated 1 times",
+                        "d last message repeated 1 times"]
+                       .into_iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                   *lines.lock().unwrap());
+    }
+
+    #[test]
+    fn test_from_fn() {
+        let lines = Mutex::new(Vec::new());
+        let logger = from_fn(|record| lines.lock().unwrap().push(record.args().to_string()));
+        info_to!(&logger, "a {}", 1);
+        trace_to!(&logger, "b");
+        assert_eq!(vec!["a 1".to_string(), "b".to_string()], *lines.lock().unwrap());
+    }
+}
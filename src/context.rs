@@ -0,0 +1,650 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The diagnostic context of each thread, which is attached to every record
+// logged on it.
+//
+// The mapped context is kept in insertion order, so loggers see its pairs in
+// a predictable order. Pairs passed to the macros come first, and a context
+// pair is left out if the record already has a pair with the same key.
+//
+// The scope stack is kept already joined, along with the length of the joined
+// string below each scope, so that records can borrow it without any work.
+
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use std::marker::PhantomData;
+use std::mem;
+use std::process;
+use std::string::String;
+use std::thread::{self, Thread};
+use std::vec::Vec;
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "futures")]
+use futures::{Future, Poll, Stream};
+
+use TraceId;
+use kv::Value;
+
+const SCOPE_SEPARATOR: &str = " > ";
+
+// The process id, or 0 if it hasn't been looked up yet. Every thread would
+// store the same value, so Relaxed is enough.
+static PID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, Default)]
+struct Scopes {
+    joined: String,
+    starts: Vec<usize>,
+}
+
+thread_local! {
+    static MDC: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+    static SCOPES: RefCell<Scopes> = const { RefCell::new(Scopes {
+        joined: String::new(),
+        starts: Vec::new(),
+    }) };
+    static TRACE_ID: Cell<Option<TraceId>> = const { Cell::new(None) };
+    static THREAD: Thread = thread::current()
+}
+
+/// Sets `key` to `value` in the current thread's mapped diagnostic context,
+/// returning the previous value, if any.
+pub fn insert<V: fmt::Display>(key: &str, value: V) -> Option<String> {
+    // rendered up front, in case formatting the value logs something
+    let mut rendered = String::new();
+    let _ = write!(rendered, "{}", value);
+    MDC.with(|mdc| {
+        let mut mdc = mdc.borrow_mut();
+        match mdc.iter().position(|(k, _)| k == key) {
+            Some(idx) => Some(mem::replace(&mut mdc[idx].1, rendered)),
+            None => {
+                mdc.push((key.into(), rendered));
+                None
+            }
+        }
+    })
+}
+
+/// Returns the value of `key` in the current thread's mapped diagnostic
+/// context.
+pub fn get(key: &str) -> Option<String> {
+    MDC.with(|mdc| {
+        mdc.borrow().iter().find(|&(k, _)| k == key).map(|(_, v)| v.clone())
+    })
+}
+
+/// Removes `key` from the current thread's mapped diagnostic context,
+/// returning its value, if any.
+pub fn remove(key: &str) -> Option<String> {
+    MDC.with(|mdc| {
+        let mut mdc = mdc.borrow_mut();
+        mdc.iter().position(|(k, _)| k == key).map(|idx| mdc.remove(idx).1)
+    })
+}
+
+/// Removes every key from the current thread's mapped diagnostic context.
+pub fn clear() {
+    MDC.with(|mdc| mdc.borrow_mut().clear())
+}
+
+/// Sets the trace id attached to records logged on the current thread,
+/// returning the previous one.
+///
+/// A server would typically set this from an incoming request's headers when
+/// it starts handling the request, and reset it to `None` when it is done.
+/// The id is available to loggers from `LogMetadata::trace_id`.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// use log::TraceId;
+///
+/// fn main() {
+///     log::set_current_trace_id(Some(TraceId::from_parts(7, 42)));
+///     // logged with the trace id 0000000000000007000000000000002a
+///     info!("handling request");
+///     log::set_current_trace_id(None);
+/// }
+/// ```
+pub fn set_current_trace_id(trace_id: Option<TraceId>) -> Option<TraceId> {
+    TRACE_ID.with(|current| current.replace(trace_id))
+}
+
+/// Returns the trace id attached to records logged on the current thread.
+pub fn current_trace_id() -> Option<TraceId> {
+    TRACE_ID.try_with(|current| current.get()).unwrap_or(None)
+}
+
+/// Pushes a scope onto the current thread's diagnostic scope stack.
+///
+/// Records logged on this thread while the returned guard is alive carry the
+/// scope, joined to the scopes pushed before it with `" > "`, which is
+/// available from `LogRecord::scope`. Dropping the guard pops the scope along
+/// with any scopes pushed after it.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// fn handle(conn: u32) {
+///     let _scope = log::push_scope(format_args!("conn {}", conn));
+///     parse();
+/// }
+///
+/// fn parse() {
+///     let _scope = log::push_scope("parsing request");
+///     // logged with the scope "conn 42 > parsing request"
+///     warn!("header too long");
+/// }
+///
+/// fn main() {
+///     handle(42);
+/// }
+/// ```
+pub fn push_scope<S: fmt::Display>(scope: S) -> ScopeGuard {
+    let mut rendered = String::new();
+    let _ = write!(rendered, "{}", scope);
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let start = scopes.joined.len();
+        if !scopes.starts.is_empty() {
+            scopes.joined.push_str(SCOPE_SEPARATOR);
+        }
+        scopes.joined.push_str(&rendered);
+        scopes.starts.push(start);
+        ScopeGuard {
+            depth: scopes.starts.len() - 1,
+            _not_send: PhantomData,
+        }
+    })
+}
+
+/// A guard which pops a scope pushed by `push_scope` when dropped.
+#[must_use]
+#[derive(Debug)]
+pub struct ScopeGuard {
+    depth: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let depth = self.depth;
+        let _ = SCOPES.try_with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            if depth < scopes.starts.len() {
+                let start = scopes.starts[depth];
+                scopes.joined.truncate(start);
+                scopes.starts.truncate(depth);
+            }
+        });
+    }
+}
+
+/// A snapshot of a thread's diagnostic context.
+///
+/// Work handed to a thread pool loses the mapped context and scopes of the
+/// thread which submitted it. Capturing a `LogContext` on the submitting
+/// thread and attaching it on the worker gives records logged by the work the
+/// same context they would have had if it had run on the submitting thread.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// use std::thread;
+/// use log::LogContext;
+///
+/// fn main() {
+///     log::mdc::insert("request_id", 17);
+///     let _scope = log::push_scope("handling request");
+///
+///     let context = LogContext::capture();
+///     thread::spawn(move || {
+///         let _context = context.attach();
+///         // logged with request_id=17 and the scope "handling request"
+///         info!("resizing image");
+///     }).join().unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LogContext {
+    mdc: Vec<(String, String)>,
+    scopes: Scopes,
+    trace_id: Option<TraceId>,
+}
+
+impl LogContext {
+    /// Captures the current thread's mapped context, scopes and trace id.
+    pub fn capture() -> LogContext {
+        LogContext {
+            mdc: MDC.with(|mdc| mdc.borrow().clone()),
+            scopes: SCOPES.with(|scopes| scopes.borrow().clone()),
+            trace_id: current_trace_id(),
+        }
+    }
+
+    /// Returns the value of `key` in the captured mapped context.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.mdc.iter().find(|&(k, _)| k == key).map(|(_, v)| &**v)
+    }
+
+    /// Returns the captured scopes, joined as by `LogRecord::scope`.
+    pub fn scope(&self) -> &str {
+        &self.scopes.joined
+    }
+
+    /// Returns the captured trace id.
+    pub fn trace_id(&self) -> Option<TraceId> {
+        self.trace_id
+    }
+
+    /// Replaces the current thread's mapped context, scopes and trace id with
+    /// the captured ones.
+    ///
+    /// The thread's previous context is restored when the returned guard is
+    /// dropped.
+    pub fn attach(&self) -> ContextGuard {
+        let LogContext { mdc: new_mdc, scopes: new_scopes, trace_id } = self.clone();
+        ContextGuard {
+            previous: Some(LogContext {
+                mdc: MDC.with(|mdc| mem::replace(&mut *mdc.borrow_mut(), new_mdc)),
+                scopes: SCOPES.with(|scopes| mem::replace(&mut *scopes.borrow_mut(), new_scopes)),
+                trace_id: set_current_trace_id(trace_id),
+            }),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+// Swaps the current thread's context with the one in context.
+#[cfg(feature = "futures")]
+fn swap(context: &mut LogContext) {
+    MDC.with(|mdc| mem::swap(&mut *mdc.borrow_mut(), &mut context.mdc));
+    SCOPES.with(|scopes| mem::swap(&mut *scopes.borrow_mut(), &mut context.scopes));
+    context.trace_id = set_current_trace_id(context.trace_id);
+}
+
+#[cfg(feature = "futures")]
+impl LogContext {
+    /// Wraps a future or stream so that this context is attached to the
+    /// thread polling it.
+    ///
+    /// Changes the future makes to the context while it is being polled, such
+    /// as inserting keys into the mapped context, are kept for the next poll
+    /// rather than leaking into whatever the thread runs next.
+    ///
+    /// This is only available with the `futures` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate log;
+    /// # extern crate futures;
+    /// use futures::Future;
+    /// use futures::future;
+    /// use log::LogContext;
+    ///
+    /// # fn main() {
+    /// log::mdc::insert("request_id", 17);
+    /// let task = LogContext::capture().wrap(future::lazy(|| {
+    ///     // logged with request_id=17, wherever the future is polled
+    ///     info!("sending response");
+    ///     Ok::<(), ()>(())
+    /// }));
+    /// log::mdc::clear();
+    /// task.wait().unwrap();
+    /// # }
+    /// ```
+    pub fn wrap<F>(self, inner: F) -> WithContext<F> {
+        WithContext {
+            inner,
+            context: self,
+        }
+    }
+}
+
+/// A future or stream which polls another one with a `LogContext` attached.
+///
+/// This is created by `LogContext::wrap`.
+#[cfg(feature = "futures")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WithContext<F> {
+    inner: F,
+    context: LogContext,
+}
+
+#[cfg(feature = "futures")]
+impl<F> WithContext<F> {
+    fn poll_with<T, P>(&mut self, poll: P) -> T
+        where P: FnOnce(&mut F) -> T
+    {
+        // swaps the polling thread's context back, even if poll panics
+        struct Restore<'a>(&'a mut LogContext);
+
+        impl<'a> Drop for Restore<'a> {
+            fn drop(&mut self) {
+                swap(self.0);
+            }
+        }
+
+        swap(&mut self.context);
+        let _restore = Restore(&mut self.context);
+        poll(&mut self.inner)
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<F: Future> Future for WithContext<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        self.poll_with(|inner| inner.poll())
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S: Stream> Stream for WithContext<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        self.poll_with(|inner| inner.poll())
+    }
+}
+
+/// A guard which restores a thread's previous diagnostic context when
+/// dropped.
+///
+/// This is returned by `LogContext::attach`.
+#[must_use]
+#[derive(Debug)]
+pub struct ContextGuard {
+    previous: Option<LogContext>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            let LogContext { mdc: old_mdc, scopes: old_scopes, trace_id } = previous;
+            let _ = MDC.try_with(|mdc| *mdc.borrow_mut() = old_mdc);
+            let _ = SCOPES.try_with(|scopes| *scopes.borrow_mut() = old_scopes);
+            let _ = TRACE_ID.try_with(|current| current.set(trace_id));
+        }
+    }
+}
+
+// Returns the id of the current process.
+pub fn current_pid() -> u32 {
+    match PID.load(Ordering::Relaxed) {
+        0 => {
+            let pid = process::id();
+            PID.store(pid as usize, Ordering::Relaxed);
+            pid
+        }
+        pid => pid as u32,
+    }
+}
+
+// Forgets the cached process id, after a fork.
+#[cfg(all(unix, feature = "fork_safety"))]
+pub fn reset_pid() {
+    PID.store(0, Ordering::Relaxed);
+}
+
+// Returns a handle to the current thread, unless it is exiting.
+pub fn current_thread() -> Option<Thread> {
+    THREAD.try_with(|thread| thread.clone()).ok()
+}
+
+// Calls f with key_values followed by the current thread's mapped context, and
+// with the current thread's joined scopes.
+pub fn with_context<F, T>(key_values: &[(&str, Value)], f: F) -> T
+    where F: FnOnce(&[(&str, Value)], &str) -> T
+{
+    // the context is gone if this thread is exiting
+    let mdc = MDC.try_with(|mdc| {
+        mdc.try_borrow().map(|mdc| !mdc.is_empty()).unwrap_or(false)
+    }).unwrap_or(false);
+    let scopes = SCOPES.try_with(|scopes| {
+        scopes.try_borrow().map(|scopes| !scopes.starts.is_empty()).unwrap_or(false)
+    }).unwrap_or(false);
+
+    match (mdc, scopes) {
+        (false, false) => f(key_values, ""),
+        (true, false) => with_mdc(key_values, |key_values| f(key_values, "")),
+        (false, true) => SCOPES.with(|scopes| f(key_values, &scopes.borrow().joined)),
+        (true, true) => {
+            SCOPES.with(|scopes| {
+                with_mdc(key_values, |key_values| f(key_values, &scopes.borrow().joined))
+            })
+        }
+    }
+}
+
+fn with_mdc<F, T>(key_values: &[(&str, Value)], f: F) -> T
+    where F: FnOnce(&[(&str, Value)]) -> T
+{
+    MDC.with(|mdc| {
+        let mdc = mdc.borrow();
+        let mut all = Vec::with_capacity(key_values.len() + mdc.len());
+        all.extend_from_slice(key_values);
+        for (k, v) in mdc.iter() {
+            if !key_values.iter().any(|&(key, _)| key == &**k) {
+                all.push((&**k, Value::Str(v)));
+            }
+        }
+        f(&all)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    use kv::Value;
+    use TraceId;
+    use super::{insert, get, remove, clear, push_scope, with_context, LogContext,
+                set_current_trace_id, current_trace_id};
+
+    fn pairs(key_values: &[(&str, Value)]) -> Vec<String> {
+        key_values.iter().map(|&(k, v)| format!("{}={}", k, v)).collect()
+    }
+
+    fn scope() -> String {
+        with_context(&[], |_, scope| scope.to_string())
+    }
+
+    #[test]
+    fn test_mdc() {
+        clear();
+        assert_eq!(None, insert("request_id", 42));
+        assert_eq!(None, insert("user", "alice"));
+        assert_eq!(Some("alice".to_string()), insert("user", "bob"));
+        assert_eq!(Some("42".to_string()), get("request_id"));
+
+        let explicit = [("user", Value::from("carol")), ("port", Value::from(80))];
+        assert_eq!(vec!["user=carol", "port=80", "request_id=42"],
+                   with_context(&explicit, |kvs, _| pairs(kvs)));
+
+        assert_eq!(Some("42".to_string()), remove("request_id"));
+        assert_eq!(None, remove("request_id"));
+        assert_eq!(vec!["user=bob"], with_context(&[], |kvs, _| pairs(kvs)));
+
+        clear();
+        assert_eq!(None, get("user"));
+        assert!(with_context(&[], |kvs, _| kvs.is_empty()));
+    }
+
+    #[test]
+    fn test_mdc_attached() {
+        ::test::init().unwrap();
+        ::test::clear();
+        clear();
+
+        insert("request_id", 7);
+        info!(attempt = 2, "retrying");
+        clear();
+        info!("done");
+
+        let records = ::test::drain();
+        assert_eq!(&[("attempt".to_string(), "2".to_string()),
+                     ("request_id".to_string(), "7".to_string())],
+                   records[0].key_values());
+        assert!(records[1].key_values().is_empty());
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!("", scope());
+        {
+            let _conn = push_scope(format_args!("conn {}", 42));
+            assert_eq!("conn 42", scope());
+            {
+                let _parse = push_scope("parsing");
+                assert_eq!("conn 42 > parsing", scope());
+            }
+            let _write = push_scope("writing");
+            assert_eq!("conn 42 > writing", scope());
+        }
+        assert_eq!("", scope());
+
+        // dropping an outer guard first also pops the scopes above it
+        let outer = push_scope("outer");
+        let inner = push_scope("inner");
+        drop(outer);
+        assert_eq!("", scope());
+        drop(inner);
+        assert_eq!("", scope());
+        let _next = push_scope("next");
+        assert_eq!("next", scope());
+    }
+
+    #[test]
+    fn test_log_context() {
+        use std::thread;
+
+        let trace_id = TraceId::from_parts(3, 4);
+        clear();
+        insert("request_id", 17);
+        set_current_trace_id(Some(trace_id));
+        let context = {
+            let _scope = push_scope("request");
+            LogContext::capture()
+        };
+        clear();
+        set_current_trace_id(None);
+        assert_eq!(Some("17"), context.get("request_id"));
+        assert_eq!("request", context.scope());
+        assert_eq!(Some(trace_id), context.trace_id());
+
+        insert("worker", 1);
+        {
+            let _context = context.attach();
+            assert_eq!(None, get("worker"));
+            assert_eq!(vec!["request_id=17"], with_context(&[], |kvs, _| pairs(kvs)));
+            assert_eq!("request", scope());
+            assert_eq!(Some(trace_id), current_trace_id());
+        }
+        assert_eq!(None, current_trace_id());
+        assert_eq!(Some("1".to_string()), get("worker"));
+        assert_eq!(None, get("request_id"));
+        assert_eq!("", scope());
+        clear();
+
+        thread::spawn(move || {
+            let _context = context.attach();
+            assert_eq!(Some("17".to_string()), get("request_id"));
+            assert_eq!("request", scope());
+        }).join().unwrap();
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_with_context() {
+        use futures::{Async, Future};
+        use futures::future;
+
+        clear();
+        insert("request_id", 17);
+        let mut polls = 0;
+        let mut task = LogContext::capture().wrap(future::poll_fn(move || {
+            assert_eq!(Some("17".to_string()), get("request_id"));
+            polls += 1;
+            if polls == 1 {
+                insert("attempt", 1);
+                Ok(Async::NotReady)
+            } else {
+                assert_eq!(Some("1".to_string()), get("attempt"));
+                Ok::<_, ()>(Async::Ready(polls))
+            }
+        }));
+        clear();
+
+        insert("other", 3);
+        assert_eq!(Ok(Async::NotReady), task.poll());
+        assert_eq!(None, get("attempt"));
+        assert_eq!(Some("3".to_string()), get("other"));
+        assert_eq!(Ok(2), task.wait());
+        clear();
+    }
+
+    #[test]
+    fn test_trace_id() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        let trace_id = TraceId::from_parts(0x4bf92f3577b34da6, 0xa3ce929d0e0e4736);
+        assert_eq!(None, set_current_trace_id(Some(trace_id)));
+        info!("traced");
+        assert_eq!(Some(trace_id), set_current_trace_id(None));
+        info!("untraced");
+
+        let records = ::test::drain();
+        assert_eq!(Some(trace_id), records[0].trace_id());
+        assert_eq!(None, records[1].trace_id());
+        assert_eq!("4bf92f3577b34da6a3ce929d0e0e4736", trace_id.to_string());
+    }
+
+    #[test]
+    fn test_thread() {
+        use std::thread;
+
+        ::test::init().unwrap();
+        ::test::clear();
+
+        info!("on the test thread");
+        let records = ::test::drain();
+        assert_eq!(thread::current().id(), records[0].thread().unwrap().id());
+        assert_eq!(Some(::std::process::id()), records[0].pid());
+
+        thread::Builder::new().name("log-worker".to_string()).spawn(|| {
+            info!("on a worker");
+            let records = ::test::drain();
+            assert_eq!(Some("log-worker"), records[0].thread().unwrap().name());
+        }).unwrap().join().unwrap();
+    }
+}
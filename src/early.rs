@@ -0,0 +1,140 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The buffer of records logged before a logger is installed.
+//
+// Like the per-target level table, the buffer is allocated when it is first
+// needed and never freed. ACTIVE is only set between buffer_early_records and
+// the first logger being installed, so the lock is never touched otherwise.
+
+use std::boxed::Box;
+use std::mem;
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, Once};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::vec::Vec;
+
+use {Log, LogLevelFilter, LogRecord, OwnedLogRecord};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static INIT: Once = Once::new();
+static mut BUFFER: *const Mutex<Buffer> = ptr::null();
+
+struct Buffer {
+    records: Vec<OwnedLogRecord>,
+    capacity: usize,
+    // set once the records have been replayed
+    closed: bool,
+}
+
+fn buffer() -> MutexGuard<'static, Buffer> {
+    let buffer = unsafe {
+        INIT.call_once(|| {
+            BUFFER = Box::into_raw(Box::new(Mutex::new(Buffer {
+                records: Vec::new(),
+                capacity: 0,
+                closed: false,
+            })));
+        });
+        &*BUFFER
+    };
+    match buffer.lock() {
+        Ok(buffer) => buffer,
+        Err(err) => err.into_inner(),
+    }
+}
+
+/// Keeps records logged before a logger is installed, and replays them into
+/// the first logger once it is.
+///
+/// Up to `capacity` records are kept, and any more are dropped and counted in
+/// `dropped_records`. Since no records get past the global maximum log level
+/// until a logger sets it, this also sets the maximum log level to `level`.
+/// When the logger is installed, each kept record is passed to it if its
+/// level is within the maximum log level the logger set and any per-target
+/// level for its target. The kept records reach the logger before any record
+/// logged after it was installed.
+///
+/// This has no effect once a logger has been installed.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// use log::LogLevelFilter;
+///
+/// fn main() {
+///     log::buffer_early_records(256, LogLevelFilter::Info);
+///     info!("loading configuration");
+///     // ... install a logger, which then receives the record above
+/// }
+/// ```
+pub fn buffer_early_records(capacity: usize, level: LogLevelFilter) {
+    {
+        let mut buffer = buffer();
+        if buffer.closed {
+            return;
+        }
+        buffer.capacity = capacity;
+        ACTIVE.store(true, Ordering::SeqCst);
+    }
+    ::set_max_level(level);
+}
+
+// Keeps a copy of record to replay later. Returns false if buffering isn't
+// active, or the buffer is full.
+pub fn push(record: &LogRecord) -> bool {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    let mut buffer = buffer();
+    if buffer.closed || buffer.records.len() >= buffer.capacity {
+        return false;
+    }
+    buffer.records.push(record.to_owned());
+    true
+}
+
+// Passes the buffered records to the newly installed logger, stops buffering,
+// and then calls publish to make the logger visible to log calls.
+//
+// Until publish is called, records logged by other threads, or by the logger
+// itself, are still buffered, and are replayed after the ones before them.
+// The buffer is closed under the same lock as publish is called, so nothing
+// logged afterwards can overtake a buffered record.
+pub fn replay<F: FnOnce()>(logger: &dyn Log, publish: F) {
+    if !ACTIVE.load(Ordering::SeqCst) {
+        return publish();
+    }
+
+    loop {
+        let records = {
+            let mut buffer = buffer();
+            if buffer.records.is_empty() {
+                buffer.closed = true;
+                ACTIVE.store(false, Ordering::SeqCst);
+                return publish();
+            }
+            mem::take(&mut buffer.records)
+        };
+
+        let max_level = ::max_log_level();
+        let records = records.iter().filter(|record| {
+            record.is_audit() ||
+            (record.level() <= max_level && ::targets::enabled(record.level(), record.target()))
+        });
+        for record in records {
+            record.with_record(|record| logger.log(record));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Extension traits which log the failure case of a Result or Option.
+//
+// A method can't find out where it was called from, so the caller passes in
+// its location with log_location!. The record's target is the module path of
+// that location, as it is for the logging macros.
+
+use core::fmt;
+
+use {LogLevel, LogLocation};
+
+/// Logs the error case of a `Result`.
+///
+/// Each method logs a message if the result is an `Err`, and then returns the
+/// result unchanged so it can still be handled or propagated. The
+/// message is the given description followed by the error's `Display`
+/// output. The location should be given with the `log_location!` macro, so
+/// that the record points at the caller.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use std::fs::File;
+/// use std::io;
+/// use log::ResultExt;
+///
+/// fn open_config() -> io::Result<File> {
+///     let file = File::open("/etc/app.conf").log_err(log_location!(), "no config")?;
+///     Ok(file)
+/// }
+/// # fn main() { let _ = open_config(); }
+/// ```
+pub trait ResultExt<T, E> {
+    /// Logs the error at the error level.
+    fn log_err(self, location: &'static LogLocation, msg: &str) -> Result<T, E>;
+
+    /// Logs the error at the warn level.
+    fn warn_err(self, location: &'static LogLocation, msg: &str) -> Result<T, E>;
+}
+
+impl<T, E: fmt::Display> ResultExt<T, E> for Result<T, E> {
+    fn log_err(self, location: &'static LogLocation, msg: &str) -> Result<T, E> {
+        if let Err(ref err) = self {
+            log(LogLevel::Error, location, format_args!("{}: {}", msg, err));
+        }
+        self
+    }
+
+    fn warn_err(self, location: &'static LogLocation, msg: &str) -> Result<T, E> {
+        if let Err(ref err) = self {
+            log(LogLevel::Warn, location, format_args!("{}: {}", msg, err));
+        }
+        self
+    }
+}
+
+/// Logs the `None` case of an `Option`.
+///
+/// This works like `ResultExt`, but the message is just the given
+/// description.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use std::env;
+/// use log::OptionExt;
+///
+/// # fn main() {
+/// let home = env::var_os("APP_HOME").warn_none(log_location!(), "APP_HOME is not set");
+/// # let _ = home;
+/// # }
+/// ```
+pub trait OptionExt<T> {
+    /// Logs the message at the error level if the option is `None`.
+    fn log_none(self, location: &'static LogLocation, msg: &str) -> Option<T>;
+
+    /// Logs the message at the warn level if the option is `None`.
+    fn warn_none(self, location: &'static LogLocation, msg: &str) -> Option<T>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    fn log_none(self, location: &'static LogLocation, msg: &str) -> Option<T> {
+        if self.is_none() {
+            log(LogLevel::Error, location, format_args!("{}", msg));
+        }
+        self
+    }
+
+    fn warn_none(self, location: &'static LogLocation, msg: &str) -> Option<T> {
+        if self.is_none() {
+            log(LogLevel::Warn, location, format_args!("{}", msg));
+        }
+        self
+    }
+}
+
+fn log(level: LogLevel, location: &'static LogLocation, args: fmt::Arguments) {
+    let target = location.__module_path;
+    if level <= ::__static_max_level_for(target) && level <= ::max_log_level() {
+        ::__log(level, target, location, args, &[]);
+    }
+}
+
+#[cfg(all(test, not(feature = "freestanding")))]
+mod tests {
+    use LogLevel;
+    use super::{OptionExt, ResultExt};
+
+    #[test]
+    fn test_result_option_ext() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        let ok: Result<u32, &str> = Ok(1);
+        assert_eq!(Ok(1), ok.log_err(log_location!(), "unused"));
+        assert_eq!(Some(2), Some(2).warn_none(log_location!(), "unused"));
+        assert!(::test::drain().is_empty());
+
+        let err: Result<u32, &str> = Err("timed out");
+        assert_eq!(Err("timed out"), err.log_err(log_location!(), "connect failed"));
+        let line = line!() - 1;
+        assert_eq!(None::<u32>, None.warn_none(log_location!(), "missing config"));
+
+        let records = ::test::drain();
+        assert_eq!(2, records.len());
+        assert_eq!(LogLevel::Error, records[0].level());
+        assert_eq!("connect failed: timed out", records[0].message());
+        assert_eq!(module_path!(), records[0].target());
+        assert_eq!(file!(), records[0].location().file());
+        assert_eq!(line, records[0].location().line());
+        assert_eq!(LogLevel::Warn, records[1].level());
+        assert_eq!("missing config", records[1].message());
+    }
+}
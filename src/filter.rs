@@ -0,0 +1,1000 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing and evaluation of `RUST_LOG`-style filter specifications.
+//!
+//! A specification is a comma separated list of directives, optionally
+//! followed by a `/` and a message filter:
+//!
+//! * `level` sets the level for all targets, e.g. `warn`.
+//! * `path` enables all logging for the target `path` and the targets inside
+//!   it, e.g. `hyper`.
+//! * `path=level` sets the level for the target `path` and the targets inside
+//!   it, e.g. `my_app::db=trace`.
+//!
+//! For example, `warn,hyper=debug,my_app::db=trace/request 42` logs warnings
+//! and errors everywhere, debug messages from hyper, trace messages from
+//! `my_app::db`, and asks the logger to only keep messages matching
+//! `request 42`.
+//!
+//! A `Filter` built from the directives decides which records a logger
+//! should keep. This module only needs an allocator, so it is also available
+//! in `freestanding` builds when the `alloc` feature is enabled.
+//!
+//! The message filter is a regular expression, and is only used by `Filter`
+//! when the `regex` feature is enabled, which requires the standard library.
+//! Otherwise it is ignored.
+//!
+//! # Configuration files
+//!
+//! With the `toml` or `json` features, directives can also be loaded from a
+//! configuration file shipped alongside a program. Both formats describe the
+//! same structure: an optional default `level`, an optional `message` filter,
+//! and a `targets` table mapping target prefixes to levels. In TOML:
+//!
+//! ```toml
+//! level = "warn"
+//! message = "request 42"
+//!
+//! [targets]
+//! hyper = "debug"
+//! "my_app::db" = "trace"
+//! ```
+//!
+//! And in JSON:
+//!
+//! ```json
+//! {
+//!     "level": "warn",
+//!     "message": "request 42",
+//!     "targets": { "hyper": "debug", "my_app::db": "trace" }
+//! }
+//! ```
+//!
+//! A filter can be changed while the program runs with `Filter::reload`. With
+//! the `watch` feature, a `Watcher` can also reload it whenever its
+//! configuration file changes. Reloads only affect the global maximum log
+//! level if the filter was marked with `Filter::global`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use log::LogLevelFilter;
+//! use log::filter::Directives;
+//!
+//! let directives: Directives = "warn,hyper=debug".parse().unwrap();
+//! assert_eq!(2, directives.directives().len());
+//! assert_eq!(None, directives.directives()[0].name());
+//! assert_eq!(LogLevelFilter::Warn, directives.directives()[0].level());
+//! assert_eq!(Some("hyper"), directives.directives()[1].name());
+//! ```
+
+#[cfg(not(feature = "freestanding"))]
+use std::error;
+#[cfg(not(feature = "freestanding"))]
+use std::string::{String, ToString};
+#[cfg(not(feature = "freestanding"))]
+use std::vec::Vec;
+#[cfg(not(feature = "freestanding"))]
+use std::sync::RwLock;
+#[cfg(feature = "freestanding")]
+use collections::string::{String, ToString};
+#[cfg(feature = "freestanding")]
+use collections::vec::Vec;
+
+use core::fmt;
+#[cfg(not(feature = "freestanding"))]
+use core::mem;
+use core::str::FromStr;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+#[cfg(any(feature = "toml", feature = "json"))]
+use std::fs::File;
+#[cfg(any(feature = "toml", feature = "json"))]
+use std::io::{self, Read};
+#[cfg(any(feature = "toml", feature = "json"))]
+use std::path::Path;
+#[cfg(feature = "json")]
+use serde_json;
+#[cfg(feature = "toml")]
+use toml;
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+use std::fs;
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+use std::sync::Arc;
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+use std::thread::{self, JoinHandle};
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+use std::time::{Duration, SystemTime};
+
+use {LogLevel, LogLevelFilter, LogMetadata, LogRecord, LOG_LEVEL_NAMES};
+
+/// A single directive of a filter specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Directive {
+    name: Option<String>,
+    level: LogLevelFilter,
+}
+
+impl Directive {
+    /// Creates a directive for the target `name` and the targets inside it,
+    /// or for all targets if `name` is `None`.
+    pub fn new(name: Option<&str>, level: LogLevelFilter) -> Directive {
+        Directive {
+            name: name.map(|s| s.to_string()),
+            level,
+        }
+    }
+
+    /// The target prefix this directive applies to, or `None` if it applies
+    /// to all targets.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The maximum level of messages let through by this directive.
+    pub fn level(&self) -> LogLevelFilter {
+        self.level
+    }
+}
+
+/// A parsed filter specification.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Directives {
+    directives: Vec<Directive>,
+    message_filter: Option<String>,
+}
+
+impl Directives {
+    /// Parses a filter specification.
+    ///
+    /// This is the same as `spec.parse()`.
+    pub fn parse(spec: &str) -> Result<Directives, ParseDirectivesError> {
+        let mut parts = spec.splitn(2, '/');
+        let mods = parts.next().unwrap_or("");
+        let message_filter = parts.next();
+        if message_filter.is_some_and(|f| f.contains('/')) {
+            return Err(ParseDirectivesError::new(spec));
+        }
+        #[cfg(feature = "regex")]
+        {
+            if let Some(f) = message_filter {
+                if Regex::new(f).is_err() {
+                    return Err(ParseDirectivesError::new(f));
+                }
+            }
+        }
+
+        let mut directives = Vec::new();
+        for s in mods.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let mut parts = s.split('=');
+            let directive = match (parts.next(), parts.next().map(|s| s.trim()),
+                                   parts.next()) {
+                // a lone level is the fallback for all targets
+                (Some(part0), None, None) => {
+                    match parse_level(part0) {
+                        Some(level) => Directive::new(None, level),
+                        None => Directive::new(Some(part0), LogLevelFilter::max()),
+                    }
+                }
+                (Some(part0), Some(""), None) => {
+                    Directive::new(Some(part0.trim()), LogLevelFilter::max())
+                }
+                (Some(part0), Some(part1), None) => {
+                    match parse_level(part1) {
+                        Some(level) => Directive::new(Some(part0.trim()), level),
+                        None => return Err(ParseDirectivesError::new(s)),
+                    }
+                }
+                _ => return Err(ParseDirectivesError::new(s)),
+            };
+            directives.push(directive);
+        }
+
+        Ok(Directives {
+            directives,
+            message_filter: message_filter.map(|s| s.to_string()),
+        })
+    }
+
+    /// The directives, in the order they were specified.
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// The message filter following the `/`, if any.
+    pub fn message_filter(&self) -> Option<&str> {
+        self.message_filter.as_deref()
+    }
+}
+
+// Unlike LogLevelFilter's FromStr, this doesn't accept numbers or aliases,
+// which could be mistaken for target names.
+fn parse_level(level: &str) -> Option<LogLevelFilter> {
+    LOG_LEVEL_NAMES.iter()
+                   .position(|name| ::name_matches(name, level))
+                   .and_then(LogLevelFilter::from_usize)
+}
+
+impl FromStr for Directives {
+    type Err = ParseDirectivesError;
+
+    fn from_str(spec: &str) -> Result<Directives, ParseDirectivesError> {
+        Directives::parse(spec)
+    }
+}
+
+/// The type returned when a filter specification can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDirectivesError {
+    directive: String,
+}
+
+impl ParseDirectivesError {
+    fn new(directive: &str) -> ParseDirectivesError {
+        ParseDirectivesError { directive: directive.to_string() }
+    }
+}
+
+impl fmt::Display for ParseDirectivesError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid logging directive `{}`", self.directive)
+    }
+}
+
+// only implement the Error trait when it's available
+#[cfg(not(feature = "freestanding"))]
+impl error::Error for ParseDirectivesError {
+    fn description(&self) -> &str { "invalid logging directive" }
+}
+
+/// Decides which records to log based on a set of directives.
+///
+/// A record is enabled if its level is at most the level of the directive
+/// with the longest name that its target is, or is inside of, so `app::db`
+/// matches `app::db::pool` but not `app::dbx`. Directives without a name
+/// match every target. If several directives have the same name, the
+/// last one wins, and records that match no directive are disabled.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{LogLevel, LogLevelFilter, LogMetadata};
+/// use log::filter::Filter;
+///
+/// let filter = Filter::parse("warn,hyper=debug,hyper::http=off").unwrap();
+/// assert_eq!(LogLevelFilter::Debug, filter.max_level());
+///
+/// let metadata = LogMetadata::builder()
+///                    .level(LogLevel::Debug)
+///                    .target("hyper::client")
+///                    .build();
+/// assert!(filter.enabled(&metadata));
+/// ```
+#[derive(Debug)]
+pub struct Filter {
+    #[cfg(not(feature = "freestanding"))]
+    rules: RwLock<Rules>,
+    #[cfg(feature = "freestanding")]
+    rules: Rules,
+    // whether reloading updates the global maximum log level
+    #[cfg(not(feature = "freestanding"))]
+    global: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Rules {
+    // sorted by descending name length, nameless directives last
+    directives: Vec<Directive>,
+    max_level: LogLevelFilter,
+    #[cfg(feature = "regex")]
+    message_filter: Option<Regex>,
+}
+
+impl Rules {
+    fn new(directives: Directives) -> Rules {
+        let mut sorted: Vec<Directive> = Vec::new();
+        for directive in directives.directives {
+            match sorted.iter().position(|d| d.name == directive.name) {
+                Some(idx) => sorted[idx] = directive,
+                None => sorted.push(directive),
+            }
+        }
+        sorted.sort_by(|a, b| {
+            let a = a.name.as_ref().map_or(0, |n| n.len());
+            let b = b.name.as_ref().map_or(0, |n| n.len());
+            b.cmp(&a)
+        });
+
+        let max_level = sorted.iter()
+                              .map(|d| d.level)
+                              .max()
+                              .unwrap_or(LogLevelFilter::Off);
+        Rules {
+            directives: sorted,
+            max_level,
+            // already checked by Directives::parse
+            #[cfg(feature = "regex")]
+            message_filter: directives.message_filter
+                                      .map(|f| Regex::new(&f).unwrap()),
+        }
+    }
+
+    fn level_enabled(&self, level: LogLevel, target: &str) -> bool {
+        for directive in &self.directives {
+            match directive.name {
+                Some(ref name) if !::module_matches(name, target) => {}
+                _ => return level <= directive.level,
+            }
+        }
+        false
+    }
+
+    #[cfg(feature = "regex")]
+    fn message_matches(&self, record: &LogRecord) -> bool {
+        match self.message_filter {
+            Some(ref re) => re.is_match(&record.args().to_string()),
+            None => true,
+        }
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn message_matches(&self, _: &LogRecord) -> bool {
+        true
+    }
+}
+
+impl Filter {
+    /// Creates a filter from a set of directives.
+    pub fn new(directives: Directives) -> Filter {
+        Filter::from_rules(Rules::new(directives))
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    fn from_rules(rules: Rules) -> Filter {
+        Filter {
+            rules: RwLock::new(rules),
+            global: false,
+        }
+    }
+
+    #[cfg(feature = "freestanding")]
+    fn from_rules(rules: Rules) -> Filter {
+        Filter { rules }
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    fn with_rules<F, T>(&self, f: F) -> T
+        where F: FnOnce(&Rules) -> T
+    {
+        match self.rules.read() {
+            Ok(rules) => f(&rules),
+            Err(err) => f(&err.into_inner()),
+        }
+    }
+
+    #[cfg(feature = "freestanding")]
+    fn with_rules<F, T>(&self, f: F) -> T
+        where F: FnOnce(&Rules) -> T
+    {
+        f(&self.rules)
+    }
+
+    /// Parses a filter specification and creates a filter from it.
+    pub fn parse(spec: &str) -> Result<Filter, ParseDirectivesError> {
+        Directives::parse(spec).map(Filter::new)
+    }
+
+    /// Returns the most verbose level enabled for any target.
+    ///
+    /// Loggers should use this to set the global maximum log level.
+    pub fn max_level(&self) -> LogLevelFilter {
+        self.with_rules(|rules| rules.max_level)
+    }
+
+    /// Determines if a message with the specified metadata should be logged.
+    ///
+    /// Audit records are always enabled.
+    pub fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.is_audit() || self.level_enabled(metadata.level(), metadata.target())
+    }
+
+    /// Determines if a record should be logged.
+    ///
+    /// With the `regex` feature, this also checks the record's message against
+    /// the message filter, formatting it to do so. Audit records skip the
+    /// level directives, but not the message filter.
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        self.with_rules(|rules| {
+            (record.is_audit() || rules.level_enabled(record.level(), record.target())) &&
+                rules.message_matches(record)
+        })
+    }
+
+    fn level_enabled(&self, level: LogLevel, target: &str) -> bool {
+        self.with_rules(|rules| rules.level_enabled(level, target))
+    }
+
+    /// Makes reloading this filter update the global maximum log level.
+    ///
+    /// This should be called on the filter which decides what the installed
+    /// logger logs. Reloading it then sets the global maximum log level to the
+    /// new `max_level` and rebuilds the interest cache. Other filters, such as
+    /// one for a single branch of a `Tee`, leave both alone, and whoever
+    /// reloads them is responsible for calling `rebuild_interest_cache`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::filter::Filter;
+    ///
+    /// let filter = Filter::parse("warn").unwrap().global();
+    /// log::set_max_level(filter.max_level());
+    /// filter.reload("info").unwrap();
+    /// assert_eq!(log::LogLevelFilter::Info, log::max_level());
+    /// ```
+    #[cfg(not(feature = "freestanding"))]
+    pub fn global(mut self) -> Filter {
+        self.global = true;
+        self
+    }
+
+    /// Replaces the filter's directives with a new set.
+    ///
+    /// The new directives take effect atomically: every call to `enabled` or
+    /// `matches` sees either the old or the new directives, never a mix. If
+    /// the filter was made global with `global`, the global maximum log level
+    /// and the interest cache are updated as well.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn reload_directives(&self, directives: Directives) {
+        let rules = Rules::new(directives);
+        let max_level = rules.max_level;
+        let old = {
+            let mut current = match self.rules.write() {
+                Ok(current) => current,
+                Err(err) => err.into_inner(),
+            };
+            mem::replace(&mut *current, rules)
+        };
+        if self.global {
+            ::set_max_level(max_level);
+            ::rebuild_interest_cache();
+        }
+        drop(old);
+    }
+
+    /// Parses a filter specification and replaces the filter's directives
+    /// with it.
+    ///
+    /// If the specification is invalid, the filter is left unchanged. See
+    /// `reload_directives`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevelFilter;
+    /// use log::filter::Filter;
+    ///
+    /// let filter = Filter::parse("warn").unwrap();
+    /// filter.reload("warn,my_app=debug").unwrap();
+    /// assert_eq!(LogLevelFilter::Debug, filter.max_level());
+    /// ```
+    #[cfg(not(feature = "freestanding"))]
+    pub fn reload(&self, spec: &str) -> Result<(), ParseDirectivesError> {
+        let directives = Directives::parse(spec)?;
+        self.reload_directives(directives);
+        Ok(())
+    }
+}
+
+impl Clone for Filter {
+    #[cfg(not(feature = "freestanding"))]
+    fn clone(&self) -> Filter {
+        Filter {
+            rules: RwLock::new(self.with_rules(|rules| rules.clone())),
+            global: self.global,
+        }
+    }
+
+    #[cfg(feature = "freestanding")]
+    fn clone(&self) -> Filter {
+        Filter::from_rules(self.rules.clone())
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseDirectivesError;
+
+    fn from_str(spec: &str) -> Result<Filter, ParseDirectivesError> {
+        Filter::parse(spec)
+    }
+}
+
+/// The type returned when a filter configuration file can't be loaded.
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Debug)]
+pub struct LoadConfigError(LoadConfigErrorKind);
+
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Debug)]
+enum LoadConfigErrorKind {
+    Io(io::Error),
+    Syntax(String),
+    Invalid(String),
+    UnknownFormat,
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl fmt::Display for LoadConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            LoadConfigErrorKind::Io(ref err) => {
+                write!(fmt, "unable to read logging configuration: {}", err)
+            }
+            LoadConfigErrorKind::Syntax(ref err) => {
+                write!(fmt, "invalid logging configuration: {}", err)
+            }
+            LoadConfigErrorKind::Invalid(ref err) => {
+                write!(fmt, "invalid logging configuration: {}", err)
+            }
+            LoadConfigErrorKind::UnknownFormat => {
+                write!(fmt, "unknown logging configuration format")
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl error::Error for LoadConfigError {
+    fn description(&self) -> &str { "unable to load logging configuration" }
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+fn invalid<T>(msg: fmt::Arguments) -> Result<T, LoadConfigError> {
+    Err(LoadConfigError(LoadConfigErrorKind::Invalid(msg.to_string())))
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl Directives {
+    /// Loads directives from a configuration file.
+    ///
+    /// The format is picked from the file's extension, which must be `toml`
+    /// or `json` and have the corresponding feature enabled.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Directives, LoadConfigError> {
+        let path = path.as_ref();
+        let mut config = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut config))
+            .map_err(|e| LoadConfigError(LoadConfigErrorKind::Io(e)))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Directives::from_toml(&config),
+            #[cfg(feature = "json")]
+            Some("json") => Directives::from_json(&config),
+            _ => Err(LoadConfigError(LoadConfigErrorKind::UnknownFormat)),
+        }
+    }
+
+    /// Loads directives from a TOML configuration.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(config: &str) -> Result<Directives, LoadConfigError> {
+        let value = config.parse::<toml::Value>().map_err(|e| {
+            LoadConfigError(LoadConfigErrorKind::Syntax(e.to_string()))
+        })?;
+        let table = match value.as_table() {
+            Some(table) => table,
+            None => return invalid(format_args!("expected a table")),
+        };
+
+        let mut targets = Vec::new();
+        if let Some(value) = table.get("targets") {
+            let value = match value.as_table() {
+                Some(value) => value,
+                None => {
+                    return invalid(format_args!("`targets` must be a table"))
+                }
+            };
+            for (name, level) in value {
+                match level.as_str() {
+                    Some(level) => targets.push((&**name, level)),
+                    None => {
+                        return invalid(format_args!("target levels must be strings"))
+                    }
+                }
+            }
+        }
+
+        Directives::from_config(toml_str(table.get("level"), "level")?,
+                                toml_str(table.get("message"), "message")?,
+                                &targets)
+    }
+
+    /// Loads directives from a JSON configuration.
+    #[cfg(feature = "json")]
+    pub fn from_json(config: &str) -> Result<Directives, LoadConfigError> {
+        let value = serde_json::from_str::<serde_json::Value>(config).map_err(|e| {
+            LoadConfigError(LoadConfigErrorKind::Syntax(e.to_string()))
+        })?;
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => return invalid(format_args!("expected an object")),
+        };
+
+        let mut targets = Vec::new();
+        if let Some(value) = object.get("targets") {
+            let value = match value.as_object() {
+                Some(value) => value,
+                None => {
+                    return invalid(format_args!("`targets` must be an object"))
+                }
+            };
+            for (name, level) in value {
+                match level.as_str() {
+                    Some(level) => targets.push((&**name, level)),
+                    None => {
+                        return invalid(format_args!("target levels must be strings"))
+                    }
+                }
+            }
+        }
+
+        Directives::from_config(json_str(object.get("level"), "level")?,
+                                json_str(object.get("message"), "message")?,
+                                &targets)
+    }
+
+    fn from_config(level: Option<&str>, message: Option<&str>,
+                   targets: &[(&str, &str)]) -> Result<Directives, LoadConfigError> {
+        let mut directives = Vec::new();
+        if let Some(level) = level {
+            match parse_level(level) {
+                Some(level) => directives.push(Directive::new(None, level)),
+                None => return invalid(format_args!("invalid level `{}`", level)),
+            }
+        }
+        for &(name, level) in targets {
+            match parse_level(level) {
+                Some(level) => directives.push(Directive::new(Some(name), level)),
+                None => return invalid(format_args!("invalid level `{}`", level)),
+            }
+        }
+
+        #[cfg(feature = "regex")]
+        {
+            if let Some(message) = message {
+                if let Err(e) = Regex::new(message) {
+                    return invalid(format_args!("{}", e));
+                }
+            }
+        }
+
+        Ok(Directives {
+            directives,
+            message_filter: message.map(|s| s.to_string()),
+        })
+    }
+}
+
+#[cfg(feature = "toml")]
+fn toml_str<'a>(value: Option<&'a toml::Value>, key: &str)
+                -> Result<Option<&'a str>, LoadConfigError> {
+    match value {
+        None => Ok(None),
+        Some(value) => match value.as_str() {
+            Some(s) => Ok(Some(s)),
+            None => invalid(format_args!("`{}` must be a string", key)),
+        },
+    }
+}
+
+#[cfg(feature = "json")]
+fn json_str<'a>(value: Option<&'a serde_json::Value>, key: &str)
+                -> Result<Option<&'a str>, LoadConfigError> {
+    match value {
+        None => Ok(None),
+        Some(value) => match value.as_str() {
+            Some(s) => Ok(Some(s)),
+            None => invalid(format_args!("`{}` must be a string", key)),
+        },
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl Filter {
+    /// Loads a filter from a configuration file.
+    ///
+    /// See `Directives::from_file`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Filter, LoadConfigError> {
+        Directives::from_file(path).map(Filter::new)
+    }
+}
+
+/// A handle to a thread reloading a filter from a configuration file.
+///
+/// The thread is stopped when the handle is dropped. Besides `watch`, this
+/// needs the `toml` or `json` feature to be enabled.
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+impl Watcher {
+    /// Starts watching the configuration file at `path`.
+    ///
+    /// The file's modification time and size are checked every `interval`,
+    /// so that a rewrite is noticed even if the file system's timestamps are
+    /// too coarse to tell it apart, and the file is loaded with `Directives::from_file` and passed to
+    /// `filter.reload_directives` whenever it changes. If the file can't be
+    /// loaded, the filter is left as it was until the next change.
+    ///
+    /// Returns an error if the file's metadata can't be read initially.
+    pub fn new<P>(filter: Arc<Filter>, path: P, interval: Duration)
+                  -> io::Result<Watcher>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref().to_path_buf();
+        let mut modified = stamp(&path)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = thread::spawn(move || {
+            loop {
+                thread::park_timeout(interval);
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let now = match stamp(&path) {
+                    Ok(now) => now,
+                    Err(_) => continue,
+                };
+                if now == modified {
+                    continue;
+                }
+                modified = now;
+                if let Ok(directives) = Directives::from_file(&path) {
+                    filter.reload_directives(directives);
+                }
+            }
+        });
+
+        Ok(Watcher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+// The modification time and size of the file at path.
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+fn stamp(path: &Path) -> io::Result<(SystemTime, u64)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+#[cfg(all(feature = "watch", any(feature = "toml", feature = "json")))]
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use collections::string::ToString;
+
+    use {LogLevel, LogLevelFilter};
+    use super::{Directive, Directives, Filter};
+
+    #[test]
+    fn test_parse() {
+        let dirs = Directives::parse("crate1::mod1=error,crate1::mod2,crate2=debug,warn")
+                       .unwrap();
+        assert_eq!(&[Directive::new(Some("crate1::mod1"), LogLevelFilter::Error),
+                     Directive::new(Some("crate1::mod2"), LogLevelFilter::max()),
+                     Directive::new(Some("crate2"), LogLevelFilter::Debug),
+                     Directive::new(None, LogLevelFilter::Warn)],
+                   dirs.directives());
+        assert_eq!(None, dirs.message_filter());
+
+        let dirs = Directives::parse("crate1=,").unwrap();
+        assert_eq!(&[Directive::new(Some("crate1"), LogLevelFilter::max())],
+                   dirs.directives());
+
+        assert_eq!(Directives::default(), Directives::parse("").unwrap());
+    }
+
+    #[test]
+    fn test_parse_message_filter() {
+        let dirs = Directives::parse("crate1=info/a.c").unwrap();
+        assert_eq!(&[Directive::new(Some("crate1"), LogLevelFilter::Info)],
+                   dirs.directives());
+        assert_eq!(Some("a.c"), dirs.message_filter());
+
+        let dirs = Directives::parse("/foo").unwrap();
+        assert!(dirs.directives().is_empty());
+        assert_eq!(Some("foo"), dirs.message_filter());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Directives::parse("crate1::mod1=warn=info,crate2=debug").is_err());
+        assert!(Directives::parse("crate1::mod1=noNumber").is_err());
+        assert!(Directives::parse("crate1/a/b").is_err());
+        assert_eq!("invalid logging directive `crate1=bad`",
+                   Directives::parse("crate1=bad").unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_filter_longest_match() {
+        let filter = Filter::parse("crate1::mod1=error,crate1=debug,warn").unwrap();
+        assert!(filter.level_enabled(LogLevel::Error, "crate1::mod1"));
+        assert!(!filter.level_enabled(LogLevel::Warn, "crate1::mod1::inner"));
+        assert!(filter.level_enabled(LogLevel::Debug, "crate1::mod2"));
+        assert!(!filter.level_enabled(LogLevel::Trace, "crate1::mod2"));
+        assert!(filter.level_enabled(LogLevel::Warn, "crate2"));
+        assert!(!filter.level_enabled(LogLevel::Info, "crate2"));
+        assert_eq!(LogLevelFilter::Debug, filter.max_level());
+    }
+
+    #[test]
+    fn test_filter_last_directive_wins() {
+        let filter = Filter::parse("crate1=trace,crate1=info").unwrap();
+        assert!(filter.level_enabled(LogLevel::Info, "crate1"));
+        assert!(!filter.level_enabled(LogLevel::Debug, "crate1"));
+        assert_eq!(LogLevelFilter::Info, filter.max_level());
+    }
+
+    #[test]
+    fn test_filter_no_match() {
+        let filter = Filter::parse("crate1=info").unwrap();
+        assert!(!filter.level_enabled(LogLevel::Error, "crate2"));
+        assert!(!filter.level_enabled(LogLevel::Error, "crate10"));
+
+        let filter = Filter::parse("").unwrap();
+        assert!(!filter.level_enabled(LogLevel::Error, "crate1"));
+        assert_eq!(LogLevelFilter::Off, filter.max_level());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_filter_message() {
+        use LogRecord;
+
+        let filter = Filter::parse("info/req(uest)? 4[0-9]").unwrap();
+        let check = |msg: &str| {
+            filter.matches(&LogRecord::builder()
+                                .args(format_args!("{}", msg))
+                                .build())
+        };
+        assert!(check("handling request 42"));
+        assert!(check("req 41 done"));
+        assert!(!check("handling request 52"));
+
+        assert!(Directives::parse("info/a(").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml() {
+        let dirs = Directives::from_toml(r#"
+            level = "warn"
+            message = "foo"
+
+            [targets]
+            hyper = "debug"
+            "my_app::db" = "TRACE"
+        "#).unwrap();
+        assert_eq!(&[Directive::new(None, LogLevelFilter::Warn),
+                     Directive::new(Some("hyper"), LogLevelFilter::Debug),
+                     Directive::new(Some("my_app::db"), LogLevelFilter::Trace)],
+                   dirs.directives());
+        assert_eq!(Some("foo"), dirs.message_filter());
+
+        assert!(Directives::from_toml("level = 3").is_err());
+        assert!(Directives::from_toml("[targets]\nhyper = \"loud\"").is_err());
+        assert!(Directives::from_toml("level = ").is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_from_json() {
+        let dirs = Directives::from_json(r#"{
+            "level": "info",
+            "targets": { "hyper": "off" }
+        }"#).unwrap();
+        assert_eq!(&[Directive::new(None, LogLevelFilter::Info),
+                     Directive::new(Some("hyper"), LogLevelFilter::Off)],
+                   dirs.directives());
+        assert_eq!(None, dirs.message_filter());
+
+        assert!(Directives::from_json(r#"{ "targets": [] }"#).is_err());
+        assert!(Directives::from_json("{").is_err());
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    #[test]
+    fn test_reload() {
+        let filter = Filter::parse("crate1=info").unwrap();
+        assert!(!filter.level_enabled(LogLevel::Debug, "crate1"));
+
+        filter.reload("crate1=debug,crate2=trace").unwrap();
+        assert!(filter.level_enabled(LogLevel::Debug, "crate1"));
+        assert!(filter.level_enabled(LogLevel::Trace, "crate2"));
+        assert_eq!(LogLevelFilter::Trace, filter.max_level());
+
+        assert!(filter.reload("crate1=bogus").is_err());
+        assert!(filter.level_enabled(LogLevel::Debug, "crate1"));
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    #[test]
+    fn test_reload_global() {
+        use max_log_level;
+
+        // only ever raises the level, so that tests running at the same time
+        // still see everything they log
+        let saved = max_log_level();
+        let filter = Filter::parse("crate1=info").unwrap();
+        filter.reload("crate1=trace3").unwrap();
+        assert_eq!(saved, max_log_level());
+
+        let filter = filter.global();
+        filter.reload("crate1=trace3").unwrap();
+        assert_eq!(LogLevelFilter::Trace3, max_log_level());
+        ::set_max_level(saved);
+    }
+
+    #[cfg(all(feature = "watch", feature = "toml"))]
+    #[test]
+    fn test_watch() {
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::Write;
+        use std::process;
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+        use super::Watcher;
+
+        // unique per process, so concurrent test runs don't share the file
+        let name = format!("log-filter-test-watch-{}.toml", process::id());
+        let path = env::temp_dir().join(name);
+        File::create(&path).unwrap().write_all(b"level = \"info\"").unwrap();
+
+        let filter = Arc::new(Filter::from_file(&path).unwrap());
+        let watcher = Watcher::new(filter.clone(), &path,
+                                   Duration::from_millis(10)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        // a different size from the first contents, in case the mtime doesn't
+        // change
+        File::create(&path).unwrap().write_all(b"level = \"debug\"").unwrap();
+
+        for _ in 0..500 {
+            if filter.level_enabled(LogLevel::Debug, "crate1") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(filter.level_enabled(LogLevel::Debug, "crate1"));
+        drop(watcher);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Handlers keeping the global logger state consistent across fork().
+//
+// Only the forking thread exists in the child, so any other thread which was
+// inside the logger at the time of the fork has left a count in REFCOUNT that
+// will never be released, and shutdown_logger and swap_logger would wait for
+// it until they time out. The child handler clears it, and also forgets the
+// cached process id. The prepare handler flushes the logger, so records
+// buffered before the fork aren't written by both processes.
+
+use std::os::raw::c_int;
+use std::sync::Once;
+
+#[cfg(not(feature = "leak_logger"))]
+use core::sync::atomic::Ordering;
+
+extern "C" {
+    fn pthread_atfork(prepare: Option<extern "C" fn()>,
+                      parent: Option<extern "C" fn()>,
+                      child: Option<extern "C" fn()>)
+                      -> c_int;
+}
+
+static REGISTER: Once = Once::new();
+
+pub fn register() {
+    REGISTER.call_once(|| unsafe {
+        pthread_atfork(Some(prepare), None, Some(child));
+    });
+}
+
+extern "C" fn prepare() {
+    ::flush();
+}
+
+extern "C" fn child() {
+    #[cfg(not(feature = "leak_logger"))]
+    ::REFCOUNT.store(0, Ordering::SeqCst);
+    ::context::reset_pid();
+}
+
+#[cfg(all(test, not(feature = "leak_logger")))]
+mod tests {
+    use std::os::raw::c_int;
+    use std::process;
+
+    use core::sync::atomic::Ordering;
+
+    use super::register;
+
+    extern "C" {
+        fn fork() -> c_int;
+        fn waitpid(pid: c_int, status: *mut c_int, options: c_int) -> c_int;
+        fn _exit(status: c_int) -> !;
+    }
+
+    #[test]
+    fn test_fork_resets_refcount() {
+        register();
+        // cache the parent's pid, and act as if another thread were inside
+        // the logger
+        ::context::current_pid();
+        ::REFCOUNT.fetch_add(1, Ordering::SeqCst);
+        unsafe {
+            let pid = fork();
+            assert!(pid >= 0);
+            if pid == 0 {
+                let ok = ::REFCOUNT.load(Ordering::SeqCst) == 0 &&
+                         ::context::current_pid() == process::id();
+                _exit(if ok { 0 } else { 1 });
+            }
+            ::REFCOUNT.fetch_sub(1, Ordering::SeqCst);
+
+            let mut status = 0;
+            assert_eq!(pid, waitpid(pid, &mut status, 0));
+            assert_eq!(0, status);
+        }
+    }
+}
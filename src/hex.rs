@@ -0,0 +1,113 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Hex dumps for log_hex!.
+//
+// The bytes are split into records of at most CHUNK bytes, so that a large
+// buffer doesn't end up as one enormous message. Each record holds lines of 16
+// bytes in the style of `hexdump -C`, with offsets counted from the start of
+// the whole buffer. Dumps are formatted straight into the logger, so nothing
+// is allocated.
+
+use core::fmt;
+
+use {kv, LogLevel, LogLocation};
+
+const LINE: usize = 16;
+const CHUNK: usize = 16 * LINE;
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+pub fn __log_hex(level: LogLevel, target: &str, loc: &LogLocation, bytes: &[u8]) {
+    for (idx, chunk) in bytes.chunks(CHUNK).enumerate() {
+        let dump = HexDump {
+            bytes: chunk,
+            offset: idx * CHUNK,
+        };
+        ::__log(level, target, loc, format_args!("{}", dump),
+                &[("len", kv::Value::from(bytes.len()))]);
+    }
+}
+
+struct HexDump<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> fmt::Display for HexDump<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, line) in self.bytes.chunks(LINE).enumerate() {
+            if idx > 0 {
+                fmt.write_str("\n")?;
+            }
+            write!(fmt, "{:08x} ", self.offset + idx * LINE)?;
+            for col in 0..LINE {
+                if col % 8 == 0 {
+                    fmt.write_str(" ")?;
+                }
+                match line.get(col) {
+                    Some(byte) => write!(fmt, "{:02x} ", byte)?,
+                    None => fmt.write_str("   ")?,
+                }
+            }
+            fmt.write_str(" |")?;
+            for &byte in line {
+                let c = if (0x20..0x7f).contains(&byte) { byte as char } else { '.' };
+                write!(fmt, "{}", c)?;
+            }
+            fmt.write_str("|")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(feature = "freestanding")))]
+mod tests {
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    use LogLevel;
+    use super::HexDump;
+
+    #[test]
+    fn test_hex_dump() {
+        let dump = HexDump {
+            bytes: b"GET / HTTP/1.1\r\nHost: a\r\n",
+            offset: 0x100,
+        };
+        assert_eq!("00000100  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  \
+                    |GET / HTTP/1.1..|\n\
+                    00000110  48 6f 73 74 3a 20 61 0d  0a                       \
+                    |Host: a..|",
+                   dump.to_string());
+    }
+
+    #[test]
+    fn test_trace_hex() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        let buf = (0..300).map(|i| i as u8).collect::<Vec<u8>>();
+        trace_hex!(target: "hex_test", &buf[..]);
+        log_hex!(LogLevel::Debug, &buf[..0]);
+
+        let records = ::test::drain();
+        assert_eq!(2, records.len());
+        assert_eq!(LogLevel::Trace, records[0].level());
+        assert_eq!("hex_test", records[0].target());
+        assert_eq!(16, records[0].message().lines().count());
+        assert!(records[0].message().starts_with("00000000  00 01 02 03"));
+        assert!(records[1].message().starts_with("00000100  00 01 02 03"));
+        assert_eq!(3, records[1].message().lines().count());
+        assert_eq!(vec![("len".to_string(), "300".to_string())], records[1].key_values());
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Field names for loggers writing to the systemd journal.
+//!
+//! A journal entry is a set of `NAME=value` fields. These are the names a
+//! journald logger should use for the parts of a `LogRecord`, so that entries
+//! written by different loggers can be queried in the same way. The value of
+//! the `PRIORITY` field is given by `LogLevel::to_journald_priority`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use log::{journald, LogLevel};
+//!
+//! let priority = format!("{}={}", journald::PRIORITY, LogLevel::Warn.to_journald_priority());
+//! assert_eq!("PRIORITY=4", priority);
+//! ```
+
+/// The formatted message of the record.
+pub const MESSAGE: &str = "MESSAGE";
+
+/// The level of the record, as a number from 0 to 7.
+pub const PRIORITY: &str = "PRIORITY";
+
+/// The file in which the record was logged.
+pub const CODE_FILE: &str = "CODE_FILE";
+
+/// The line on which the record was logged.
+pub const CODE_LINE: &str = "CODE_LINE";
+
+/// The module in which the record was logged.
+///
+/// This isn't one of the fields defined by systemd, which has no notion of
+/// modules, but is the name conventionally used for them.
+pub const CODE_MODULE: &str = "CODE_MODULE";
+
+/// The target of the record.
+///
+/// Like `CODE_MODULE`, this isn't defined by systemd.
+pub const TARGET: &str = "TARGET";
+
+/// The name of the program which logged the record.
+pub const SYSLOG_IDENTIFIER: &str = "SYSLOG_IDENTIFIER";
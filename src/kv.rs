@@ -0,0 +1,383 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structured key-value pairs attached to log records.
+//!
+//! Each `LogRecord` carries a (possibly empty) list of key-value pairs in
+//! addition to its formatted message. Loggers can walk these pairs with a
+//! `Visitor` to produce structured output such as JSON or journald fields.
+//! None of the types in this module allocate, so they are available in
+//! `freestanding` builds as well.
+//!
+//! With the `serde` feature enabled, values can also be captured through their
+//! `serde::Serialize` implementation, and every `Value` implements
+//! `Serialize` so that sinks can emit JSON, msgpack, or any other format
+//! supported by serde.
+//!
+//! An error can be attached to a record by starting the arguments of a logging
+//! macro with `err = &e;`. It is stored under the `err` key, and kept as an
+//! `Error` trait object so that sinks can walk its chain of causes.
+
+use core::fmt;
+#[cfg(not(feature = "freestanding"))]
+use std::error;
+
+#[cfg(feature = "serde")]
+use serde;
+
+/// A value attached to a log record.
+///
+/// Primitive values are stored directly, while anything else is captured by
+/// reference through its `Display` or `Debug` implementation.
+#[derive(Copy, Clone)]
+pub enum Value<'a> {
+    /// A string slice.
+    Str(&'a str),
+    /// A signed integer.
+    I64(i64),
+    /// An unsigned integer.
+    U64(u64),
+    /// A floating point number.
+    F64(f64),
+    /// A boolean.
+    Bool(bool),
+    /// A value formatted through its `Display` implementation.
+    Display(&'a dyn fmt::Display),
+    /// A value formatted through its `Debug` implementation.
+    Debug(&'a dyn fmt::Debug),
+    /// A value serialized through its `serde::Serialize` implementation.
+    #[cfg(feature = "serde")]
+    Serde(&'a dyn Serializable),
+    /// An error, formatted through its `Display` implementation.
+    #[cfg(not(feature = "freestanding"))]
+    Error(&'a (dyn error::Error + 'a)),
+}
+
+impl<'a> Value<'a> {
+    /// Captures a value through its `Display` implementation.
+    pub fn from_display<T: fmt::Display>(value: &'a T) -> Value<'a> {
+        Value::Display(value)
+    }
+
+    /// Captures a value through its `Debug` implementation.
+    pub fn from_debug<T: fmt::Debug>(value: &'a T) -> Value<'a> {
+        Value::Debug(value)
+    }
+
+    /// Captures a value through its `serde::Serialize` implementation.
+    ///
+    /// The value's `Debug` implementation is used when it is formatted as
+    /// text.
+    #[cfg(feature = "serde")]
+    pub fn from_serde<T: serde::Serialize + fmt::Debug>(value: &'a T) -> Value<'a> {
+        Value::Serde(value)
+    }
+
+    /// Captures an error.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn from_error(value: &'a (dyn error::Error + 'a)) -> Value<'a> {
+        Value::Error(value)
+    }
+
+    /// Returns the error this value captured, if it is one.
+    ///
+    /// Its `cause` method gives access to the errors which led to it.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn as_error(&self) -> Option<&'a (dyn error::Error + 'a)> {
+        match *self {
+            Value::Error(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Str(v) => fmt::Display::fmt(v, fmt),
+            Value::I64(v) => fmt::Display::fmt(&v, fmt),
+            Value::U64(v) => fmt::Display::fmt(&v, fmt),
+            Value::F64(v) => fmt::Display::fmt(&v, fmt),
+            Value::Bool(v) => fmt::Display::fmt(&v, fmt),
+            Value::Display(v) => v.fmt(fmt),
+            Value::Debug(v) => v.fmt(fmt),
+            #[cfg(feature = "serde")]
+            Value::Serde(v) => v.fmt(fmt),
+            #[cfg(not(feature = "freestanding"))]
+            Value::Error(v) => fmt::Display::fmt(v, fmt),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Value<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Str(v) => fmt::Debug::fmt(v, fmt),
+            Value::I64(v) => fmt::Debug::fmt(&v, fmt),
+            Value::U64(v) => fmt::Debug::fmt(&v, fmt),
+            Value::F64(v) => fmt::Debug::fmt(&v, fmt),
+            Value::Bool(v) => fmt::Debug::fmt(&v, fmt),
+            Value::Display(v) => v.fmt(fmt),
+            Value::Debug(v) => v.fmt(fmt),
+            #[cfg(feature = "serde")]
+            Value::Serde(v) => v.fmt(fmt),
+            #[cfg(not(feature = "freestanding"))]
+            Value::Error(v) => fmt::Debug::fmt(v, fmt),
+        }
+    }
+}
+
+/// A value which can be both serialized and formatted for debugging.
+///
+/// This is implemented for every type that implements `serde::Serialize` and
+/// `fmt::Debug`, and exists so that such values can be stored as trait
+/// objects.
+#[cfg(feature = "serde")]
+pub trait Serializable: erased_serde::Serialize + fmt::Debug {}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + fmt::Debug> Serializable for T {}
+
+#[cfg(feature = "serde")]
+serialize_trait_object!(Serializable);
+
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for Value<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        match *self {
+            Value::Str(v) => serializer.serialize_str(v),
+            Value::I64(v) => serializer.serialize_i64(v),
+            Value::U64(v) => serializer.serialize_u64(v),
+            Value::F64(v) => serializer.serialize_f64(v),
+            Value::Bool(v) => serializer.serialize_bool(v),
+            Value::Display(v) => serializer.collect_str(v),
+            Value::Debug(v) => serializer.collect_str(&format_args!("{:?}", v)),
+            Value::Serde(v) => v.serialize(serializer),
+            #[cfg(not(feature = "freestanding"))]
+            Value::Error(v) => {
+                use serde::ser::SerializeStruct;
+
+                let mut state = serializer.serialize_struct("Error", 2)?;
+                state.serialize_field("message", &format_args!("{}", v))?;
+                state.serialize_field("causes", &Causes(v))?;
+                state.end()
+            }
+        }
+    }
+}
+
+// Serializes the messages of the causes of an error, innermost last.
+#[cfg(all(feature = "serde", not(feature = "freestanding")))]
+struct Causes<'a>(&'a (dyn error::Error + 'a));
+
+#[cfg(all(feature = "serde", not(feature = "freestanding")))]
+impl<'a> serde::Serialize for Causes<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        let mut cause = self.0.source();
+        while let Some(err) = cause {
+            seq.serialize_element(&format_args!("{}", err))?;
+            cause = err.source();
+        }
+        seq.end()
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(v: &'a str) -> Value<'a> {
+        Value::Str(v)
+    }
+}
+
+impl<'a> From<bool> for Value<'a> {
+    fn from(v: bool) -> Value<'a> {
+        Value::Bool(v)
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident, $repr:ty, $($t:ty),+) => {
+        $(
+            impl<'a> From<$t> for Value<'a> {
+                fn from(v: $t) -> Value<'a> {
+                    Value::$variant(v as $repr)
+                }
+            }
+        )+
+    }
+}
+
+impl_from!(I64, i64, i8, i16, i32, i64, isize);
+impl_from!(U64, u64, u8, u16, u32, u64, usize);
+impl_from!(F64, f64, f32, f64);
+
+/// A visitor over the key-value pairs of a log record.
+pub trait Visitor {
+    /// Visits a single key-value pair.
+    fn visit_pair(&mut self, key: &str, value: Value);
+}
+
+/// The key-value pairs attached to a log record.
+#[derive(Copy, Clone)]
+pub struct KeyValues<'a> {
+    pairs: &'a [(&'a str, Value<'a>)],
+}
+
+impl<'a> KeyValues<'a> {
+    /// Creates a set of key-value pairs from a slice.
+    pub fn new(pairs: &'a [(&'a str, Value<'a>)]) -> KeyValues<'a> {
+        KeyValues { pairs }
+    }
+
+    /// Calls `visitor` with each pair, in the order they were specified.
+    pub fn visit(&self, visitor: &mut dyn Visitor) {
+        for &(key, value) in self.pairs {
+            visitor.visit_pair(key, value);
+        }
+    }
+
+    /// Returns the value of the first pair with the given key, if any.
+    pub fn get(&self, key: &str) -> Option<Value<'a>> {
+        self.pairs.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v)
+    }
+
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns `true` if there are no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl<'a> fmt::Debug for KeyValues<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_map()
+           .entries(self.pairs.iter().map(|&(k, ref v)| (k, v)))
+           .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use collections::string::{String, ToString};
+    use super::{KeyValues, Value, Visitor};
+
+    struct Collect(String);
+
+    impl Visitor for Collect {
+        fn visit_pair(&mut self, key: &str, value: Value) {
+            self.0.push_str(key);
+            self.0.push('=');
+            self.0.push_str(&value.to_string());
+            self.0.push(' ');
+        }
+    }
+
+    #[test]
+    fn test_value_display() {
+        assert_eq!("foo", Value::from("foo").to_string());
+        assert_eq!("-3", Value::from(-3i8).to_string());
+        assert_eq!("7", Value::from(7usize).to_string());
+        assert_eq!("true", Value::from(true).to_string());
+        assert_eq!("\"bar\"", Value::from_debug(&"bar").to_string());
+        assert_eq!("bar", Value::from_display(&"bar").to_string());
+    }
+
+    #[test]
+    fn test_visit() {
+        let pairs = [("a", Value::from(1u32)), ("b", Value::from("two"))];
+        let kvs = KeyValues::new(&pairs);
+        let mut collect = Collect(String::new());
+        kvs.visit(&mut collect);
+        assert_eq!("a=1 b=two ", collect.0);
+        assert_eq!(2, kvs.len());
+        assert_eq!("two", kvs.get("b").unwrap().to_string());
+        assert!(kvs.get("c").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_value_serialize() {
+        use serde_json;
+
+        #[derive(Debug)]
+        struct Point(i32, i32);
+
+        impl ::serde::Serialize for Point {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where S: ::serde::Serializer
+            {
+                (self.0, self.1).serialize(serializer)
+            }
+        }
+
+        let point = Point(1, 2);
+        assert_eq!("[1,2]",
+                   serde_json::to_string(&Value::from_serde(&point)).unwrap());
+        assert_eq!("Point(1, 2)", Value::from_serde(&point).to_string());
+        assert_eq!("\"x\"", serde_json::to_string(&Value::from_display(&'x')).unwrap());
+        assert_eq!("5", serde_json::to_string(&Value::from(5u8)).unwrap());
+    }
+
+    #[cfg(not(feature = "freestanding"))]
+    #[test]
+    #[allow(deprecated)]
+    fn test_error_value() {
+        use std::error::Error;
+        use std::fmt;
+
+        #[derive(Debug)]
+        struct Outer(Inner);
+        #[derive(Debug)]
+        struct Inner;
+
+        impl fmt::Display for Outer {
+            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("outer")
+            }
+        }
+
+        impl fmt::Display for Inner {
+            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("inner")
+            }
+        }
+
+        impl Error for Outer {
+            fn description(&self) -> &str { "outer error" }
+            fn source(&self) -> Option<&(dyn Error + 'static)> { Some(&self.0) }
+        }
+
+        impl Error for Inner {
+            fn description(&self) -> &str { "inner error" }
+        }
+
+        let err = Outer(Inner);
+        let value = Value::from_error(&err);
+        assert_eq!("outer", value.to_string());
+        let err = value.as_error().unwrap();
+        assert_eq!("outer error", err.description());
+        assert_eq!("inner", err.source().unwrap().to_string());
+        assert!(Value::from(1u8).as_error().is_none());
+
+        #[cfg(feature = "serde")]
+        assert_eq!("{\"message\":\"outer\",\"causes\":[\"inner\"]}",
+                   ::serde_json::to_string(&value).unwrap());
+    }
+}
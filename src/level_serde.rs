@@ -0,0 +1,124 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// serde support for LogLevel and LogLevelFilter.
+//
+// Levels are serialized as their names. They are deserialized from anything
+// FromStr accepts, or from their numbers, but only formats which describe
+// their own types can say which they have, so other formats only get names.
+
+use core::fmt;
+use core::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, Unexpected, Visitor};
+
+use {LogLevel, LogLevelFilter};
+
+impl Serialize for LogLevel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Serialize for LogLevelFilter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for LogLevel {
+    fn deserialize<D>(deserializer: D) -> Result<LogLevel, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserialize(deserializer, LevelVisitor("a log level", LogLevel::from_usize))
+    }
+}
+
+impl<'de> Deserialize<'de> for LogLevelFilter {
+    fn deserialize<D>(deserializer: D) -> Result<LogLevelFilter, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserialize(deserializer,
+                    LevelVisitor("a log level filter", LogLevelFilter::from_usize))
+    }
+}
+
+fn deserialize<'de, D, T>(deserializer: D, visitor: LevelVisitor<T>) -> Result<T, D::Error>
+    where D: Deserializer<'de>,
+          T: FromStr
+{
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(visitor)
+    } else {
+        deserializer.deserialize_str(visitor)
+    }
+}
+
+// What is expected, and the from_usize of the type being deserialized.
+struct LevelVisitor<T>(&'static str, fn(usize) -> Option<T>);
+
+impl<'de, T: FromStr> Visitor<'de> for LevelVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} name or number", self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        value.parse().map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+        if value <= 9 {
+            if let Some(level) = (self.1)(value as usize) {
+                return Ok(level);
+            }
+        }
+        Err(E::invalid_value(Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<T, E> {
+        if value < 0 {
+            return Err(E::invalid_value(Unexpected::Signed(value), &self));
+        }
+        self.visit_u64(value as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use {LogLevel, LogLevelFilter};
+
+    #[test]
+    fn test_level_serde() {
+        assert_eq!("\"WARN\"", serde_json::to_string(&LogLevel::Warn).unwrap());
+        assert_eq!("\"OFF\"", serde_json::to_string(&LogLevelFilter::Off).unwrap());
+
+        assert_eq!(LogLevel::Info, serde_json::from_str::<LogLevel>("\"info\"").unwrap());
+        assert_eq!(LogLevel::Debug, serde_json::from_str::<LogLevel>("6").unwrap());
+        assert_eq!(LogLevel::Critical, serde_json::from_str::<LogLevel>("\"fatal\"").unwrap());
+        assert_eq!(LogLevelFilter::Off, serde_json::from_str::<LogLevelFilter>("0").unwrap());
+        assert_eq!(LogLevelFilter::Warn,
+                   serde_json::from_str::<LogLevelFilter>("\"warning\"").unwrap());
+
+        assert!(serde_json::from_str::<LogLevel>("0").is_err());
+        assert!(serde_json::from_str::<LogLevel>("\"OFF\"").is_err());
+        assert!(serde_json::from_str::<LogLevelFilter>("-1").is_err());
+        assert!(serde_json::from_str::<LogLevelFilter>("10").is_err());
+        assert!(serde_json::from_str::<LogLevelFilter>("true").is_err());
+    }
+}
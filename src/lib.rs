@@ -93,7 +93,7 @@
 //! # Logger implementations
 //!
 //! Loggers implement the `Log` trait. Here's a very basic example that simply
-//! logs all messages at the `Error`, `Warn` or `Info` levels to stdout:
+//! logs all messages at the `Info` level or above to stdout:
 //!
 //! ```rust
 //! extern crate log;
@@ -117,7 +117,8 @@
 //! # fn main() {}
 //! ```
 //!
-//! Loggers are installed by calling the `set_logger` function. It takes a
+//! Loggers are installed by calling the `set_logger` function, or
+//! `set_boxed_logger` for loggers that are constructed at runtime. It takes a
 //! closure which is provided a `MaxLogLevel` token and returns a `Log` trait
 //! object. The `MaxLogLevel` token controls the global maximum log level. The
 //! logging facade uses this as an optimization to improve performance of log
@@ -126,6 +127,23 @@
 //! `Debug` or `Trace` level log messages. A logging framework should provide a
 //! function that wraps a call to `set_logger`, handling initialization of the
 //! logger.
+//!
+//! # Leaking the logger
+//!
+//! By default, every log call keeps track of how many threads are currently
+//! inside the logger, so that `shutdown_logger` and `swap_logger` can safely
+//! hand the old logger back. Programs which never remove their logger can
+//! enable the `leak_logger` feature, which removes those two functions along
+//! with the bookkeeping, making each log call slightly cheaper. The installed
+//! logger is then simply leaked when the program exits.
+//!
+//! # Targets without compare-and-swap
+//!
+//! Some cores, such as `thumbv6` microcontrollers, have atomic loads and
+//! stores but no atomic read-modify-write instructions. The `racy` feature,
+//! which implies `freestanding`, builds the crate without any such
+//! instructions. With it, `set_logger` is unavailable and the logger must be
+//! installed with the unsafe `set_logger_racy` instead.
 #![doc(html_logo_url = "https://www.rust-lang.org/logos/rust-logo-128x128-blk-v2.png",
        html_favicon_url = "https://www.rust-lang.org/favicon.ico",
        html_root_url = "https://doc.rust-lang.org/log/")]
@@ -135,79 +153,289 @@
 #![feature(no_std)]
 #![feature(core_slice_ext)]
 #![feature(collections)]
+#![cfg_attr(feature = "oom_hook", feature(alloc_error_hook))]
 #![no_std]
 
 #[cfg(not(feature = "freestanding"))]
-extern crate libc;
-#[cfg(not(feature = "freestanding"))]
+#[macro_use]
 extern crate std;
-#[cfg(test)]
+#[cfg(any(test, all(feature = "freestanding", feature = "alloc")))]
 extern crate collections;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate erased_serde;
+#[cfg(any(feature = "json", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(feature = "toml")]
+extern crate toml;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(all(feature = "futures", not(feature = "freestanding")))]
+extern crate futures;
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+extern crate backtrace;
 
-#[cfg(not(feature = "freestanding"))]
-use std::ascii::AsciiExt;
 #[cfg(not(feature = "freestanding"))]
 use std::error;
 #[cfg(not(feature = "freestanding"))]
 use std::boxed::Box;
+#[cfg(not(feature = "freestanding"))]
+use std::string::{String, ToString};
+#[cfg(all(feature = "freestanding", feature = "alloc"))]
+use collections::string::{String, ToString};
+#[cfg(not(feature = "freestanding"))]
+use std::vec::Vec;
+#[cfg(not(feature = "freestanding"))]
+use std::sync::Arc;
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+use std::thread;
+#[cfg(not(feature = "freestanding"))]
+use std::thread::Thread;
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+use backtrace::Backtrace;
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+use std::time::{Duration, Instant};
+#[cfg(not(feature = "freestanding"))]
+use std::time::SystemTime;
 
 use core::cmp;
+use core::convert::TryFrom;
 use core::fmt;
+use core::hash::{Hash, Hasher};
+#[cfg(not(feature = "freestanding"))]
 use core::mem;
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+use core::ptr;
+#[cfg(not(feature = "freestanding"))]
+use std::cell::Cell;
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+use std::cell::UnsafeCell;
 use core::ops::Deref;
 use core::str::FromStr;
-use core::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
+#[macro_use]
 mod macros;
+pub mod kv;
+mod callsite;
+#[cfg(feature = "serde")]
+mod level_serde;
+mod severity;
+mod mask;
+pub mod journald;
+pub mod combinators;
+mod ext;
+mod hex;
+#[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+pub mod filter;
+#[cfg(not(feature = "freestanding"))]
+mod targets;
+#[cfg(not(feature = "freestanding"))]
+mod early;
+#[cfg(not(feature = "freestanding"))]
+mod context;
+#[cfg(not(feature = "freestanding"))]
+pub mod mdc;
+#[cfg(not(feature = "freestanding"))]
+mod prefix;
+#[cfg(not(feature = "freestanding"))]
+mod span;
+#[cfg(not(feature = "freestanding"))]
+mod sample;
+#[cfg(not(feature = "freestanding"))]
+mod limit;
+#[cfg(feature = "statistics")]
+mod stats;
+#[cfg(not(feature = "freestanding"))]
+mod async_log;
+#[cfg(not(feature = "freestanding"))]
+mod panic_hook;
+#[cfg(all(feature = "oom_hook", not(feature = "freestanding")))]
+mod oom;
+#[cfg(all(unix, not(feature = "freestanding")))]
+pub mod signal_safe;
+#[cfg(all(unix, feature = "fork_safety", not(feature = "freestanding")))]
+mod fork;
+#[cfg(all(unix, not(feature = "freestanding")))]
+mod sighup;
+#[cfg(all(feature = "simple_logger", not(feature = "freestanding")))]
+mod simple;
+#[cfg(not(feature = "freestanding"))]
+pub mod test;
 
-// The setup here is a bit weird to make at_exit work.
+pub use combinators::from_fn;
+pub use mask::LevelMask;
+pub use ext::{ResultExt, OptionExt};
+#[doc(hidden)]
+pub use callsite::{CallsiteState, __CALLSITE_STATE_INIT};
+#[doc(hidden)]
+pub use hex::__log_hex;
+pub use callsite::{Callsite, rebuild_interest_cache};
+#[cfg(not(feature = "freestanding"))]
+pub use callsite::{Callsites, callsites};
+#[cfg(not(feature = "freestanding"))]
+pub use targets::{set_target_max_level, set_target_levels, remove_target_max_level,
+                  clear_target_max_levels, target_max_level, target_levels};
+#[cfg(not(feature = "freestanding"))]
+pub use early::buffer_early_records;
+#[cfg(not(feature = "freestanding"))]
+pub use prefix::PrefixLogger;
+#[cfg(not(feature = "freestanding"))]
+pub use span::{Span, current_span_id};
+#[cfg(not(feature = "freestanding"))]
+pub use sample::Sampler;
+#[cfg(not(feature = "freestanding"))]
+#[doc(hidden)]
+pub use sample::__SAMPLER_INIT;
+#[cfg(not(feature = "freestanding"))]
+#[doc(hidden)]
+pub use limit::{Counter, RateLimit, __COUNTER_INIT, __RATE_LIMIT_INIT};
+#[cfg(feature = "statistics")]
+pub use stats::{Statistics, statistics};
+#[cfg(all(feature = "statistics", not(feature = "freestanding")))]
+pub use stats::{count_targets, target_statistics};
+#[cfg(not(feature = "freestanding"))]
+pub use async_log::{AsyncLog, RingLog, Overflow};
+#[cfg(not(feature = "freestanding"))]
+pub use panic_hook::install_panic_hook;
+#[cfg(all(feature = "oom_hook", not(feature = "freestanding")))]
+pub use oom::install_oom_hook;
+#[cfg(all(unix, not(feature = "freestanding")))]
+pub use sighup::install_sighup_handler;
+#[cfg(not(feature = "freestanding"))]
+pub use context::{push_scope, ScopeGuard, LogContext, ContextGuard,
+                  set_current_trace_id, current_trace_id};
+#[cfg(all(feature = "futures", not(feature = "freestanding")))]
+pub use context::WithContext;
+#[cfg(all(feature = "simple_logger", not(feature = "freestanding")))]
+pub use simple::{SimpleLogger, init_simple};
+
+// The setup here is a bit weird to make shutdown_logger and swap_logger work.
 //
 // There are four different states that we care about: the logger's
 // uninitialized, the logger's initializing (set_logger's been called but
-// LOGGER hasn't actually been set yet), the logger's active, or the logger's
-// shutting down inside of at_exit.
+// LOGGER hasn't actually been set yet, or the logger is being swapped or shut
+// down), the logger's active, or the logger's been shut down by
+// shutdown_logger.
 //
-// The LOGGER static is normally a Box<Box<Log>> with some special possible
-// values as well. The uninitialized and initializing states are represented by
-// the values 0 and 1 respectively. The shutting down state is also represented
-// by 1. Any other value is a valid pointer to the logger.
+// The STATE static tracks which of these we're in, as the values 0, 1, 2 and
+// 3 respectively. LOGGER may only be read while STATE is 2, and may only be
+// written by whoever moved STATE to 1.
 //
-// The at_exit routine needs to make sure that no threads are actively logging
-// when it deallocates the logger. The number of actively logging threads is
-// tracked in the REFCOUNT static. The routine first sets LOGGER back to 1.
-// All logging calls past that point will immediately return without accessing
-// the logger. At that point, the at_exit routine just waits for the refcount
-// to reach 0 before deallocating the logger. Note that the refcount does not
-// necessarily monotonically decrease at this point, as new log calls still
-// increment and decrement it, but the interval in between is small enough that
-// the wait is really just for the active log calls to finish.
-static LOGGER: AtomicUsize = ATOMIC_USIZE_INIT;
-// when freestanding, do not refcount the logger instance
-#[cfg(not(feature = "freestanding"))]
-static REFCOUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+// shutdown_logger and swap_logger need to make sure that no threads are
+// actively logging when they hand the old logger back. The number of actively
+// logging threads is tracked in the REFCOUNT static. The routines first set
+// STATE back to 1. All logging calls past that point will immediately return
+// without accessing the logger. At that point, the routine just waits for the
+// refcount to reach 0 before taking the logger out of LOGGER. Note that the
+// refcount does not necessarily monotonically decrease at this point, as new
+// log calls still increment and decrement it, but the interval in between is
+// small enough that the wait is really just for the active log calls to
+// finish. Once shut down, STATE stays at 3 so the logger can't be
+// reinitialized.
+//
+// Logging calls increment REFCOUNT and then load STATE, while shutdown_logger
+// and swap_logger move STATE away from 2 and then load REFCOUNT. Each side
+// writes one location and then reads the other, so these four operations
+// must all be SeqCst: with anything weaker, both sides could miss the
+// other's write, and a logger could be freed while a call is still inside it.
+// Everything else on the logging path is weaker. The decrement in
+// LoggerGuard's destructor is Release, which is enough to order the call's
+// use of the logger before the waiting thread's load of REFCOUNT, and calls
+// that back out without touching the logger decrement with Relaxed.
+//
+// Each thread also caches the logger it last read from LOGGER in
+// CACHED_LOGGER, tagged with the value LOGGER_EPOCH had when it was read, and
+// uses it instead of loading STATE and LOGGER while the epoch hasn't changed.
+// shutdown_logger and swap_logger bump the epoch after moving STATE away from
+// 2 and before loading REFCOUNT, and logging calls load it after incrementing
+// REFCOUNT, so this is the same pattern as above, and the load is SeqCst too.
+// A call which still sees the epoch its cached logger was read under is
+// therefore waited for before that logger is freed. The refcount can't be
+// skipped for cached loggers, as it's what makes this safe.
+//
+// With the leak_logger feature, shutdown_logger and swap_logger don't exist,
+// so an installed logger is never freed. Logging calls then don't need to
+// touch REFCOUNT at all, saving two read-modify-write operations per call,
+// and only need an Acquire load of STATE to see the write to LOGGER.
+static mut LOGGER: &'static dyn Log = &NopLogger;
+// how LOGGER is owned, so that shutdown_logger can hand it back
+#[cfg(not(feature = "freestanding"))]
+static mut LOGGER_OWNERSHIP: Ownership = Ownership::Static;
+static STATE: AtomicUsize = AtomicUsize::new(0);
+// when freestanding, or when loggers are leaked, do not refcount the logger
+// instance
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+static REFCOUNT: AtomicUsize = AtomicUsize::new(0);
+// bumped whenever LOGGER may change, invalidating every thread's cached copy
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+static LOGGER_EPOCH: AtomicUsize = AtomicUsize::new(0);
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+thread_local! {
+    static CACHED_LOGGER: Cell<Option<(usize, &'static dyn Log)>> = const { Cell::new(None) }
+}
 
 const UNINITIALIZED: usize = 0;
 const INITIALIZING: usize = 1;
-
-static MAX_LOG_LEVEL_FILTER: AtomicUsize = ATOMIC_USIZE_INIT;
-
-static LOG_LEVEL_NAMES: [&'static str; 6] = ["OFF", "ERROR", "WARN", "INFO",
-                                             "DEBUG", "TRACE"];
+const INITIALIZED: usize = 2;
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+const SHUT_DOWN: usize = 3;
+
+static MAX_LOG_LEVEL_FILTER: AtomicUsize = AtomicUsize::new(0);
+
+static LOG_LEVEL_NAMES: [&str; 10] = ["OFF", "CRITICAL", "ERROR", "WARN",
+                                      "NOTICE", "INFO", "DEBUG", "TRACE",
+                                      "TRACE2", "TRACE3"];
+
+// The names printed by the alternate form of Display.
+static LOWERCASE_LOG_LEVEL_NAMES: [&str; 10] = ["off", "critical", "error", "warn",
+                                                "notice", "info", "debug",
+                                                "trace", "trace2", "trace3"];
+
+// Other names for levels used by other logging systems, which FromStr also
+// accepts.
+static LOG_LEVEL_ALIASES: [(&str, LogLevelFilter); 6] = [
+    ("CRIT", LogLevelFilter::Critical),
+    ("FATAL", LogLevelFilter::Critical),
+    ("ERR", LogLevelFilter::Error),
+    ("WARNING", LogLevelFilter::Warn),
+    ("INFORMATION", LogLevelFilter::Info),
+    ("VERBOSE", LogLevelFilter::Trace),
+];
 
 /// An enum representing the available verbosity levels of the logging framework
 ///
 /// A `LogLevel` may be compared directly to a `LogLevelFilter`.
+///
+/// Each level has a number, given by `as usize`, which counts up from 1 for
+/// `Critical` to 9 for `Trace3` in order of decreasing severity. `from_usize`
+/// and `from_str` accept these numbers, so `Error` is 2, `Warn` is 3, `Info`
+/// is 5, `Debug` is 6 and `Trace` is 7.
+///
+/// With the `serde` feature, a `LogLevel` is serialized as its name, and may
+/// be deserialized from anything `from_str` accepts, or from its number.
 #[repr(usize)]
 #[derive(Copy, Eq, Debug)]
 pub enum LogLevel {
+    /// The "critical" level.
+    ///
+    /// Designates unrecoverable errors, after which the program or some part
+    /// of it can't continue.
+    Critical = 1, // This way these line up with the discriminants for LogLevelFilter below
     /// The "error" level.
     ///
     /// Designates very serious errors.
-    Error = 1, // This way these line up with the discriminants for LogLevelFilter below
+    Error,
     /// The "warn" level.
     ///
     /// Designates hazardous situations.
     Warn,
+    /// The "notice" level.
+    ///
+    /// Designates normal but significant conditions.
+    Notice,
     /// The "info" level.
     ///
     /// Designates useful information.
@@ -220,6 +448,16 @@ pub enum LogLevel {
     ///
     /// Designates very low priority, often extremely verbose, information.
     Trace,
+    /// The "trace2" level.
+    ///
+    /// Designates information too verbose even for the trace level, such as
+    /// the contents of messages passing through a protocol.
+    Trace2,
+    /// The "trace3" level.
+    ///
+    /// Designates the most verbose information of all, such as every byte
+    /// read or written.
+    Trace3,
 }
 
 impl Clone for LogLevel {
@@ -264,6 +502,13 @@ impl Ord for LogLevel {
     }
 }
 
+impl Hash for LogLevel {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self as usize).hash(state)
+    }
+}
+
 fn ok_or<T, E>(t: Option<T>, e: E) -> Result<T, E> {
     match t {
         Some(t) => Ok(t),
@@ -271,46 +516,135 @@ fn ok_or<T, E>(t: Option<T>, e: E) -> Result<T, E> {
     }
 }
 
+/// The type returned by `from_str` when a string can't be parsed as a
+/// `LogLevel` or `LogLevelFilter`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseLevelError {
+    #[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+    level: String,
+}
+
+impl ParseLevelError {
+    #[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+    fn new(level: &str) -> ParseLevelError {
+        ParseLevelError { level: level.to_string() }
+    }
+
+    // there's nowhere to keep the string without an allocator
+    #[cfg(all(feature = "freestanding", not(feature = "alloc")))]
+    fn new(_: &str) -> ParseLevelError {
+        ParseLevelError {}
+    }
+
+    /// Returns the string which couldn't be parsed.
+    ///
+    /// This is not available when freestanding without the `alloc` feature.
+    #[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+    pub fn level(&self) -> &str {
+        &self.level
+    }
+}
+
+impl fmt::Display for ParseLevelError {
+    #[cfg(any(not(feature = "freestanding"), feature = "alloc"))]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid log level `{}`", self.level)
+    }
+
+    #[cfg(all(feature = "freestanding", not(feature = "alloc")))]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid log level")
+    }
+}
+
+// only implement the Error trait when it's available
 #[cfg(not(feature = "freestanding"))]
-impl FromStr for LogLevel {
-    type Err = ();
-    fn from_str(level: &str) -> Result<LogLevel, ()> {
-        ok_or(LOG_LEVEL_NAMES.iter()
-              .position(|&name| name.eq_ignore_ascii_case(level))
-              .into_iter()
-              .filter(|&idx| idx != 0)
-              .map(|idx| LogLevel::from_usize(idx).unwrap())
-              .next(), ())
+impl error::Error for ParseLevelError {
+    fn description(&self) -> &str { "invalid log level" }
+}
+
+// Parses a level name or alias, or a level's number from "0" for Off to "9"
+// for Trace3.
+fn parse_level(level: &str) -> Option<LogLevelFilter> {
+    LOG_LEVEL_NAMES.iter()
+                   .position(|&name| name_matches(name, level))
+                   .or_else(|| parse_level_number(level))
+                   .and_then(LogLevelFilter::from_usize)
+                   .or_else(|| {
+                       LOG_LEVEL_ALIASES.iter()
+                                        .find(|&&(name, _)| name_matches(name, level))
+                                        .map(|&(_, filter)| filter)
+                   })
+}
+
+// Compares an upper case level name to level, ignoring the case of level.
+// This only needs core, unlike AsciiExt.
+fn name_matches(name: &str, level: &str) -> bool {
+    name.len() == level.len() &&
+        name.bytes().zip(level.bytes()).all(|(a, b)| a == b.to_ascii_uppercase())
+}
+
+fn parse_level_number(level: &str) -> Option<usize> {
+    let bytes = level.as_bytes();
+    if bytes.len() == 1 && b'0' <= bytes[0] && bytes[0] <= b'9' {
+        Some((bytes[0] - b'0') as usize)
+    } else {
+        None
     }
 }
 
-#[cfg(feature = "freestanding")]
+/// Parses a level from its name or from its number as given by `as usize`,
+/// so `"warn"` and `"3"` both give `LogLevel::Warn`. Names are matched
+/// ignoring case.
+///
+/// Some names used by other logging systems are also accepted: `CRIT` and
+/// `FATAL` for `Critical`, `ERR` for `Error`, `WARNING` for `Warn`,
+/// `INFORMATION` for `Info`, and `VERBOSE` for `Trace`.
 impl FromStr for LogLevel {
-    type Err = ();
-    fn from_str(level: &str) -> Result<LogLevel, ()> {
-        ok_or(LOG_LEVEL_NAMES.iter()
-              .position(|&name| name == level)
-              .into_iter()
-              .filter(|&idx| idx != 0)
-              .map(|idx| LogLevel::from_usize(idx).unwrap())
-              .next(), ())
+    type Err = ParseLevelError;
+    fn from_str(level: &str) -> Result<LogLevel, ParseLevelError> {
+        ok_or(parse_level(level).and_then(|filter| filter.to_log_level()),
+              ParseLevelError::new(level))
     }
 }
 
+/// Prints the level's name in upper case, or in lower case with the alternate
+/// flag, as in `{:#}`.
 impl fmt::Display for LogLevel {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.pad(LOG_LEVEL_NAMES[*self as usize])
+        if fmt.alternate() {
+            fmt.pad(LOWERCASE_LOG_LEVEL_NAMES[*self as usize])
+        } else {
+            fmt.pad(self.as_str())
+        }
     }
 }
 
 impl LogLevel {
-    fn from_usize(u: usize) -> Option<LogLevel> {
+    /// Converts a level's number, as given by `as usize`, back to the level.
+    ///
+    /// Returns `None` if `u` is not the number of a level.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevel;
+    ///
+    /// assert_eq!(Some(LogLevel::Warn), LogLevel::from_usize(LogLevel::Warn as usize));
+    /// assert_eq!(None, LogLevel::from_usize(0));
+    /// ```
+    #[inline]
+    pub fn from_usize(u: usize) -> Option<LogLevel> {
         match u {
-            1 => Some(LogLevel::Error),
-            2 => Some(LogLevel::Warn),
-            3 => Some(LogLevel::Info),
-            4 => Some(LogLevel::Debug),
-            5 => Some(LogLevel::Trace),
+            1 => Some(LogLevel::Critical),
+            2 => Some(LogLevel::Error),
+            3 => Some(LogLevel::Warn),
+            4 => Some(LogLevel::Notice),
+            5 => Some(LogLevel::Info),
+            6 => Some(LogLevel::Debug),
+            7 => Some(LogLevel::Trace),
+            8 => Some(LogLevel::Trace2),
+            9 => Some(LogLevel::Trace3),
             _ => None
         }
     }
@@ -318,7 +652,7 @@ impl LogLevel {
     /// Returns the most verbose logging level.
     #[inline]
     pub fn max() -> LogLevel {
-        LogLevel::Trace
+        LogLevel::Trace3
     }
 
     /// Converts the `LogLevel` to the equivalent `LogLevelFilter`.
@@ -326,27 +660,65 @@ impl LogLevel {
     pub fn to_log_level_filter(&self) -> LogLevelFilter {
         LogLevelFilter::from_usize(*self as usize).unwrap()
     }
+
+    /// Returns the name of the level, as used by `Display`, such as
+    /// `"INFO"`.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        LOG_LEVEL_NAMES[*self as usize]
+    }
+
+    /// Returns an iterator over every level, from `Critical` to `Trace3`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevel;
+    ///
+    /// let names = LogLevel::iter().map(|level| level.as_str()).collect::<Vec<_>>();
+    /// assert_eq!(vec!["CRITICAL", "ERROR", "WARN", "NOTICE", "INFO", "DEBUG", "TRACE",
+    ///                 "TRACE2", "TRACE3"],
+    ///            names);
+    /// ```
+    #[inline]
+    pub fn iter() -> LogLevelIter {
+        LogLevelIter { next: 1 }
+    }
 }
 
 /// An enum representing the available verbosity level filters of the logging
 /// framework.
 ///
 /// A `LogLevelFilter` may be compared directly to a `LogLevel`.
+///
+/// `Off` is numbered 0, and every other level filter has the same number as
+/// the corresponding `LogLevel`.
+///
+/// With the `serde` feature, a `LogLevelFilter` is serialized as its name, and
+/// may be deserialized from anything `from_str` accepts, or from its number.
 #[repr(usize)]
 #[derive(Copy, Eq, Debug)]
 pub enum LogLevelFilter {
     /// A level lower than all log levels.
     Off,
+    /// Corresponds to the `Critical` log level.
+    Critical,
     /// Corresponds to the `Error` log level.
     Error,
     /// Corresponds to the `Warn` log level.
     Warn,
+    /// Corresponds to the `Notice` log level.
+    Notice,
     /// Corresponds to the `Info` log level.
     Info,
     /// Corresponds to the `Debug` log level.
     Debug,
     /// Corresponds to the `Trace` log level.
     Trace,
+    /// Corresponds to the `Trace2` log level.
+    Trace2,
+    /// Corresponds to the `Trace3` log level.
+    Trace3,
 }
 
 // Deriving generates terrible impls of these traits
@@ -393,48 +765,84 @@ impl Ord for LogLevelFilter {
     }
 }
 
-#[cfg(not(feature = "freestanding"))]
-impl FromStr for LogLevelFilter {
-    type Err = ();
-    fn from_str(level: &str) -> Result<LogLevelFilter, ()> {
-        ok_or(LOG_LEVEL_NAMES.iter()
-              .position(|&name| name.eq_ignore_ascii_case(level))
-              .map(|p| LogLevelFilter::from_usize(p).unwrap()), ())
+impl Hash for LogLevelFilter {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self as usize).hash(state)
     }
 }
 
-#[cfg(feature = "freestanding")]
+/// Parses a level filter from its name or from its number as given by
+/// `as usize`, so `"off"` and `"0"` both give `LogLevelFilter::Off`. Names are
+/// matched ignoring case.
+///
+/// The same other names are accepted as for `LogLevel`.
 impl FromStr for LogLevelFilter {
-    type Err = ();
-    fn from_str(level: &str) -> Result<LogLevelFilter, ()> {
-        ok_or(LOG_LEVEL_NAMES.iter()
-              .position(|&name| name == level)
-              .map(|p| LogLevelFilter::from_usize(p).unwrap()), ())
+    type Err = ParseLevelError;
+    fn from_str(level: &str) -> Result<LogLevelFilter, ParseLevelError> {
+        ok_or(parse_level(level), ParseLevelError::new(level))
     }
 }
 
+/// Prints the level filter's name in upper case, or in lower case with the
+/// alternate flag, as in `{:#}`.
 impl fmt::Display for LogLevelFilter {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}", LOG_LEVEL_NAMES[*self as usize])
+        if fmt.alternate() {
+            fmt.pad(LOWERCASE_LOG_LEVEL_NAMES[*self as usize])
+        } else {
+            fmt.pad(self.as_str())
+        }
     }
 }
 
 impl LogLevelFilter {
-    fn from_usize(u: usize) -> Option<LogLevelFilter> {
+    /// Converts a level filter's number, as given by `as usize`, back to the
+    /// level filter.
+    ///
+    /// Returns `None` if `u` is not the number of a level filter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevelFilter;
+    ///
+    /// assert_eq!(Some(LogLevelFilter::Off), LogLevelFilter::from_usize(0));
+    /// assert_eq!(None, LogLevelFilter::from_usize(10));
+    /// ```
+    ///
+    /// A command line program can turn each `-v` flag into another level of
+    /// detail, up to `Trace3`:
+    ///
+    /// ```rust
+    /// use log::LogLevelFilter;
+    ///
+    /// let verbosity = 4; // -vvvv
+    /// let level = LogLevelFilter::from_usize(LogLevelFilter::Info as usize + verbosity)
+    ///     .unwrap_or(LogLevelFilter::max());
+    /// assert_eq!(LogLevelFilter::Trace3, level);
+    /// ```
+    #[inline]
+    pub fn from_usize(u: usize) -> Option<LogLevelFilter> {
         match u {
             0 => Some(LogLevelFilter::Off),
-            1 => Some(LogLevelFilter::Error),
-            2 => Some(LogLevelFilter::Warn),
-            3 => Some(LogLevelFilter::Info),
-            4 => Some(LogLevelFilter::Debug),
-            5 => Some(LogLevelFilter::Trace),
+            1 => Some(LogLevelFilter::Critical),
+            2 => Some(LogLevelFilter::Error),
+            3 => Some(LogLevelFilter::Warn),
+            4 => Some(LogLevelFilter::Notice),
+            5 => Some(LogLevelFilter::Info),
+            6 => Some(LogLevelFilter::Debug),
+            7 => Some(LogLevelFilter::Trace),
+            8 => Some(LogLevelFilter::Trace2),
+            9 => Some(LogLevelFilter::Trace3),
             _ => None
         }
     }
+
     /// Returns the most verbose logging level filter.
     #[inline]
     pub fn max() -> LogLevelFilter {
-        LogLevelFilter::Trace
+        LogLevelFilter::Trace3
     }
 
     /// Converts `self` to the equivalent `LogLevel`.
@@ -444,6 +852,89 @@ impl LogLevelFilter {
     pub fn to_log_level(&self) -> Option<LogLevel> {
         LogLevel::from_usize(*self as usize)
     }
+
+    /// Returns the name of the level filter, as used by `Display`, such as
+    /// `"OFF"`.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        LOG_LEVEL_NAMES[*self as usize]
+    }
+
+    /// Returns an iterator over every level filter, from `Off` to `Trace3`.
+    #[inline]
+    pub fn iter() -> LogLevelFilterIter {
+        LogLevelFilterIter { next: 0 }
+    }
+}
+
+/// An iterator over every `LogLevel`, returned by `LogLevel::iter`.
+#[derive(Clone, Debug)]
+pub struct LogLevelIter {
+    next: usize,
+}
+
+impl Iterator for LogLevelIter {
+    type Item = LogLevel;
+
+    fn next(&mut self) -> Option<LogLevel> {
+        let level = LogLevel::from_usize(self.next);
+        if level.is_some() {
+            self.next += 1;
+        }
+        level
+    }
+}
+
+/// An iterator over every `LogLevelFilter`, returned by
+/// `LogLevelFilter::iter`.
+#[derive(Clone, Debug)]
+pub struct LogLevelFilterIter {
+    next: usize,
+}
+
+impl Iterator for LogLevelFilterIter {
+    type Item = LogLevelFilter;
+
+    fn next(&mut self) -> Option<LogLevelFilter> {
+        let filter = LogLevelFilter::from_usize(self.next);
+        if filter.is_some() {
+            self.next += 1;
+        }
+        filter
+    }
+}
+
+impl From<LogLevel> for LogLevelFilter {
+    #[inline]
+    fn from(level: LogLevel) -> LogLevelFilter {
+        level.to_log_level_filter()
+    }
+}
+
+/// Fails for `LogLevelFilter::Off`, which has no matching level.
+impl TryFrom<LogLevelFilter> for LogLevel {
+    type Error = TryFromLevelFilterError;
+
+    #[inline]
+    fn try_from(filter: LogLevelFilter) -> Result<LogLevel, TryFromLevelFilterError> {
+        ok_or(filter.to_log_level(), TryFromLevelFilterError(()))
+    }
+}
+
+/// The error returned when converting `LogLevelFilter::Off` to a `LogLevel`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TryFromLevelFilterError(());
+
+impl fmt::Display for TryFromLevelFilterError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "the `Off` level filter has no matching log level")
+    }
+}
+
+// only implement the Error trait when it's available
+#[cfg(not(feature = "freestanding"))]
+impl error::Error for TryFromLevelFilterError {
+    fn description(&self) -> &str { "level filter is `Off`" }
 }
 
 /// The "payload" of a log message.
@@ -451,16 +942,82 @@ pub struct LogRecord<'a> {
     metadata: LogMetadata<'a>,
     location: &'a LogLocation,
     args: fmt::Arguments<'a>,
+    key_values: &'a [(&'a str, kv::Value<'a>)],
+    scope: &'a str,
+    span_id: Option<u64>,
+    #[cfg(not(feature = "freestanding"))]
+    timestamp: Option<SystemTime>,
+    #[cfg(not(feature = "freestanding"))]
+    thread: Option<&'a Thread>,
+    #[cfg(not(feature = "freestanding"))]
+    pid: Option<u32>,
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    backtrace: Option<RecordBacktrace<'a>>,
+}
+
+// The backtrace of a LogRecord. Those captured by the logging macros are only
+// resolved once a logger asks for them, since looking up the symbols costs far
+// more than walking the stack.
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+#[derive(Copy, Clone)]
+enum RecordBacktrace<'a> {
+    Resolved(&'a Backtrace),
+    Lazy(&'a LazyBacktrace),
+}
+
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+impl<'a> RecordBacktrace<'a> {
+    fn get(self) -> &'a Backtrace {
+        match self {
+            RecordBacktrace::Resolved(backtrace) => backtrace,
+            RecordBacktrace::Lazy(backtrace) => backtrace.get(),
+        }
+    }
+}
+
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+struct LazyBacktrace {
+    resolved: Cell<bool>,
+    backtrace: UnsafeCell<Backtrace>,
+}
+
+#[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+impl LazyBacktrace {
+    fn new() -> LazyBacktrace {
+        LazyBacktrace {
+            resolved: Cell::new(false),
+            backtrace: UnsafeCell::new(Backtrace::new_unresolved()),
+        }
+    }
+
+    fn get(&self) -> &Backtrace {
+        // No reference to the backtrace is handed out until it's resolved,
+        // and the Cell keeps this from being shared between threads, so the
+        // mutable borrow is unique.
+        if !self.resolved.get() {
+            unsafe { (*self.backtrace.get()).resolve() };
+            self.resolved.set(true);
+        }
+        unsafe { &*self.backtrace.get() }
+    }
 }
 
 impl<'a> LogRecord<'a> {
+    /// Returns a new builder.
+    ///
+    /// This is intended for code which forwards records from another logging
+    /// system into a `Log` implementation.
+    pub fn builder() -> LogRecordBuilder<'a> {
+        LogRecordBuilder::new()
+    }
+
     /// The message body.
     pub fn args(&self) -> &fmt::Arguments<'a> {
         &self.args
     }
 
     /// Metadata about the log directive.
-    pub fn metadata(&self) -> &LogMetadata {
+    pub fn metadata(&self) -> &LogMetadata<'_> {
         &self.metadata
     }
 
@@ -478,286 +1035,1778 @@ impl<'a> LogRecord<'a> {
     pub fn target(&self) -> &str {
         self.metadata.target()
     }
-}
 
-/// Metadata about a log message.
-pub struct LogMetadata<'a> {
-    level: LogLevel,
-    target: &'a str,
-}
+    /// Returns true if the message was logged with `audit!`.
+    ///
+    /// See `LogMetadata::is_audit`.
+    pub fn is_audit(&self) -> bool {
+        self.metadata.is_audit()
+    }
 
-impl<'a> LogMetadata<'a> {
-    /// The verbosity level of the message.
-    pub fn level(&self) -> LogLevel {
-        self.level
+    /// The structured key-value pairs attached to the message.
+    pub fn key_values(&self) -> kv::KeyValues<'a> {
+        kv::KeyValues::new(self.key_values)
     }
 
-    /// The name of the target of the directive.
-    pub fn target(&self) -> &str {
-        self.target
+    /// The error attached to the message with `err = &e;`, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[macro_use]
+    /// # extern crate log;
+    /// use std::fs::File;
+    ///
+    /// # fn main() {
+    /// let path = "/etc/app.conf";
+    /// if let Err(e) = File::open(path) {
+    ///     error!(err = &e; "failed to open {}", path);
+    /// }
+    /// # }
+    /// ```
+    #[cfg(not(feature = "freestanding"))]
+    pub fn error(&self) -> Option<&'a (dyn error::Error + 'a)> {
+        self.key_values().get("err").and_then(|value| value.as_error())
     }
-}
 
-/// A trait encapsulating the operations required of a logger
-pub trait Log: Sync+Send {
-    /// Determines if a log message with the specified metadata would be
-    /// logged.
+    /// The diagnostic scopes the message was logged in, outermost first and
+    /// separated by `" > "`.
     ///
-    /// This is used by the `log_enabled!` macro to allow callers to avoid
-    /// expensive computation of log message arguments if the message would be
-    /// discarded anyway.
-    fn enabled(&self, metadata: &LogMetadata) -> bool;
+    /// This is empty if no scopes were pushed with `push_scope`.
+    pub fn scope(&self) -> &str {
+        self.scope
+    }
 
-    /// Logs the `LogRecord`.
+    /// The id of the innermost span the message was logged in, if any.
     ///
-    /// Note that `enabled` is *not* necessarily called before this method.
-    /// Implementations of `log` should perform all necessary filtering
-    /// internally.
-    fn log(&self, record: &LogRecord);
-}
+    /// See the `span!` macro.
+    pub fn span_id(&self) -> Option<u64> {
+        self.span_id
+    }
 
-/// The location of a log message.
-///
-/// # Warning
-///
-/// The fields of this struct are public so that they may be initialized by the
-/// `log!` macro. They are subject to change at any time and should never be
-/// accessed directly.
-#[derive(Copy, Clone, Debug)]
-pub struct LogLocation {
-    #[doc(hidden)]
-    pub __module_path: &'static str,
-    #[doc(hidden)]
-    pub __file: &'static str,
-    #[doc(hidden)]
-    pub __line: u32,
-}
+    /// The time at which the message was logged.
+    ///
+    /// This is only captured with the `timestamp` feature, and is `None`
+    /// otherwise.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
 
-impl LogLocation {
-    /// The module path of the message.
-    pub fn module_path(&self) -> &str {
-        self.__module_path
+    /// The thread which logged the message.
+    ///
+    /// This is `None` if the thread was exiting, or if the record was built by
+    /// a `LogRecordBuilder` without a thread.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn thread(&self) -> Option<&Thread> {
+        self.thread
     }
 
-    /// The source file containing the message.
-    pub fn file(&self) -> &str {
-        self.__file
+    /// The id of the process which logged the message.
+    ///
+    /// This is `None` if the record was built by a `LogRecordBuilder` without
+    /// a process id.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
     }
 
-    /// The line containing the message.
-    pub fn line(&self) -> u32 {
-        self.__line
+    /// The stack at the point where the message was logged.
+    ///
+    /// With the `backtrace` feature, this is captured for every record at the
+    /// `Critical` and `Error` levels which the logger is enabled for, and is
+    /// `None` for other records. Its symbols are looked up the first time
+    /// this is called, so loggers which never call it don't pay for that.
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.map(RecordBacktrace::get)
+    }
+
+    /// Creates an owned copy of the record.
+    ///
+    /// The message and key-value pairs are rendered to strings, so the
+    /// resulting `OwnedLogRecord` may be sent to another thread.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn to_owned(&self) -> OwnedLogRecord {
+        let mut key_values = Vec::with_capacity(self.key_values.len());
+        for &(key, ref value) in self.key_values {
+            key_values.push((key.to_string(), value.to_string()));
+        }
+
+        OwnedLogRecord {
+            level: self.level(),
+            target: self.target().to_string(),
+            location: *self.location,
+            message: self.args.to_string(),
+            key_values,
+            scope: self.scope.to_string(),
+            span_id: self.span_id,
+            trace_id: self.metadata.trace_id,
+            audit: self.metadata.audit,
+            timestamp: self.timestamp,
+            thread: self.thread.cloned(),
+            pid: self.pid,
+            #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+            backtrace: self.backtrace().cloned(),
+        }
+    }
+}
+
+/// An owned version of a `LogRecord`.
+///
+/// Unlike `LogRecord`, this type does not borrow from the log directive, so it
+/// can be queued and handed to a logger running on another thread.
+#[cfg(not(feature = "freestanding"))]
+#[derive(Clone, Debug)]
+pub struct OwnedLogRecord {
+    level: LogLevel,
+    target: String,
+    location: LogLocation,
+    message: String,
+    key_values: Vec<(String, String)>,
+    scope: String,
+    span_id: Option<u64>,
+    trace_id: Option<TraceId>,
+    audit: bool,
+    timestamp: Option<SystemTime>,
+    thread: Option<Thread>,
+    pid: Option<u32>,
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    backtrace: Option<Backtrace>,
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl OwnedLogRecord {
+    /// The verbosity level of the message.
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    /// The name of the target of the directive.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// The location of the log directive.
+    pub fn location(&self) -> &LogLocation {
+        &self.location
+    }
+
+    /// The formatted message body.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The rendered key-value pairs attached to the message.
+    pub fn key_values(&self) -> &[(String, String)] {
+        &self.key_values
+    }
+
+    /// The diagnostic scopes the message was logged in.
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    /// The id of the innermost span the message was logged in, if any.
+    pub fn span_id(&self) -> Option<u64> {
+        self.span_id
+    }
+
+    /// The trace id of the request being handled when the message was
+    /// logged, if any.
+    pub fn trace_id(&self) -> Option<TraceId> {
+        self.trace_id
+    }
+
+    /// Returns true if the message was logged with `audit!`.
+    pub fn is_audit(&self) -> bool {
+        self.audit
+    }
+
+    /// The time at which the message was logged, if it was captured.
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+
+    /// The thread which logged the message, if it was known.
+    pub fn thread(&self) -> Option<&Thread> {
+        self.thread.as_ref()
+    }
+
+    /// The id of the process which logged the message, if it was known.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// The stack at the point where the message was logged, if it was
+    /// captured.
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+
+    /// Sets the verbosity level of the message.
+    pub fn set_level(&mut self, level: LogLevel) {
+        self.level = level;
+    }
+
+    /// Sets the name of the target of the directive.
+    pub fn set_target(&mut self, target: &str) {
+        self.target.clear();
+        self.target.push_str(target);
+    }
+
+    /// Sets the message body.
+    pub fn set_message(&mut self, message: &str) {
+        self.message.clear();
+        self.message.push_str(message);
+    }
+
+    /// Returns a mutable reference to the structured key-value pairs.
+    pub fn key_values_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.key_values
+    }
+
+    /// Calls `f` with a `LogRecord` borrowing from this record.
+    ///
+    /// This is used to hand an owned record to a `Log` implementation.
+    pub fn with_record<F, T>(&self, f: F) -> T
+        where F: FnOnce(&LogRecord) -> T
+    {
+        let key_values = self.key_values.iter()
+                             .map(|(k, v)| (&**k, kv::Value::Str(v)))
+                             .collect::<Vec<_>>();
+        f(&LogRecord {
+            metadata: LogMetadata {
+                level: self.level,
+                target: &self.target,
+                trace_id: self.trace_id,
+                audit: self.audit,
+            },
+            location: &self.location,
+            args: format_args!("{}", self.message),
+            key_values: &key_values,
+            scope: &self.scope,
+            span_id: self.span_id,
+            timestamp: self.timestamp,
+            thread: self.thread.as_ref(),
+            pid: self.pid,
+            #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+            backtrace: self.backtrace.as_ref().map(RecordBacktrace::Resolved),
+        })
+    }
+}
+
+/// Builder for `LogRecord`.
+///
+/// Any field which is not set defaults to the corresponding default of
+/// `LogMetadataBuilder`, an empty message, no key-value pairs, and a location
+/// with an empty module path and file.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{LogLevel, LogRecord};
+///
+/// let record = LogRecord::builder()
+///                  .level(LogLevel::Warn)
+///                  .target("ffi")
+///                  .args(format_args!("code {}", 7))
+///                  .build();
+/// assert_eq!(record.target(), "ffi");
+/// ```
+pub struct LogRecordBuilder<'a> {
+    record: LogRecord<'a>,
+}
+
+static DEFAULT_LOCATION: LogLocation = LogLocation {
+    __module_path: "",
+    __file: "",
+    __line: 0,
+};
+
+impl<'a> Default for LogRecordBuilder<'a> {
+    fn default() -> LogRecordBuilder<'a> {
+        LogRecordBuilder::new()
+    }
+}
+
+impl<'a> LogRecordBuilder<'a> {
+    /// Creates a new builder.
+    pub fn new() -> LogRecordBuilder<'a> {
+        LogRecordBuilder {
+            record: LogRecord {
+                metadata: LogMetadataBuilder::new().build(),
+                location: &DEFAULT_LOCATION,
+                args: format_args!(""),
+                key_values: &[],
+                scope: "",
+                span_id: None,
+                #[cfg(not(feature = "freestanding"))]
+                timestamp: None,
+                #[cfg(not(feature = "freestanding"))]
+                thread: None,
+                #[cfg(not(feature = "freestanding"))]
+                pid: None,
+                #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+                backtrace: None,
+            },
+        }
+    }
+
+    /// Sets the message body.
+    pub fn args(&mut self, args: fmt::Arguments<'a>) -> &mut LogRecordBuilder<'a> {
+        self.record.args = args;
+        self
+    }
+
+    /// Sets the metadata.
+    pub fn metadata(&mut self, metadata: LogMetadata<'a>) -> &mut LogRecordBuilder<'a> {
+        self.record.metadata = metadata;
+        self
+    }
+
+    /// Sets the verbosity level.
+    pub fn level(&mut self, level: LogLevel) -> &mut LogRecordBuilder<'a> {
+        self.record.metadata.level = level;
+        self
+    }
+
+    /// Sets the target.
+    pub fn target(&mut self, target: &'a str) -> &mut LogRecordBuilder<'a> {
+        self.record.metadata.target = target;
+        self
+    }
+
+    /// Sets the location.
+    pub fn location(&mut self, location: &'a LogLocation) -> &mut LogRecordBuilder<'a> {
+        self.record.location = location;
+        self
+    }
+
+    /// Sets the structured key-value pairs.
+    pub fn key_values(&mut self, key_values: &'a [(&'a str, kv::Value<'a>)])
+                      -> &mut LogRecordBuilder<'a> {
+        self.record.key_values = key_values;
+        self
+    }
+
+    /// Sets the diagnostic scopes.
+    pub fn scope(&mut self, scope: &'a str) -> &mut LogRecordBuilder<'a> {
+        self.record.scope = scope;
+        self
+    }
+
+    /// Sets the span id.
+    pub fn span_id(&mut self, span_id: Option<u64>) -> &mut LogRecordBuilder<'a> {
+        self.record.span_id = span_id;
+        self
+    }
+
+    /// Sets the timestamp.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn timestamp(&mut self, timestamp: Option<SystemTime>) -> &mut LogRecordBuilder<'a> {
+        self.record.timestamp = timestamp;
+        self
+    }
+
+    /// Sets the thread.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn thread(&mut self, thread: Option<&'a Thread>) -> &mut LogRecordBuilder<'a> {
+        self.record.thread = thread;
+        self
+    }
+
+    /// Sets the process id.
+    #[cfg(not(feature = "freestanding"))]
+    pub fn pid(&mut self, pid: Option<u32>) -> &mut LogRecordBuilder<'a> {
+        self.record.pid = pid;
+        self
+    }
+
+    /// Sets the backtrace.
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    pub fn backtrace(&mut self, backtrace: Option<&'a Backtrace>) -> &mut LogRecordBuilder<'a> {
+        self.record.backtrace = backtrace.map(RecordBacktrace::Resolved);
+        self
+    }
+
+    /// Returns a `LogRecord` with the values set on this builder.
+    pub fn build(&self) -> LogRecord<'a> {
+        LogRecord {
+            metadata: self.record.metadata.clone(),
+            location: self.record.location,
+            args: self.record.args,
+            key_values: self.record.key_values,
+            scope: self.record.scope,
+            span_id: self.record.span_id,
+            #[cfg(not(feature = "freestanding"))]
+            timestamp: self.record.timestamp,
+            #[cfg(not(feature = "freestanding"))]
+            thread: self.record.thread,
+            #[cfg(not(feature = "freestanding"))]
+            pid: self.record.pid,
+            #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+            backtrace: self.record.backtrace,
+        }
+    }
+}
+
+/// Metadata about a log message.
+#[derive(Clone)]
+pub struct LogMetadata<'a> {
+    level: LogLevel,
+    target: &'a str,
+    trace_id: Option<TraceId>,
+    audit: bool,
+}
+
+impl<'a> LogMetadata<'a> {
+    /// Returns a new builder.
+    pub fn builder() -> LogMetadataBuilder<'a> {
+        LogMetadataBuilder::new()
+    }
+
+    /// The verbosity level of the message.
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    /// The name of the target of the directive.
+    pub fn target(&self) -> &str {
+        self.target
+    }
+
+    /// The trace id of the request being handled when the message was
+    /// logged, if any.
+    ///
+    /// See `set_current_trace_id`.
+    pub fn trace_id(&self) -> Option<TraceId> {
+        self.trace_id
+    }
+
+    /// Returns true if the message was logged with `audit!`.
+    ///
+    /// Audit records are not subject to the maximum log level or per-target
+    /// levels, and loggers which filter by level should let them through as
+    /// well.
+    pub fn is_audit(&self) -> bool {
+        self.audit
+    }
+}
+
+/// Builder for `LogMetadata`.
+///
+/// The level defaults to `LogLevel::Info` and the target to the empty string.
+pub struct LogMetadataBuilder<'a> {
+    metadata: LogMetadata<'a>,
+}
+
+impl<'a> Default for LogMetadataBuilder<'a> {
+    fn default() -> LogMetadataBuilder<'a> {
+        LogMetadataBuilder::new()
+    }
+}
+
+impl<'a> LogMetadataBuilder<'a> {
+    /// Creates a new builder.
+    pub fn new() -> LogMetadataBuilder<'a> {
+        LogMetadataBuilder {
+            metadata: LogMetadata {
+                level: LogLevel::Info,
+                target: "",
+                trace_id: None,
+                audit: false,
+            },
+        }
+    }
+
+    /// Sets the verbosity level.
+    pub fn level(&mut self, level: LogLevel) -> &mut LogMetadataBuilder<'a> {
+        self.metadata.level = level;
+        self
+    }
+
+    /// Sets the target.
+    pub fn target(&mut self, target: &'a str) -> &mut LogMetadataBuilder<'a> {
+        self.metadata.target = target;
+        self
+    }
+
+    /// Sets the trace id.
+    pub fn trace_id(&mut self, trace_id: Option<TraceId>) -> &mut LogMetadataBuilder<'a> {
+        self.metadata.trace_id = trace_id;
+        self
+    }
+
+    /// Sets whether this is an audit record.
+    pub fn audit(&mut self, audit: bool) -> &mut LogMetadataBuilder<'a> {
+        self.metadata.audit = audit;
+        self
+    }
+
+    /// Returns a `LogMetadata` with the values set on this builder.
+    pub fn build(&self) -> LogMetadata<'a> {
+        self.metadata.clone()
+    }
+}
+
+/// A 128-bit id correlating the records logged while handling one request,
+/// possibly across several services.
+///
+/// It is displayed as 32 lowercase hexadecimal digits, the format used by the
+/// W3C Trace Context `traceparent` header.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::TraceId;
+///
+/// let id = TraceId::from_parts(0x4bf92f3577b34da6, 0xa3ce929d0e0e4736);
+/// assert_eq!(id.to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TraceId([u8; 16]);
+
+impl TraceId {
+    /// Creates a trace id from its bytes, most significant first.
+    pub fn new(bytes: [u8; 16]) -> TraceId {
+        TraceId(bytes)
+    }
+
+    /// Creates a trace id from its high and low 64 bits.
+    pub fn from_parts(high: u64, low: u64) -> TraceId {
+        let mut bytes = [0; 16];
+        for i in 0..8 {
+            bytes[i] = (high >> (56 - 8 * i)) as u8;
+            bytes[8 + i] = (low >> (56 - 8 * i)) as u8;
+        }
+        TraceId(bytes)
+    }
+
+    /// Returns the bytes of the trace id, most significant first.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(fmt, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// A trait encapsulating the operations required of a logger
+///
+/// `Log` is also implemented for references to loggers, and for `Box` and
+/// `Arc` wrapping a logger, so these can be passed to anything expecting a
+/// logger, such as the types in `combinators`.
+pub trait Log: Sync+Send {
+    /// Determines if a log message with the specified metadata would be
+    /// logged.
+    ///
+    /// This is used by the `log_enabled!` macro to allow callers to avoid
+    /// expensive computation of log message arguments if the message would be
+    /// discarded anyway.
+    ///
+    /// The logging macros also remember the answer for each log statement, so
+    /// that a disabled statement costs next to nothing. It is asked again after
+    /// a logger is installed, a per-target level changes, or
+    /// `rebuild_interest_cache` is called, so the answer should only depend on
+    /// the level and target, and on the logger's configuration at those
+    /// times.
+    fn enabled(&self, metadata: &LogMetadata) -> bool;
+
+    /// Logs the `LogRecord`.
+    ///
+    /// Note that `enabled` is *not* necessarily called before this method.
+    /// Implementations of `log` should perform all necessary filtering
+    /// internally.
+    fn log(&self, record: &LogRecord);
+
+    /// Logs the `LogRecord`, reporting whether it was accepted.
+    ///
+    /// This is used by the `try_log!` family of macros, for callers which need
+    /// to know that a record was not lost, for example because a queue was
+    /// full or a write failed. A record which the logger filters out is not an
+    /// error.
+    ///
+    /// The default implementation calls `log` and returns `Ok`.
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        self.log(record);
+        Ok(())
+    }
+
+    /// Flushes any buffered records.
+    ///
+    /// The default implementation does nothing.
+    fn flush(&self) {}
+
+    /// Closes and reopens any files records are written to.
+    ///
+    /// This is called after the files have been rotated, so that new records
+    /// go to a fresh file rather than the renamed one. The default
+    /// implementation does nothing.
+    fn reopen(&self) {}
+
+    /// Handles a control message sent through `log::control`.
+    ///
+    /// The default implementation calls `flush` for `ControlMessage::Flush`
+    /// and `reopen` for `ControlMessage::Reopen`, and ignores any other
+    /// message. Implementations which override this should do the same for
+    /// messages they don't recognize, as more may be added in the future.
+    fn handle_control(&self, msg: ControlMessage) {
+        match msg {
+            ControlMessage::Flush => self.flush(),
+            ControlMessage::Reopen => self.reopen(),
+            _ => {}
+        }
+    }
+}
+
+impl<L: Log + ?Sized> Log for &L {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        (**self).enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        (**self).log(record)
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        (**self).try_log(record)
+    }
+
+    fn flush(&self) {
+        (**self).flush()
+    }
+
+    fn reopen(&self) {
+        (**self).reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        (**self).handle_control(msg)
+    }
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl<L: Log + ?Sized> Log for Box<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        (**self).enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        (**self).log(record)
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        (**self).try_log(record)
+    }
+
+    fn flush(&self) {
+        (**self).flush()
+    }
+
+    fn reopen(&self) {
+        (**self).reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        (**self).handle_control(msg)
+    }
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl<L: Log + ?Sized> Log for Arc<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        (**self).enabled(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        (**self).log(record)
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        (**self).try_log(record)
+    }
+
+    fn flush(&self) {
+        (**self).flush()
+    }
+
+    fn reopen(&self) {
+        (**self).reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        (**self).handle_control(msg)
+    }
+}
+
+/// A message asking a logger to change its behavior at runtime.
+///
+/// Control messages are sent to the global logger with `log::control`, and
+/// are handled by `Log::handle_control`. They let an application poke the
+/// installed logger without knowing its concrete type.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum ControlMessage {
+    /// Flush any buffered records.
+    Flush,
+    /// Close and reopen any files records are written to.
+    Reopen,
+    /// Change the level at which the logger filters records.
+    SetLevel(LogLevelFilter),
+}
+
+/// The location of a log message.
+///
+/// # Warning
+///
+/// The fields of this struct are public so that they may be initialized by the
+/// `log!` macro. They are subject to change at any time and should never be
+/// accessed directly.
+#[derive(Copy, Clone, Debug)]
+pub struct LogLocation {
+    #[doc(hidden)]
+    pub __module_path: &'static str,
+    #[doc(hidden)]
+    pub __file: &'static str,
+    #[doc(hidden)]
+    pub __line: u32,
+}
+
+impl LogLocation {
+    /// The module path of the message.
+    pub fn module_path(&self) -> &str {
+        self.__module_path
+    }
+
+    /// The source file containing the message.
+    pub fn file(&self) -> &str {
+        self.__file
+    }
+
+    /// The line containing the message.
+    pub fn line(&self) -> u32 {
+        self.__line
     }
 }
 
 /// A token providing read and write access to the global maximum log level
 /// filter.
 ///
-/// The maximum log level is used as an optimization to avoid evaluating log
-/// messages that will be ignored by the logger. Any message with a level
-/// higher than the maximum log level filter will be ignored. A logger should
-/// make sure to keep the maximum log level filter in sync with its current
-/// configuration.
+/// The maximum log level is used as an optimization to avoid evaluating log
+/// messages that will be ignored by the logger. Any message with a level
+/// higher than the maximum log level filter will be ignored. A logger should
+/// make sure to keep the maximum log level filter in sync with its current
+/// configuration.
+#[allow(missing_copy_implementations)]
+pub struct MaxLogLevelFilter(());
+
+impl fmt::Debug for MaxLogLevelFilter {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "MaxLogLevelFilter")
+    }
+}
+
+impl MaxLogLevelFilter {
+    /// Gets the current maximum log level filter.
+    pub fn get(&self) -> LogLevelFilter {
+        max_log_level()
+    }
+
+    /// Sets the maximum log level.
+    pub fn set(&self, level: LogLevelFilter) {
+        set_max_level(level)
+    }
+}
+
+/// Returns the current maximum log level.
+///
+/// The `log!` macro and the macros for each level, such as `info!`, check
+/// this value and discard any message logged at a higher level.
+/// The maximum log level is set by the `MaxLogLevel` token passed to loggers,
+/// or by `set_max_level`.
+#[inline(always)]
+pub fn max_log_level() -> LogLevelFilter {
+    // only ever set from a valid LogLevelFilter
+    LogLevelFilter::from_usize(MAX_LOG_LEVEL_FILTER.load(Ordering::Relaxed))
+        .unwrap_or(LogLevelFilter::max())
+}
+
+/// Returns the current maximum log level.
+///
+/// This is the same as `max_log_level`.
+#[inline(always)]
+pub fn max_level() -> LogLevelFilter {
+    max_log_level()
+}
+
+/// Sets the global maximum log level.
+///
+/// This may be called at any time, with or without a logger installed, to
+/// change how verbose the program's logging is. Note that the logger may do
+/// its own filtering as well, so raising the maximum log level above what
+/// the logger was configured with will not make it log more.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::LogLevelFilter;
+///
+/// log::set_max_level(LogLevelFilter::Warn);
+/// assert_eq!(LogLevelFilter::Warn, log::max_level());
+/// ```
+pub fn set_max_level(level: LogLevelFilter) {
+    MAX_LOG_LEVEL_FILTER.store(level as usize, Ordering::SeqCst)
+}
+
+/// Sets the global logger.
+///
+/// The `make_logger` closure is passed a `MaxLogLevel` object, which the
+/// logger should use to keep the global maximum log level in sync with the
+/// highest log level that the logger will not ignore.
+///
+/// This function may only be called once in the lifetime of a program. Any log
+/// events that occur before the call to `set_logger` completes will be
+/// ignored.
+///
+/// This function does not typically need to be called manually. Logger
+/// implementations should provide an initialization method that calls
+/// `set_logger` internally.
+///
+/// The logger must be a `&'static Log`, which works well for loggers that are
+/// zero-sized or live in a static, and requires no allocation. Loggers that are
+/// constructed at runtime can be installed with `set_boxed_logger` instead.
+///
+/// ```rust
+/// # extern crate log;
+/// # use log::{LogLevel, LogLevelFilter, SetLoggerError, LogMetadata};
+/// # struct SimpleLogger;
+/// # impl log::Log for SimpleLogger {
+/// #   fn enabled(&self, _: &LogMetadata) -> bool { false }
+/// #   fn log(&self, _: &log::LogRecord) {}
+/// # }
+/// # fn main() {}
+/// static LOGGER: SimpleLogger = SimpleLogger;
+///
+/// pub fn init() -> Result<(), SetLoggerError> {
+///     log::set_logger(|max_log_level| {
+///         max_log_level.set(LogLevelFilter::Info);
+///         &LOGGER
+///     })
+/// }
+/// ```
+#[cfg(not(feature = "racy"))]
+pub fn set_logger<M>(make_logger: M) -> Result<(), SetLoggerError>
+    where M: FnOnce(MaxLogLevelFilter) -> &'static dyn Log
+{
+    set_logger_inner(make_logger)
+}
+
+/// Sets the global logger to a `Box<Log>`.
+///
+/// This is identical to `set_logger`, except that the logger is boxed, so it
+/// may be constructed at runtime. The logger is only dropped if it is removed
+/// with `shutdown_logger` or `swap_logger`; otherwise it lives until the
+/// program exits.
+///
+/// ```rust
+/// # extern crate log;
+/// # use log::{LogLevel, LogLevelFilter, SetLoggerError, LogMetadata};
+/// # struct SimpleLogger;
+/// # impl log::Log for SimpleLogger {
+/// #   fn enabled(&self, _: &LogMetadata) -> bool { false }
+/// #   fn log(&self, _: &log::LogRecord) {}
+/// # }
+/// # fn main() {}
+/// pub fn init() -> Result<(), SetLoggerError> {
+///     log::set_boxed_logger(|max_log_level| {
+///         max_log_level.set(LogLevelFilter::Info);
+///         Box::new(SimpleLogger)
+///     })
+/// }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+pub fn set_boxed_logger<M>(make_logger: M) -> Result<(), SetLoggerError>
+    where M: FnOnce(MaxLogLevelFilter) -> Box<dyn Log>
+{
+    set_logger_inner(|max_level| unsafe {
+        let logger = &*Box::into_raw(make_logger(max_level));
+        LOGGER_OWNERSHIP = Ownership::Boxed;
+        logger
+    })
+}
+
+/// Sets the global logger to an `Arc<Log>`.
+///
+/// This is identical to `set_boxed_logger`, except that the logger is shared.
+/// The application may hold on to its own reference to the logger, or retrieve
+/// one later with `logger_arc`, in order to reconfigure it at runtime.
+///
+/// ```rust
+/// # extern crate log;
+/// # use log::{LogLevel, LogLevelFilter, SetLoggerError, LogMetadata};
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// struct SimpleLogger {
+///     verbose: AtomicBool,
+/// }
+/// # impl log::Log for SimpleLogger {
+/// #   fn enabled(&self, _: &LogMetadata) -> bool { false }
+/// #   fn log(&self, _: &log::LogRecord) {}
+/// # }
+///
+/// # fn main() { init().unwrap(); }
+/// pub fn init() -> Result<Arc<SimpleLogger>, SetLoggerError> {
+///     let logger = Arc::new(SimpleLogger { verbose: AtomicBool::new(false) });
+///     let handle = logger.clone();
+///     log::set_arc_logger(|max_log_level| {
+///         max_log_level.set(LogLevelFilter::Info);
+///         logger
+///     })?;
+///
+///     // later on
+///     handle.verbose.store(true, Ordering::SeqCst);
+///     Ok(handle)
+/// }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+pub fn set_arc_logger<M>(make_logger: M) -> Result<(), SetLoggerError>
+    where M: FnOnce(MaxLogLevelFilter) -> Arc<dyn Log>
+{
+    set_logger_inner(|max_level| unsafe {
+        let logger = make_logger(max_level);
+        let ptr = &*logger as *const dyn Log;
+        LOGGER_OWNERSHIP = Ownership::Shared(logger);
+        &*ptr
+    })
+}
+
+/// Returns a handle to the global logger, if it was installed with
+/// `set_arc_logger`.
+///
+/// Returns `None` if no logger is installed, or if it was installed with
+/// `set_logger` or `set_boxed_logger`.
+#[cfg(not(feature = "freestanding"))]
+pub fn logger_arc() -> Option<Arc<dyn Log>> {
+    logger().and_then(|_guard| unsafe {
+        match LOGGER_OWNERSHIP {
+            Ownership::Shared(ref logger) => Some(logger.clone()),
+            _ => None,
+        }
+    })
+}
+
+/// A thread-unsafe version of `set_logger`.
+///
+/// This function uses only atomic loads and stores rather than a
+/// compare-and-swap, so it is available on targets which lack atomic
+/// read-modify-write instructions, such as `thumbv6` cores. Such targets are
+/// typically single threaded. Building with the `racy` feature removes every
+/// other use of those instructions from the crate, and this function is only
+/// available with that feature.
+///
+/// # Safety
+///
+/// This function is only safe to call when no other thread may call it, or
+/// any of the other logger initialization functions, concurrently.
+#[cfg(feature = "racy")]
+pub unsafe fn set_logger_racy<M>(make_logger: M) -> Result<(), SetLoggerError>
+    where M: FnOnce(MaxLogLevelFilter) -> &'static dyn Log
+{
+    if STATE.load(Ordering::SeqCst) != UNINITIALIZED {
+        return Err(SetLoggerError(()));
+    }
+
+    STATE.store(INITIALIZING, Ordering::SeqCst);
+    install_logger(make_logger);
+    Ok(())
+}
+
+// The closure is run while STATE is INITIALIZING, so it may also write to the
+// other logger statics.
+#[cfg(not(feature = "racy"))]
+fn set_logger_inner<M>(make_logger: M) -> Result<(), SetLoggerError>
+    where M: FnOnce(MaxLogLevelFilter) -> &'static dyn Log
+{
+    if STATE.compare_exchange(UNINITIALIZED, INITIALIZING,
+                              Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Err(SetLoggerError(()));
+    }
+
+    install_logger(make_logger);
+    Ok(())
+}
+
+// Installs and publishes the logger. Must only be called by the thread which
+// moved STATE from UNINITIALIZED to INITIALIZING.
+fn install_logger<M>(make_logger: M)
+    where M: FnOnce(MaxLogLevelFilter) -> &'static dyn Log
+{
+    #[cfg(all(unix, feature = "fork_safety", not(feature = "freestanding")))]
+    fork::register();
+    unsafe {
+        LOGGER = make_logger(MaxLogLevelFilter(()));
+    }
+    publish_logger();
+}
+
+// Moves STATE from INITIALIZING to INITIALIZED, once any records buffered
+// before the logger was installed have been passed to it.
+fn publish_logger() {
+    #[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+    LOGGER_EPOCH.fetch_add(1, Ordering::SeqCst);
+    #[cfg(not(feature = "freestanding"))]
+    early::replay(unsafe { LOGGER }, || STATE.store(INITIALIZED, Ordering::SeqCst));
+    #[cfg(feature = "freestanding")]
+    STATE.store(INITIALIZED, Ordering::SeqCst);
+    callsite::invalidate();
+}
+
+#[cfg(not(feature = "freestanding"))]
+enum Ownership {
+    Static,
+    Boxed,
+    Shared(Arc<dyn Log>),
+}
+
+/// Shuts down the global logger.
+///
+/// This waits for all in-flight logging calls to finish, then removes the
+/// logger from the logging system and returns it, allowing it to be flushed
+/// and dropped deterministically. Any log events that occur after this call
+/// begins will be ignored, and the logger may not be set again afterwards.
+///
+/// The logging system does not shut the logger down by itself, so programs
+/// using a logger which buffers records should call this (or `flush`) before
+/// exiting. Otherwise the logger is simply never dropped.
+///
+/// Returns an error if no logger is currently installed, or if some thread
+/// has not returned from the logger within a few seconds. In the latter case
+/// the logger is left installed.
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+pub fn shutdown_logger() -> Result<Box<dyn Log>, ShutdownLoggerError> {
+    // Set to INITIALIZING to prevent re-initialization after
+    if STATE.compare_exchange(INITIALIZED, INITIALIZING,
+                              Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Err(ShutdownLoggerError(ShutdownErrorKind::NotInstalled));
+    }
+    LOGGER_EPOCH.fetch_add(1, Ordering::SeqCst);
+
+    // Stop other threads from calling into the logging system at all
+    let max_level = MAX_LOG_LEVEL_FILTER.swap(LogLevelFilter::Off as usize,
+                                              Ordering::SeqCst);
+
+    if !wait_for_loggers() {
+        // put everything back the way it was
+        MAX_LOG_LEVEL_FILTER.store(max_level, Ordering::SeqCst);
+        STATE.store(INITIALIZED, Ordering::SeqCst);
+        return Err(ShutdownLoggerError(ShutdownErrorKind::TimedOut));
+    }
+
+    let logger = unsafe { take_logger() };
+    STATE.store(SHUT_DOWN, Ordering::SeqCst);
+    callsite::invalidate();
+    Ok(logger)
+}
+
+/// Replaces the global logger.
+///
+/// The `make_logger` closure is passed a `MaxLogLevel` object, exactly as for
+/// `set_boxed_logger`. If a logger is already installed, this waits for all
+/// in-flight logging calls to finish, then installs the new logger and returns
+/// the old one, allowing it to be flushed and dropped. Any log events that
+/// occur while the loggers are being swapped will be ignored. If no logger is
+/// installed yet, this behaves like `set_boxed_logger` and returns `None`.
+///
+/// This allows a long running program to change where its logs are sent,
+/// for example from standard error to a file after daemonizing.
+///
+/// If the logger has been shut down with `shutdown_logger`, the new logger is
+/// not installed and is returned instead. The same happens if some thread has
+/// not returned from the old logger within a few seconds, in which case the
+/// old logger is left installed, and any maximum log level set by
+/// `make_logger` is undone.
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+pub fn swap_logger<M>(make_logger: M) -> Option<Box<dyn Log>>
+    where M: FnOnce(MaxLogLevelFilter) -> Box<dyn Log>
+{
+    let old = loop {
+        match STATE.compare_exchange(INITIALIZED, INITIALIZING,
+                                     Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => {
+                LOGGER_EPOCH.fetch_add(1, Ordering::SeqCst);
+                if !wait_for_loggers() {
+                    // put everything back the way it was
+                    let max_level = MAX_LOG_LEVEL_FILTER.load(Ordering::SeqCst);
+                    let logger = make_logger(MaxLogLevelFilter(()));
+                    MAX_LOG_LEVEL_FILTER.store(max_level, Ordering::SeqCst);
+                    STATE.store(INITIALIZED, Ordering::SeqCst);
+                    return Some(logger);
+                }
+                break Some(unsafe { take_logger() });
+            }
+            Err(UNINITIALIZED) => {
+                if STATE.compare_exchange(UNINITIALIZED, INITIALIZING,
+                                          Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                    break None;
+                }
+            }
+            Err(SHUT_DOWN) => return Some(make_logger(MaxLogLevelFilter(()))),
+            // another thread is setting or swapping the logger
+            _ => thread::yield_now(),
+        }
+    };
+
+    unsafe {
+        LOGGER = &*Box::into_raw(make_logger(MaxLogLevelFilter(())));
+        LOGGER_OWNERSHIP = Ownership::Boxed;
+    }
+    STATE.store(INITIALIZED, Ordering::SeqCst);
+    callsite::invalidate();
+    old
+}
+
+// How long shutdown_logger and swap_logger wait for in-flight log calls.
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+const WAIT_TIMEOUT_MS: u64 = 5000;
+
+// Waits until no thread is inside the logger, returning false if that doesn't
+// happen within WAIT_TIMEOUT_MS. STATE must already have been moved away from
+// INITIALIZED.
+//
+// Log calls are usually short, so this yields a few times before falling back
+// to sleeping with an exponential backoff, to avoid burning a core if a thread
+// is stuck inside the logger.
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+fn wait_for_loggers() -> bool {
+    let start = Instant::now();
+    let timeout = Duration::from_millis(WAIT_TIMEOUT_MS);
+    let mut spins = 0;
+    let mut sleep = Duration::from_millis(0);
+    while REFCOUNT.load(Ordering::SeqCst) != 0 {
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        if spins < 100 {
+            spins += 1;
+            thread::yield_now();
+        } else {
+            sleep = cmp::min(cmp::max(sleep * 2, Duration::new(0, 1000)),
+                             Duration::from_millis(1));
+            thread::sleep(sleep);
+        }
+    }
+    true
+}
+
+// Removes the current logger, boxing it if it isn't already. Must only be
+// called while STATE is INITIALIZING and no thread is inside the logger.
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+unsafe fn take_logger() -> Box<dyn Log> {
+    let logger = ptr::replace(ptr::addr_of_mut!(LOGGER), &NopLogger);
+    match ptr::replace(ptr::addr_of_mut!(LOGGER_OWNERSHIP), Ownership::Static) {
+        Ownership::Static => Box::new(logger),
+        Ownership::Boxed => Box::from_raw(logger as *const dyn Log as *mut dyn Log),
+        Ownership::Shared(logger) => Box::new(logger),
+    }
+}
+
+/// The type returned by `set_logger` and its variants if a logger has already
+/// been set.
 #[allow(missing_copy_implementations)]
-pub struct MaxLogLevelFilter(());
+#[derive(Debug)]
+pub struct SetLoggerError(());
 
-impl fmt::Debug for MaxLogLevelFilter {
+impl fmt::Display for SetLoggerError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "MaxLogLevelFilter")
+        write!(fmt, "attempted to set a logger after the logging system \
+                     was already initialized")
     }
 }
 
-impl MaxLogLevelFilter {
-    /// Gets the current maximum log level filter.
-    pub fn get(&self) -> LogLevelFilter {
-        max_log_level()
+// only implement the Error trait when it's available
+#[cfg(not(feature = "freestanding"))]
+impl error::Error for SetLoggerError {
+    fn description(&self) -> &str { "set_logger() called multiple times" }
+}
+
+/// The type returned by `Log::try_log` and the `try_log!` family of macros if
+/// a record was not accepted.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct LogError(LogErrorKind);
+
+/// The reason a record was not accepted.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum LogErrorKind {
+    /// No logger was installed.
+    NoLogger,
+    /// The logger's buffer or queue was full.
+    Full,
+    /// The logger has stopped accepting records.
+    Closed,
+    /// The logger failed to write the record.
+    Failed,
+}
+
+impl LogError {
+    /// Creates an error of the given kind.
+    pub fn new(kind: LogErrorKind) -> LogError {
+        LogError(kind)
     }
 
-    /// Sets the maximum log level.
-    pub fn set(&self, level: LogLevelFilter) {
-        MAX_LOG_LEVEL_FILTER.store(level as usize, Ordering::SeqCst)
+    /// Returns the reason the record was not accepted.
+    pub fn kind(&self) -> LogErrorKind {
+        self.0
+    }
+
+    fn description(&self) -> &str {
+        match self.0 {
+            LogErrorKind::NoLogger => "no logger is installed",
+            LogErrorKind::Full => "the logger is full",
+            LogErrorKind::Closed => "the logger is closed",
+            LogErrorKind::Failed => "the logger failed to write the record",
+        }
     }
 }
 
-/// Returns the current maximum log level.
+impl fmt::Display for LogError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "record not logged: {}", self.description())
+    }
+}
+
+#[cfg(not(feature = "freestanding"))]
+impl error::Error for LogError {
+    fn description(&self) -> &str {
+        LogError::description(self)
+    }
+}
+
+/// The type returned by `shutdown_logger` if no logger is installed, or if
+/// the logger could not be shut down in time.
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+#[allow(missing_copy_implementations)]
+#[derive(Debug)]
+pub struct ShutdownLoggerError(ShutdownErrorKind);
+
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+#[derive(Debug)]
+enum ShutdownErrorKind {
+    NotInstalled,
+    TimedOut,
+}
+
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+impl fmt::Display for ShutdownLoggerError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ShutdownErrorKind::NotInstalled => {
+                write!(fmt, "attempted to shut down the logger without an \
+                             active logger")
+            }
+            ShutdownErrorKind::TimedOut => {
+                write!(fmt, "timed out waiting for in-flight log calls while \
+                             shutting down the logger")
+            }
+        }
+    }
+}
+
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+impl error::Error for ShutdownLoggerError {
+    fn description(&self) -> &str {
+        match self.0 {
+            ShutdownErrorKind::NotInstalled => {
+                "shutdown_logger() called without an active logger"
+            }
+            ShutdownErrorKind::TimedOut => "shutdown_logger() timed out",
+        }
+    }
+}
+
+/// A logger which discards every record.
+///
+/// This is the logger used before one has been installed. It can also serve
+/// as a placeholder when building combinations of loggers, or in tests.
+///
+/// # Examples
 ///
-/// The `log!`, `error!`, `warn!`, `info!`, `debug!`, and `trace!` macros check
-/// this value and discard any message logged at a higher level. The maximum
-/// log level is set by the `MaxLogLevel` token passed to loggers.
+/// ```rust
+/// use log::{Log, LogMetadata, NopLogger};
+///
+/// let logger = NopLogger;
+/// assert!(!logger.enabled(&LogMetadata::builder().build()));
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NopLogger;
+
+impl Log for NopLogger {
+    fn enabled(&self, _: &LogMetadata) -> bool { false }
+
+    fn log(&self, _: &LogRecord) {}
+}
+
+struct LoggerGuard(&'static dyn Log);
+
+// no refcounting if freestanding or leaking
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+impl Drop for LoggerGuard {
+    fn drop(&mut self) {
+        REFCOUNT.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl Deref for LoggerGuard {
+    type Target = dyn Log;
+
+    fn deref(&self) -> &(dyn Log + 'static) {
+        self.0
+    }
+}
+
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+fn logger() -> Option<LoggerGuard> {
+    REFCOUNT.fetch_add(1, Ordering::SeqCst);
+    if let Some(logger) = scoped_logger() {
+        // counted anyway, to keep LoggerGuard simple
+        return Some(LoggerGuard(logger));
+    }
+
+    let epoch = LOGGER_EPOCH.load(Ordering::SeqCst);
+    // the cache is gone if this thread is exiting
+    if let Ok(Some((cached, logger))) = CACHED_LOGGER.try_with(|cached| cached.get()) {
+        if cached == epoch {
+            return Some(LoggerGuard(logger));
+        }
+    }
+
+    if STATE.load(Ordering::SeqCst) != INITIALIZED {
+        REFCOUNT.fetch_sub(1, Ordering::Relaxed);
+        return None;
+    }
+    let logger = unsafe { LOGGER };
+    let _ = CACHED_LOGGER.try_with(|cached| cached.set(Some((epoch, logger))));
+    Some(LoggerGuard(logger))
+}
+
+#[cfg(any(feature = "freestanding", feature = "leak_logger"))]
+fn logger() -> Option<LoggerGuard> {
+    // no refcounting when the logger can never be freed
+    if let Some(logger) = scoped_logger() {
+        Some(LoggerGuard(logger))
+    } else if STATE.load(Ordering::Acquire) != INITIALIZED {
+        None
+    } else {
+        Some(LoggerGuard(unsafe { LOGGER }))
+    }
+}
+
+// The logger set by with_logger on this thread, if any. SCOPED_LOGGERS counts
+// the active with_logger calls across all threads, so that threads can skip
+// the thread-local lookup when nothing is using it. It is only ever compared
+// against zero by a thread which may have incremented it itself, so Relaxed
+// is enough.
+#[cfg(not(feature = "freestanding"))]
+thread_local! {
+    static SCOPED_LOGGER: Cell<Option<&'static dyn Log>> = Cell::new(None)
+}
+#[cfg(not(feature = "freestanding"))]
+static SCOPED_LOGGERS: AtomicUsize = AtomicUsize::new(0);
+
+// Whether with_logger is in use on any thread.
+#[cfg(not(feature = "freestanding"))]
+fn scoped_loggers_active() -> bool {
+    SCOPED_LOGGERS.load(Ordering::Relaxed) != 0
+}
+
+#[cfg(not(feature = "freestanding"))]
+fn logger_installed() -> bool {
+    STATE.load(Ordering::SeqCst) == INITIALIZED
+}
+
+#[cfg(not(feature = "freestanding"))]
+#[inline]
+fn scoped_logger() -> Option<&'static dyn Log> {
+    if SCOPED_LOGGERS.load(Ordering::Relaxed) == 0 {
+        return None;
+    }
+    SCOPED_LOGGER.try_with(|logger| logger.get()).unwrap_or(None)
+}
+
+#[cfg(feature = "freestanding")]
 #[inline(always)]
-pub fn max_log_level() -> LogLevelFilter {
-    unsafe { mem::transmute(MAX_LOG_LEVEL_FILTER.load(Ordering::Relaxed)) }
+fn scoped_logger() -> Option<&'static dyn Log> {
+    None
 }
 
-/// Sets the global logger.
+/// Runs a closure with all records logged on the current thread sent to
+/// `logger` instead of the global logger.
 ///
-/// The `make_logger` closure is passed a `MaxLogLevel` object, which the
-/// logger should use to keep the global maximum log level in sync with the
-/// highest log level that the logger will not ignore.
+/// The global logger is restored when the closure returns or panics. Calls
+/// may be nested, in which case the innermost logger is used. Records logged
+/// by other threads, including ones spawned by the closure, still go to the
+/// global logger.
 ///
-/// This function may only be called once in the lifetime of a program. Any log
-/// events that occur before the call to `set_logger` completes will be
-/// ignored.
+/// This is mostly useful in tests, which run in parallel and so can't each
+/// install their own global logger. Note that the global maximum log level
+/// still applies, and that it is `Off` until a global logger is installed or
+/// `set_max_level` is called.
 ///
-/// This function does not typically need to be called manually. Logger
-/// implementations should provide an initialization method that calls
-/// `set_logger` internally.
+/// # Examples
 ///
 /// ```rust
-/// # extern crate log;
-/// # use log::{LogLevel, LogLevelFilter, SetLoggerError, LogMetadata};
-/// # struct SimpleLogger;
-/// # impl log::Log for SimpleLogger {
-/// #   fn enabled(&self, _: &LogMetadata) -> bool { false }
-/// #   fn log(&self, _: &log::LogRecord) {}
-/// # }
-/// # fn main() {}
-/// pub fn init() -> Result<(), SetLoggerError> {
-///     log::set_logger(|max_log_level| {
-///         max_log_level.set(LogLevelFilter::Info);
-///         Box::new(SimpleLogger)
-///     })
+/// #[macro_use]
+/// extern crate log;
+///
+/// use std::sync::Mutex;
+/// use log::{Log, LogLevelFilter, LogMetadata, LogRecord};
+///
+/// struct Collect(Mutex<Vec<String>>);
+///
+/// impl Log for Collect {
+///     fn enabled(&self, _: &LogMetadata) -> bool { true }
+///
+///     fn log(&self, record: &LogRecord) {
+///         self.0.lock().unwrap().push(record.args().to_string());
+///     }
+/// }
+///
+/// fn main() {
+///     log::set_max_level(LogLevelFilter::Info);
+///
+///     let logger = Collect(Mutex::new(vec![]));
+///     log::with_logger(&logger, || { info!("hello"); });
+///     assert_eq!(vec!["hello".to_string()], *logger.0.lock().unwrap());
 /// }
 /// ```
 #[cfg(not(feature = "freestanding"))]
-pub fn set_logger<M>(make_logger: M) -> Result<(), SetLoggerError>
-    where M: FnOnce(MaxLogLevelFilter) -> Box<Log> {
-        if LOGGER.compare_and_swap(UNINITIALIZED, INITIALIZING,
-                                   Ordering::SeqCst) != UNINITIALIZED {
-            return Err(SetLoggerError(()));
-        }
-
-        let logger = Box::new(make_logger(MaxLogLevelFilter(())));
-        let logger = unsafe { mem::transmute::<Box<Box<Log>>, usize>(logger) };
-        LOGGER.store(logger, Ordering::SeqCst);
+pub fn with_logger<F, T>(logger: &dyn Log, f: F) -> T
+    where F: FnOnce() -> T
+{
+    struct Reset(Option<&'static dyn Log>);
 
-        unsafe {
-            assert_eq!(libc::atexit(shutdown), 0);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            SCOPED_LOGGER.with(|logger| logger.set(self.0));
+            SCOPED_LOGGERS.fetch_sub(1, Ordering::Relaxed);
         }
-        return Ok(());
+    }
 
-        extern fn shutdown() {
-            // Set to INITIALIZING to prevent re-initialization after
-            let logger = LOGGER.swap(INITIALIZING, Ordering::SeqCst);
-            
-            while REFCOUNT.load(Ordering::SeqCst) != 0 {
-                // FIXME add a sleep here when it doesn't involve timers
-            }
+    // The logger is only reachable through SCOPED_LOGGER until Reset runs,
+    // which happens before this function returns or unwinds out of f.
+    let logger: &'static dyn Log = unsafe { mem::transmute(logger) };
+    SCOPED_LOGGERS.fetch_add(1, Ordering::Relaxed);
+    let _reset = Reset(SCOPED_LOGGER.with(|scoped| scoped.replace(Some(logger))));
+    f()
+}
 
-            unsafe { mem::transmute::<usize, Box<Box<Log>>>(logger); }
-        }
+/// Flushes any buffered records in the global logger.
+///
+/// This does nothing if no logger has been installed. Applications should call
+/// this before exiting or after a critical error to make sure that buffered
+/// records are not lost.
+pub fn flush() {
+    if let Some(logger) = logger() {
+        logger.flush();
     }
+}
 
-/// Sets the global logger.
+/// Asks the global logger to reopen the files it writes to.
 ///
-/// The `make_logger` closure is passed a `MaxLogLevel` object, which the
-/// logger should use to keep the global maximum log level in sync with the
-/// highest log level that the logger will not ignore.
+/// This does nothing if no logger has been installed. Applications should call
+/// this after their log files have been rotated.
+pub fn reopen() {
+    if let Some(logger) = logger() {
+        logger.reopen();
+    }
+}
+
+/// Sends a control message to the global logger.
 ///
-/// This function may only be called once in the lifetime of a program. Any log
-/// events that occur before the call to `set_logger` completes will be
-/// ignored.
+/// For `ControlMessage::SetLevel`, the global maximum log level is set first,
+/// so the new level takes effect even if the logger ignores the message.
+/// This does nothing else if no logger has been installed.
 ///
-/// This function does not typically need to be called manually. Logger
-/// implementations should provide an initialization method that calls
-/// `set_logger` internally.
+/// # Examples
 ///
-/// The closure passed to set_logger must return a pointer to a Log trait
-/// object. No checks are done to ensure this. Additionally, this function does
-/// not concern itself with the lifecycle of the logger. It is up to the
-/// programmer to ensure the object stays alive long enough, and is freed at the
-/// end of its use.
-#[cfg(feature = "freestanding")]
-pub fn set_logger<M>(make_logger: M) -> Result<(), SetLoggerError>
-    where M: FnOnce(MaxLogLevelFilter) -> *const &'static Log
-{
-    if LOGGER.compare_and_swap(UNINITIALIZED, INITIALIZING,
-                               Ordering::SeqCst) != UNINITIALIZED {
-        return Err(SetLoggerError(()));
+/// ```rust
+/// use log::{ControlMessage, LogLevelFilter};
+///
+/// log::control(ControlMessage::SetLevel(LogLevelFilter::Debug));
+/// assert_eq!(LogLevelFilter::Debug, log::max_level());
+/// ```
+pub fn control(msg: ControlMessage) {
+    if let ControlMessage::SetLevel(level) = msg {
+        set_max_level(level);
+    }
+    if let Some(logger) = logger() {
+        logger.handle_control(msg);
     }
+    if let ControlMessage::SetLevel(_) = msg {
+        rebuild_interest_cache();
+    }
+}
 
-    let logger = make_logger(MaxLogLevelFilter(()));
-    let logger: usize = unsafe {mem::transmute(logger)};
-    LOGGER.store(logger, Ordering::SeqCst);
+// The number of records dropped at each level, indexed by level - 1. These
+// are only statistics, so Relaxed is enough.
+static DROPPED_RECORDS: [AtomicUsize; 9] = [AtomicUsize::new(0), AtomicUsize::new(0),
+                                            AtomicUsize::new(0), AtomicUsize::new(0),
+                                            AtomicUsize::new(0), AtomicUsize::new(0),
+                                            AtomicUsize::new(0), AtomicUsize::new(0),
+                                            AtomicUsize::new(0)];
+
+#[cfg(not(feature = "racy"))]
+fn record_dropped(level: LogLevel) {
+    DROPPED_RECORDS[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+}
 
-    return Ok(());
+// Without fetch_add, concurrent drops may be undercounted. That is acceptable
+// for statistics on the single threaded targets this feature is meant for.
+#[cfg(feature = "racy")]
+fn record_dropped(level: LogLevel) {
+    let count = &DROPPED_RECORDS[level as usize - 1];
+    count.store(count.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
 }
 
-/// The type returned by `set_logger` if `set_logger` has already been called.
-#[allow(missing_copy_implementations)]
-#[derive(Debug)]
-pub struct SetLoggerError(());
+/// Counts of records which were dropped rather than logged.
+///
+/// This is returned by `dropped_records`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct DroppedRecords {
+    counts: [u64; 9],
+}
 
-impl fmt::Display for SetLoggerError {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "attempted to set a logger after the logging system \
-                     was already initialized")
+impl DroppedRecords {
+    /// Returns the number of records dropped at `level`.
+    pub fn level(&self, level: LogLevel) -> u64 {
+        self.counts[level as usize - 1]
+    }
+
+    /// Returns the number of records dropped at any level.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
     }
 }
 
-// only implement the Error trait when it's available
+/// Returns the number of records dropped since the program started, by
+/// level.
+///
+/// A record counts as dropped if it passed the maximum log level but was
+/// logged while no logger was installed, or was discarded by an `AsyncLog`
+/// or `RingLog` because it couldn't be queued. Records filtered out by a
+/// level or by a logger's `enabled` method are not counted.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::LogLevel;
+///
+/// let dropped = log::dropped_records();
+/// if dropped.total() > 0 {
+///     println!("lost {} error records", dropped.level(LogLevel::Error));
+/// }
+/// ```
+pub fn dropped_records() -> DroppedRecords {
+    let mut counts = [0; 9];
+    for (count, dropped) in counts.iter_mut().zip(DROPPED_RECORDS.iter()) {
+        *count = dropped.load(Ordering::Relaxed) as u64;
+    }
+    DroppedRecords { counts }
+}
+
+#[cfg(feature = "statistics")]
+#[inline]
+fn record_logged(level: LogLevel, target: &str) {
+    stats::record(level, target)
+}
+
+#[cfg(not(feature = "statistics"))]
+#[inline(always)]
+fn record_logged(_: LogLevel, _: &str) {}
+
 #[cfg(not(feature = "freestanding"))]
-impl error::Error for SetLoggerError {
-    fn description(&self) -> &str { "set_logger() called multiple times" }
+fn target_enabled(level: LogLevel, target: &str) -> bool {
+    targets::enabled(level, target)
+}
+
+// per-target levels need an allocator
+#[cfg(feature = "freestanding")]
+#[inline(always)]
+fn target_enabled(_: LogLevel, _: &str) -> bool {
+    true
+}
+
+// The parts of a record which come from the logging thread rather than from
+// the log call.
+struct ThreadContext<'a> {
+    scope: &'a str,
+    span_id: Option<u64>,
+    trace_id: Option<TraceId>,
+    #[cfg(not(feature = "freestanding"))]
+    timestamp: Option<SystemTime>,
+    #[cfg(not(feature = "freestanding"))]
+    thread: Option<Thread>,
+    #[cfg(not(feature = "freestanding"))]
+    pid: Option<u32>,
 }
 
-struct LoggerGuard(usize);
+// Adds the thread's diagnostic context to a record's key-value pairs, and
+// provides the rest of the thread's context.
+#[cfg(not(feature = "freestanding"))]
+#[inline]
+fn with_context<F, T>(key_values: &[(&str, kv::Value)], f: F) -> T
+    where F: FnOnce(&[(&str, kv::Value)], &ThreadContext) -> T
+{
+    let span_id = current_span_id();
+    let trace_id = current_trace_id();
+    let timestamp = if cfg!(feature = "timestamp") {
+        Some(SystemTime::now())
+    } else {
+        None
+    };
+    let thread = context::current_thread();
+    context::with_context(key_values, |key_values, scope| {
+        f(key_values, &ThreadContext {
+            scope,
+            span_id,
+            trace_id,
+            timestamp,
+            thread,
+            pid: Some(context::current_pid()),
+        })
+    })
+}
+
+#[cfg(feature = "freestanding")]
+#[inline(always)]
+fn with_context<F, T>(key_values: &[(&str, kv::Value)], f: F) -> T
+    where F: FnOnce(&[(&str, kv::Value)], &ThreadContext) -> T
+{
+    f(key_values, &ThreadContext {
+        scope: "",
+        span_id: None,
+        trace_id: None,
+    })
+}
+
+// trace ids are kept in thread-local storage
+#[cfg(feature = "freestanding")]
+#[inline(always)]
+fn current_trace_id() -> Option<TraceId> {
+    None
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+pub fn __enabled(level: LogLevel, target: &str) -> bool {
+    if !target_enabled(level, target) {
+        return false;
+    }
 
-// no refcounting if freestanding
-#[cfg(not(feature = "freestanding"))]
-impl Drop for LoggerGuard {
-    fn drop(&mut self) {
-        REFCOUNT.fetch_sub(1, Ordering::SeqCst);
+    if let Some(logger) = logger() {
+        logger.enabled(&LogMetadata {
+            level,
+            target,
+            trace_id: current_trace_id(),
+            audit: false,
+        })
+    } else {
+        false
     }
 }
 
-// when not freestanding, LOGGER is &Box<Log>
-#[cfg(not(feature = "freestanding"))]
-impl Deref for LoggerGuard {
-    type Target = Box<Log>;
-
-    fn deref(&self) -> &Box<Log+'static> {
-        unsafe { mem::transmute(self.0) }
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+pub fn __log(level: LogLevel, target: &str, loc: &LogLocation,
+             args: fmt::Arguments, key_values: &[(&str, kv::Value)]) {
+    if !target_enabled(level, target) {
+        return;
     }
+
+    log_enabled(level, target, loc, args, key_values)
 }
 
-// when freestanding, LOGGER is &Log
-#[cfg(feature = "freestanding")]
-impl Deref for LoggerGuard {
-    type Target = &'static Log;
-    
-    fn deref(&self) -> &&'static Log {
-        unsafe { mem::transmute(self.0) }
+// Logs a record which has already passed the level checks.
+fn log_enabled(level: LogLevel, target: &str, loc: &LogLocation,
+               args: fmt::Arguments, key_values: &[(&str, kv::Value)]) {
+    match logger() {
+        Some(logger) => dispatch(&*logger, level, target, loc, args, key_values),
+        None => {
+            if !buffer_early(level, target, false, loc, args, key_values) {
+                record_dropped(level);
+            }
+        }
     }
 }
 
-#[cfg(not(feature = "freestanding"))]
-fn logger() -> Option<LoggerGuard> {
-    REFCOUNT.fetch_add(1, Ordering::SeqCst);
-    let logger = LOGGER.load(Ordering::SeqCst);
-    if logger == UNINITIALIZED || logger == INITIALIZING {
-        REFCOUNT.fetch_sub(1, Ordering::SeqCst);
-        None
-    } else {
-        Some(LoggerGuard(logger))
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+//
+// Logs an audit record, skipping every level check.
+#[doc(hidden)]
+pub fn __log_audit(level: LogLevel, target: &str, loc: &LogLocation,
+                   args: fmt::Arguments, key_values: &[(&str, kv::Value)]) {
+    match logger() {
+        Some(logger) => {
+            record_logged(level, target);
+            build_record(level, target, true, Some(&*logger), loc, args, key_values,
+                         |record| logger.log(record))
+        }
+        None => {
+            if !buffer_early(level, target, true, loc, args, key_values) {
+                record_dropped(level);
+            }
+        }
     }
 }
 
-#[cfg(feature = "freestanding")]
-fn logger() -> Option<LoggerGuard> {
-    // no refcounting when freestanding
-    Some(LoggerGuard(LOGGER.load(Ordering::SeqCst)))
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+pub fn __log_to(logger: &dyn Log, level: LogLevel, target: &str, loc: &LogLocation,
+                args: fmt::Arguments, key_values: &[(&str, kv::Value)]) {
+    dispatch(logger, level, target, loc, args, key_values)
 }
 
 // WARNING
 // This is not considered part of the crate's public API. It is subject to
 // change at any time.
 #[doc(hidden)]
-pub fn __enabled(level: LogLevel, target: &str) -> bool {
-    if let Some(logger) = logger() {
-        logger.enabled(&LogMetadata { level: level, target: target })
-    } else {
-        false
+pub fn __try_log(level: LogLevel, target: &str, loc: &LogLocation,
+                 args: fmt::Arguments, key_values: &[(&str, kv::Value)])
+                 -> Result<(), LogError> {
+    if !target_enabled(level, target) {
+        return Ok(());
+    }
+
+    match logger() {
+        Some(logger) => {
+            record_logged(level, target);
+            build_record(level, target, false, Some(&*logger), loc, args, key_values,
+                         |record| logger.try_log(record))
+        }
+        None => {
+            if buffer_early(level, target, false, loc, args, key_values) {
+                return Ok(());
+            }
+            record_dropped(level);
+            Err(LogError(LogErrorKind::NoLogger))
+        }
     }
 }
 
@@ -765,19 +2814,85 @@ pub fn __enabled(level: LogLevel, target: &str) -> bool {
 // This is not considered part of the crate's public API. It is subject to
 // change at any time.
 #[doc(hidden)]
-pub fn __log(level: LogLevel, target: &str, loc: &LogLocation,
-             args: fmt::Arguments) {
-    if let Some(logger) = logger() {
+#[cfg(not(feature = "freestanding"))]
+pub fn __flush_and_abort() -> ! {
+    flush();
+    ::std::process::abort()
+}
+
+// Keeps the record for a log call made before a logger was installed, if
+// buffer_early_records asked for that.
+#[cfg(not(feature = "freestanding"))]
+fn buffer_early(level: LogLevel, target: &str, audit: bool, loc: &LogLocation,
+                args: fmt::Arguments, key_values: &[(&str, kv::Value)]) -> bool {
+    build_record(level, target, audit, None, loc, args, key_values, early::push)
+}
+
+// buffering needs an allocator
+#[cfg(feature = "freestanding")]
+#[inline(always)]
+fn buffer_early(_: LogLevel, _: &str, _: bool, _: &LogLocation, _: fmt::Arguments,
+                _: &[(&str, kv::Value)]) -> bool {
+    false
+}
+
+// Passes the record for a log call to logger.
+fn dispatch(logger: &dyn Log, level: LogLevel, target: &str, loc: &LogLocation,
+            args: fmt::Arguments, key_values: &[(&str, kv::Value)]) {
+    record_logged(level, target);
+    build_record(level, target, false, Some(logger), loc, args, key_values,
+                 |record| logger.log(record))
+}
+
+// Builds a record from a log call and the current thread's context, and calls
+// f with it. logger is the logger the record is going to, if there is one yet,
+// which is asked whether it wants the record before a backtrace is captured.
+#[cfg_attr(not(all(feature = "backtrace", not(feature = "freestanding"))),
+           allow(unused_variables))]
+#[allow(clippy::too_many_arguments)]
+fn build_record<F, T>(level: LogLevel, target: &str, audit: bool, logger: Option<&dyn Log>,
+                      loc: &LogLocation, args: fmt::Arguments,
+                      key_values: &[(&str, kv::Value)], f: F) -> T
+    where F: FnOnce(&LogRecord) -> T
+{
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    let backtrace = if level <= LogLevel::Error && logger.is_none_or(|logger| {
+        logger.enabled(&LogMetadata {
+            level,
+            target,
+            trace_id: current_trace_id(),
+            audit,
+        })
+    }) {
+        Some(LazyBacktrace::new())
+    } else {
+        None
+    };
+
+    with_context(key_values, |key_values, cx| {
         let record = LogRecord {
             metadata: LogMetadata {
-                level: level,
-                target: target,
+                level,
+                target,
+                trace_id: cx.trace_id,
+                audit,
             },
             location: loc,
-            args: args
+            args,
+            key_values,
+            scope: cx.scope,
+            span_id: cx.span_id,
+            #[cfg(not(feature = "freestanding"))]
+            timestamp: cx.timestamp,
+            #[cfg(not(feature = "freestanding"))]
+            thread: cx.thread.as_ref(),
+            #[cfg(not(feature = "freestanding"))]
+            pid: cx.pid,
+            #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+            backtrace: backtrace.as_ref().map(RecordBacktrace::Lazy),
         };
-        logger.log(&record)
-    }
+        f(&record)
+    })
 }
 
 // WARNING
@@ -786,34 +2901,106 @@ pub fn __log(level: LogLevel, target: &str, loc: &LogLocation,
 #[inline(always)]
 #[doc(hidden)]
 pub fn __static_max_level() -> LogLevelFilter {
+    cmp::min(feature_max_level(), env_max_level())
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[inline(always)]
+#[doc(hidden)]
+pub fn __static_max_level_for(module_path: &str) -> LogLevelFilter {
+    for &(prefix, level) in STATIC_MODULE_MAX_LEVELS {
+        if module_matches(prefix, module_path) {
+            return cmp::min(feature_max_level(), level);
+        }
+    }
+    __static_max_level()
+}
+
+// Returns whether path is the module prefix or a module inside it, so that
+// `app::db` matches `app::db::pool` but not `app::dbx`.
+#[inline]
+fn module_matches(prefix: &str, path: &str) -> bool {
+    path.starts_with(prefix) &&
+        (path.len() == prefix.len() || path[prefix.len()..].starts_with("::"))
+}
+
+// generated by the build script from LOG_STATIC_MAX_LEVEL
+include!(concat!(env!("OUT_DIR"), "/static_max_levels.rs"));
+
+#[inline(always)]
+fn feature_max_level() -> LogLevelFilter {
     if !cfg!(debug_assertions) {
         // This is a release build. Check `release_max_level_*` first.
         if cfg!(feature = "release_max_level_off") {
             return LogLevelFilter::Off
+        } else if cfg!(feature = "release_max_level_critical") {
+            return LogLevelFilter::Critical
         } else if cfg!(feature = "release_max_level_error") {
             return LogLevelFilter::Error
         } else if cfg!(feature = "release_max_level_warn") {
             return LogLevelFilter::Warn
+        } else if cfg!(feature = "release_max_level_notice") {
+            return LogLevelFilter::Notice
         } else if cfg!(feature = "release_max_level_info") {
             return LogLevelFilter::Info
         } else if cfg!(feature = "release_max_level_debug") {
             return LogLevelFilter::Debug
         } else if cfg!(feature = "release_max_level_trace") {
             return LogLevelFilter::Trace
+        } else if cfg!(feature = "release_max_level_trace2") {
+            return LogLevelFilter::Trace2
+        } else if cfg!(feature = "release_max_level_trace3") {
+            return LogLevelFilter::Trace3
         }
     }
     if cfg!(feature = "max_level_off") {
         LogLevelFilter::Off
+    } else if cfg!(feature = "max_level_critical") {
+        LogLevelFilter::Critical
     } else if cfg!(feature = "max_level_error") {
         LogLevelFilter::Error
     } else if cfg!(feature = "max_level_warn") {
         LogLevelFilter::Warn
+    } else if cfg!(feature = "max_level_notice") {
+        LogLevelFilter::Notice
     } else if cfg!(feature = "max_level_info") {
         LogLevelFilter::Info
     } else if cfg!(feature = "max_level_debug") {
         LogLevelFilter::Debug
+    } else if cfg!(feature = "max_level_trace") {
+        LogLevelFilter::Trace
+    } else if cfg!(feature = "max_level_trace2") {
+        LogLevelFilter::Trace2
     } else {
+        LogLevelFilter::Trace3
+    }
+}
+
+// set by the build script from LOG_STATIC_MAX_LEVEL
+#[inline(always)]
+fn env_max_level() -> LogLevelFilter {
+    if cfg!(log_static_max_level = "off") {
+        LogLevelFilter::Off
+    } else if cfg!(log_static_max_level = "critical") {
+        LogLevelFilter::Critical
+    } else if cfg!(log_static_max_level = "error") {
+        LogLevelFilter::Error
+    } else if cfg!(log_static_max_level = "warn") {
+        LogLevelFilter::Warn
+    } else if cfg!(log_static_max_level = "notice") {
+        LogLevelFilter::Notice
+    } else if cfg!(log_static_max_level = "info") {
+        LogLevelFilter::Info
+    } else if cfg!(log_static_max_level = "debug") {
+        LogLevelFilter::Debug
+    } else if cfg!(log_static_max_level = "trace") {
         LogLevelFilter::Trace
+    } else if cfg!(log_static_max_level = "trace2") {
+        LogLevelFilter::Trace2
+    } else {
+        LogLevelFilter::Trace3
     }
 }
 
@@ -822,90 +3009,152 @@ mod tests {
     #[cfg(not(feature = "freestanding"))]
     use std::error::Error;
     use collections::string::ToString;
+    use collections::vec::Vec;
     #[cfg(not(feature = "freestanding"))]
-    use super::{LogLevel, LogLevelFilter, SetLoggerError};
+    use super::{LogLevel, LogLevelFilter, ParseLevelError, SetLoggerError};
     #[cfg(feature = "freestanding")]
-    use super::{LogLevel, LogLevelFilter};
+    use super::{LogLevel, LogLevelFilter, ParseLevelError};
 
-    #[cfg(not(feature = "freestanding"))]
     #[test]
     fn test_loglevelfilter_from_str() {
         let tests = [
             ("off",   Ok(LogLevelFilter::Off)),
+            ("critical", Ok(LogLevelFilter::Critical)),
             ("error", Ok(LogLevelFilter::Error)),
             ("warn",  Ok(LogLevelFilter::Warn)),
+            ("notice", Ok(LogLevelFilter::Notice)),
             ("info",  Ok(LogLevelFilter::Info)),
             ("debug", Ok(LogLevelFilter::Debug)),
             ("trace", Ok(LogLevelFilter::Trace)),
+            ("trace2", Ok(LogLevelFilter::Trace2)),
             ("OFF",   Ok(LogLevelFilter::Off)),
+            ("CRITICAL", Ok(LogLevelFilter::Critical)),
             ("ERROR", Ok(LogLevelFilter::Error)),
             ("WARN",  Ok(LogLevelFilter::Warn)),
+            ("NOTICE", Ok(LogLevelFilter::Notice)),
             ("INFO",  Ok(LogLevelFilter::Info)),
             ("DEBUG", Ok(LogLevelFilter::Debug)),
             ("TRACE", Ok(LogLevelFilter::Trace)),
-            ("asdf",  Err(())),
+            ("TRACE3", Ok(LogLevelFilter::Trace3)),
+            ("asdf",  Err(ParseLevelError::new("asdf"))),
             ];
         for &(s, ref expected) in &tests {
             assert_eq!(expected, &s.parse());
         }
     }
 
-    #[cfg(not(feature = "freestanding"))]
     #[test]
     fn test_loglevel_from_str() {
         let tests = [
-            ("OFF",   Err(())),
+            ("OFF",   Err(ParseLevelError::new("OFF"))),
+            ("critical", Ok(LogLevel::Critical)),
             ("error", Ok(LogLevel::Error)),
             ("warn",  Ok(LogLevel::Warn)),
+            ("notice", Ok(LogLevel::Notice)),
             ("info",  Ok(LogLevel::Info)),
             ("debug", Ok(LogLevel::Debug)),
             ("trace", Ok(LogLevel::Trace)),
+            ("trace2", Ok(LogLevel::Trace2)),
+            ("CRITICAL", Ok(LogLevel::Critical)),
             ("ERROR", Ok(LogLevel::Error)),
             ("WARN",  Ok(LogLevel::Warn)),
+            ("NOTICE", Ok(LogLevel::Notice)),
             ("INFO",  Ok(LogLevel::Info)),
             ("DEBUG", Ok(LogLevel::Debug)),
             ("TRACE", Ok(LogLevel::Trace)),
-            ("asdf",  Err(())),
+            ("TRACE3", Ok(LogLevel::Trace3)),
+            ("asdf",  Err(ParseLevelError::new("asdf"))),
             ];
         for &(s, ref expected) in &tests {
             assert_eq!(expected, &s.parse());
         }
     }
 
-    #[cfg(feature = "freestanding")]
     #[test]
-    fn test_loglevelfilter_from_str() {
-        let tests = [
-            ("OFF",   Ok(LogLevelFilter::Off)),
-            ("ERROR", Ok(LogLevelFilter::Error)),
-            ("WARN",  Ok(LogLevelFilter::Warn)),
-            ("INFO",  Ok(LogLevelFilter::Info)),
-            ("DEBUG", Ok(LogLevelFilter::Debug)),
-            ("TRACE", Ok(LogLevelFilter::Trace)),
-            ("asdf",  Err(())),
-            ];
-        for &(s, ref expected) in &tests {
-            assert_eq!(expected, &s.parse());
+    fn test_numeric_from_str() {
+        let filters = [LogLevelFilter::Off, LogLevelFilter::Critical, LogLevelFilter::Error,
+                       LogLevelFilter::Warn, LogLevelFilter::Notice, LogLevelFilter::Info,
+                       LogLevelFilter::Debug, LogLevelFilter::Trace, LogLevelFilter::Trace2,
+                       LogLevelFilter::Trace3];
+        for &filter in &filters {
+            assert_eq!(Ok(filter), (filter as usize).to_string().parse());
+            assert_eq!(filter.to_log_level().ok_or(ParseLevelError::new("0")),
+                       (filter as usize).to_string().parse());
         }
+        assert_eq!(Err(ParseLevelError::new("10")), "10".parse::<LogLevelFilter>());
+        assert_eq!(Err(ParseLevelError::new("03")), "03".parse::<LogLevel>());
+        assert_eq!(Err(ParseLevelError::new("-1")), "-1".parse::<LogLevel>());
     }
 
-    #[cfg(feature = "freestanding")]
     #[test]
-    fn test_loglevel_from_str() {
-        let tests = [
-            ("OFF",   Err(())),
-            ("ERROR", Ok(LogLevel::Error)),
-            ("WARN",  Ok(LogLevel::Warn)),
-            ("INFO",  Ok(LogLevel::Info)),
-            ("DEBUG", Ok(LogLevel::Debug)),
-            ("TRACE", Ok(LogLevel::Trace)),
-            ("asdf",  Err(())),
-            ];
-        for &(s, ref expected) in &tests {
-            assert_eq!(expected, &s.parse());
+    fn test_level_numbers() {
+        assert_eq!(1, LogLevel::Critical as usize);
+        assert_eq!(2, LogLevel::Error as usize);
+        assert_eq!(5, LogLevel::Info as usize);
+        assert_eq!(7, LogLevel::Trace as usize);
+        assert_eq!(Ok(LogLevel::Error), "2".parse());
+        assert_eq!(Ok(LogLevelFilter::Warn), "3".parse());
+        assert_eq!(Ok(LogLevelFilter::Info), "5".parse());
+    }
+
+    #[test]
+    fn test_alias_from_str() {
+        assert_eq!(Ok(LogLevel::Critical), "crit".parse());
+        assert_eq!(Ok(LogLevelFilter::Critical), "Fatal".parse());
+        assert_eq!(Ok(LogLevel::Error), "err".parse());
+        assert_eq!(Ok(LogLevel::Warn), "Warning".parse());
+        assert_eq!(Ok(LogLevelFilter::Trace), "verbose".parse());
+        assert_eq!(Err(ParseLevelError::new("WARNINGS")), "WARNINGS".parse::<LogLevel>());
+    }
+
+    #[test]
+    fn test_from_usize() {
+        for u in 0..11 {
+            assert_eq!(LogLevelFilter::from_usize(u).map(|f| f as usize),
+                       if u <= 9 { Some(u) } else { None });
+            assert_eq!(LogLevel::from_usize(u).map(|l| l as usize),
+                       if (1..=9).contains(&u) { Some(u) } else { None });
         }
     }
 
+    #[test]
+    fn test_level_conversions() {
+        use core::convert::TryFrom;
+        use super::TryFromLevelFilterError;
+
+        let filter: LogLevelFilter = LogLevel::Debug.into();
+        assert_eq!(LogLevelFilter::Debug, filter);
+        assert_eq!(Ok(LogLevel::Warn), LogLevel::try_from(LogLevelFilter::Warn));
+        assert_eq!(Err(TryFromLevelFilterError(())), LogLevel::try_from(LogLevelFilter::Off));
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut levels = LogLevel::iter();
+        assert_eq!(Some(LogLevel::Critical), levels.next());
+        assert_eq!(8, levels.clone().count());
+        assert_eq!(Some(LogLevel::Trace3), levels.last());
+
+        let filters = LogLevelFilter::iter().map(|f| f as usize).collect::<Vec<_>>();
+        assert_eq!(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9], &filters[..]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_hash() {
+        use std::collections::HashMap;
+
+        let mut counts = HashMap::new();
+        *counts.entry(LogLevel::Warn).or_insert(0) += 1;
+        *counts.entry(LogLevel::Warn).or_insert(0) += 1;
+        *counts.entry(LogLevel::Info).or_insert(0) += 1;
+        assert_eq!(Some(&2), counts.get(&LogLevel::Warn));
+        assert_eq!(2, counts.len());
+
+        let filters = LogLevelFilter::iter().map(|f| (f, f.as_str())).collect::<HashMap<_, _>>();
+        assert_eq!(Some(&"OFF"), filters.get(&LogLevelFilter::Off));
+    }
+
     #[test]
     fn test_loglevel_show() {
         assert_eq!("INFO", LogLevel::Info.to_string());
@@ -918,11 +3167,43 @@ mod tests {
         assert_eq!("ERROR", LogLevelFilter::Error.to_string());
     }
 
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_show_alternate() {
+        assert_eq!("info", format!("{:#}", LogLevel::Info));
+        assert_eq!("trace", format!("{:#}", LogLevel::Trace));
+        assert_eq!("off", format!("{:#}", LogLevelFilter::Off));
+        assert_eq!("warn", format!("{:#}", LogLevelFilter::Warn));
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_show_padding() {
+        assert_eq!("INFO |", format!("{:<5}|", LogLevel::Info));
+        assert_eq!("INFO |", format!("{:<5}|", LogLevelFilter::Info));
+        assert_eq!("  off|", format!("{:>#5}|", LogLevelFilter::Off));
+        assert_eq!("ERR|", format!("{:.3}|", LogLevelFilter::Error));
+    }
+
+    #[test]
+    fn test_as_str() {
+        assert_eq!("WARN", LogLevel::Warn.as_str());
+        assert_eq!("TRACE", LogLevel::Trace.as_str());
+        assert_eq!("OFF", LogLevelFilter::Off.as_str());
+        assert_eq!("DEBUG", LogLevelFilter::Debug.as_str());
+    }
+
     #[test]
     fn test_cross_cmp() {
         assert!(LogLevel::Debug > LogLevelFilter::Error);
         assert!(LogLevelFilter::Warn < LogLevel::Trace);
         assert!(LogLevelFilter::Off < LogLevel::Error);
+        assert!(LogLevel::Critical < LogLevelFilter::Error);
+        assert!(LogLevelFilter::Off < LogLevel::Critical);
+        assert!(LogLevel::Warn < LogLevelFilter::Notice);
+        assert!(LogLevelFilter::Notice < LogLevel::Info);
+        assert!(LogLevel::Trace2 > LogLevelFilter::Trace);
+        assert!(LogLevelFilter::Trace2 < LogLevel::Trace3);
     }
 
     #[test]
@@ -945,10 +3226,397 @@ mod tests {
         assert_eq!(LogLevelFilter::Trace, LogLevel::Trace.to_log_level_filter());
     }
 
+    #[test]
+    fn test_module_matches() {
+        use super::module_matches;
+        assert!(module_matches("app::db", "app::db"));
+        assert!(module_matches("app::db", "app::db::pool"));
+        assert!(!module_matches("app::db", "app::dbx"));
+        assert!(!module_matches("app::db", "app"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_owned_record() {
+        use std::time::UNIX_EPOCH;
+        use super::{kv, LogLocation, LogMetadata, LogRecord, TraceId};
+
+        static LOC: LogLocation = LogLocation {
+            __module_path: "a::b",
+            __file: "a/b.rs",
+            __line: 7,
+        };
+        let pairs = [("id", kv::Value::from(42u32))];
+        let record = LogRecord {
+            metadata: LogMetadata {
+                level: LogLevel::Warn,
+                target: "a",
+                trace_id: Some(TraceId::from_parts(1, 2)),
+                audit: false,
+            },
+            location: &LOC,
+            args: format_args!("hello {}", "world"),
+            key_values: &pairs,
+            scope: "conn 1",
+            span_id: Some(3),
+            timestamp: Some(UNIX_EPOCH),
+            thread: None,
+            pid: Some(1),
+            #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+            backtrace: None,
+        };
+        let owned = record.to_owned();
+        assert_eq!(LogLevel::Warn, owned.level());
+        assert_eq!("a", owned.target());
+        assert_eq!("hello world", owned.message());
+        assert_eq!(7, owned.location().line());
+        assert_eq!("conn 1", owned.scope());
+        assert_eq!(Some(3), owned.span_id());
+        assert_eq!(Some(TraceId::from_parts(1, 2)), owned.trace_id());
+        assert_eq!(Some(UNIX_EPOCH), owned.timestamp());
+        assert_eq!(Some(1), owned.pid());
+        owned.with_record(|record| {
+            assert_eq!("hello world", record.args().to_string());
+            assert_eq!("42", record.key_values().get("id").unwrap().to_string());
+        });
+    }
+
+    #[test]
+    #[cfg(all(feature = "backtrace", not(feature = "freestanding")))]
+    fn test_backtrace_only_when_enabled() {
+        use std::sync::Mutex;
+        use super::{Log, LogMetadata, LogRecord};
+
+        struct Logger {
+            enabled: bool,
+            backtraces: Mutex<Vec<bool>>,
+        }
+
+        impl Log for Logger {
+            fn enabled(&self, _: &LogMetadata) -> bool {
+                self.enabled
+            }
+
+            fn log(&self, record: &LogRecord) {
+                let resolved = record.backtrace().map(|backtrace| {
+                    backtrace.frames().iter().any(|frame| !frame.symbols().is_empty())
+                });
+                self.backtraces.lock().unwrap().push(resolved.unwrap_or(false));
+            }
+        }
+
+        let enabled = Logger { enabled: true, backtraces: Mutex::new(vec![]) };
+        error_to!(&enabled, "failed");
+        info_to!(&enabled, "done");
+        assert_eq!(vec![true, false], *enabled.backtraces.lock().unwrap());
+
+        let disabled = Logger { enabled: false, backtraces: Mutex::new(vec![]) };
+        error_to!(&disabled, "failed");
+        assert_eq!(vec![false], *disabled.backtraces.lock().unwrap());
+    }
+
+    #[test]
+    fn test_record_builder() {
+        use core::fmt;
+        use super::{LogLocation, LogMetadata, LogRecord};
+
+        static LOC: LogLocation = LogLocation {
+            __module_path: "bridge",
+            __file: "bridge.c",
+            __line: 12,
+        };
+
+        // the arguments only live as long as the call they're passed to
+        fn check(args: fmt::Arguments) {
+            let metadata = LogMetadata::builder()
+                               .level(LogLevel::Debug)
+                               .target("c")
+                               .build();
+            let record = LogRecord::builder()
+                             .metadata(metadata)
+                             .location(&LOC)
+                             .args(args)
+                             .build();
+            assert_eq!(LogLevel::Debug, record.level());
+            assert_eq!("c", record.target());
+            assert_eq!("bridge.c", record.location().file());
+            assert_eq!("5", record.args().to_string());
+        }
+        check(format_args!("{}", 5));
+
+        let record = LogRecord::builder().build();
+        assert_eq!(LogLevel::Info, record.level());
+        assert_eq!("", record.target());
+        assert_eq!("", record.args().to_string());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_with_logger() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::string::String;
+        use std::sync::Mutex;
+        use std::vec::Vec;
+        use super::{with_logger, max_level, set_max_level, Log, LogMetadata, LogRecord};
+
+        struct Collect(Mutex<Vec<String>>);
+
+        impl Log for Collect {
+            fn enabled(&self, _: &LogMetadata) -> bool { true }
+
+            fn log(&self, record: &LogRecord) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        let saved = max_level();
+        set_max_level(LogLevelFilter::Trace);
+        let outer = Collect(Mutex::new(Vec::new()));
+        let inner = Collect(Mutex::new(Vec::new()));
+        with_logger(&outer, || {
+            info!("a");
+            with_logger(&inner, || { info!("b"); });
+            info!("c");
+
+            // the outer logger is put back when f panics, too. The panic may
+            // be logged if another test installed the panic hook.
+            let panicking = Collect(Mutex::new(Vec::new()));
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                with_logger(&panicking, || panic!("in with_logger"))
+            }));
+            assert!(result.is_err());
+            info!("d");
+        });
+        set_max_level(saved);
+        assert_eq!(vec!["a", "c", "d"], *outer.0.lock().unwrap());
+        assert_eq!(vec!["b"], *inner.0.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_error_field() {
+        use std::error::Error;
+        use std::io;
+        use std::string::{String, ToString};
+        use std::sync::Mutex;
+        use std::vec::Vec;
+        use super::{Log, LogMetadata, LogRecord};
+
+        struct Collect(Mutex<Vec<String>>);
+
+        impl Log for Collect {
+            fn enabled(&self, _: &LogMetadata) -> bool { true }
+
+            fn log(&self, record: &LogRecord) {
+                let err = record.error().map(|err| err.to_string());
+                let msg = format!("{} {:?} {}", record.args(), err, record.key_values().len());
+                self.0.lock().unwrap().push(msg);
+            }
+        }
+
+        let logger = Collect(Mutex::new(Vec::new()));
+        let e = io::Error::other("disk on fire");
+        error_to!(&logger, err = &e; "failed to open {}", "a");
+        let dyn_err: &dyn Error = &e;
+        error_to!(&logger, err = &dyn_err; path = "b", "failed");
+        error_to!(&logger, err = "not an error", "plain");
+        assert_eq!(vec!["failed to open a Some(\"disk on fire\") 1".to_string(),
+                        "failed Some(\"disk on fire\") 2".to_string(),
+                        "plain None 1".to_string()],
+                   *logger.0.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_log_to() {
+        use std::string::String;
+        use std::sync::Mutex;
+        use std::vec::Vec;
+        use super::{Log, LogMetadata, LogRecord};
+
+        struct Collect(Mutex<Vec<String>>);
+
+        impl Log for Collect {
+            fn enabled(&self, _: &LogMetadata) -> bool { true }
+
+            fn log(&self, record: &LogRecord) {
+                let msg = format!("{} {} {}", record.level(), record.target(),
+                                  record.args());
+                self.0.lock().unwrap().push(msg);
+            }
+        }
+
+        let logger = Collect(Mutex::new(Vec::new()));
+        log_to!(&logger, LogLevel::Info, "a {}", 1);
+        warn_to!(&logger, target: "plugin", "b");
+        trace_to!(&logger, port = 8080, "c");
+        assert_eq!(vec![format!("INFO {} a 1", module_path!()),
+                        "WARN plugin b".to_string(),
+                        format!("TRACE {} c", module_path!())],
+                   *logger.0.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_control() {
+        use std::sync::Mutex;
+        use std::vec::Vec;
+        use super::{Log, LogMetadata, LogRecord, ControlMessage, LogLevelFilter};
+
+        struct Collect(Mutex<Vec<&'static str>>, Mutex<Option<LogLevelFilter>>);
+
+        impl Log for Collect {
+            fn enabled(&self, _: &LogMetadata) -> bool { true }
+
+            fn log(&self, _: &LogRecord) {}
+
+            fn flush(&self) {
+                self.0.lock().unwrap().push("flush");
+            }
+
+            fn reopen(&self) {
+                self.0.lock().unwrap().push("reopen");
+            }
+
+            fn handle_control(&self, msg: ControlMessage) {
+                match msg {
+                    ControlMessage::Flush => self.flush(),
+                    ControlMessage::Reopen => self.reopen(),
+                    ControlMessage::SetLevel(level) => *self.1.lock().unwrap() = Some(level),
+                }
+            }
+        }
+
+        // other tests depend on the global level, so put it back afterwards
+        let saved = super::max_level();
+        let logger = Collect(Mutex::new(Vec::new()), Mutex::new(None));
+        super::with_logger(&logger, || {
+            super::control(ControlMessage::Reopen);
+            super::control(ControlMessage::SetLevel(LogLevelFilter::Trace));
+            super::control(ControlMessage::Flush);
+        });
+        super::set_max_level(saved);
+        assert_eq!(vec!["reopen", "flush"], *logger.0.lock().unwrap());
+        assert_eq!(Some(LogLevelFilter::Trace), *logger.1.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_log_impls() {
+        use std::boxed::Box;
+        use std::sync::{Arc, Mutex};
+        use super::{Log, LogMetadata, LogRecord};
+        use combinators::Tee;
+
+        struct Count(Mutex<usize>);
+
+        impl Log for Count {
+            fn enabled(&self, _: &LogMetadata) -> bool { true }
+
+            fn log(&self, _: &LogRecord) {
+                *self.0.lock().unwrap() += 1;
+            }
+        }
+
+        let shared = Arc::new(Count(Mutex::new(0)));
+        let local = Count(Mutex::new(0));
+        let boxed: Box<dyn Log> = Box::new(Tee(shared.clone(), &local));
+        info_to!(&boxed, "a");
+        info_to!(&Tee(&boxed, shared.clone()), "b");
+        assert_eq!(3, *shared.0.lock().unwrap());
+        assert_eq!(2, *local.0.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_log_dbg() {
+        use std::vec::Vec;
+
+        ::test::init().unwrap();
+        ::test::clear();
+
+        let mut calls = 0;
+        let doubled = log_dbg!({ calls += 1; 21 } * 2);
+        let items: Vec<u8> = log_dbg!(target: "dbg_test", vec![1, 2]);
+        assert_eq!(42, doubled);
+        assert_eq!(1, calls);
+        assert_eq!(vec![1, 2], items);
+
+        let records = ::test::drain();
+        assert_eq!(2, records.len());
+        assert_eq!(LogLevel::Debug, records[0].level());
+        assert_eq!("{ calls += 1; 21 } * 2 = 42", records[0].message());
+        assert_eq!("dbg_test", records[1].target());
+        assert_eq!("vec![1, 2] = [1, 2]", records[1].message());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_audit() {
+        use super::{set_target_max_level, remove_target_max_level, LogLevelFilter};
+
+        ::test::init().unwrap();
+        ::test::clear();
+        set_target_max_level("audit_test", LogLevelFilter::Off);
+        warn!(target: "audit_test", "filtered");
+        audit!(target: "audit_test", user = "alice", "login");
+        remove_target_max_level("audit_test");
+
+        let records = ::test::drain();
+        assert_eq!(1, records.len());
+        assert!(records[0].is_audit());
+        assert_eq!(LogLevel::Notice, records[0].level());
+        assert_eq!("login", records[0].message());
+
+        info!(target: "audit_test", "plain");
+        assert!(!::test::drain()[0].is_audit());
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_try_log() {
+        use super::{Log, LogMetadata, LogRecord, LogError, LogErrorKind};
+
+        struct Full;
+
+        impl Log for Full {
+            fn enabled(&self, _: &LogMetadata) -> bool { true }
+
+            fn log(&self, _: &LogRecord) {}
+
+            fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+                if record.target() == "full" {
+                    Err(LogError::new(LogErrorKind::Full))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        ::test::init().unwrap();
+        super::with_logger(&Full, || {
+            assert_eq!(Ok(()), try_info!("a"));
+            assert_eq!(Err(LogError::new(LogErrorKind::Full)),
+                       try_error!(target: "full", code = 7, "b {}", 1));
+        });
+        assert_eq!(Ok(()), try_warn!(target: "full", "c"));
+    }
+
     #[test]
     #[cfg(not(feature = "freestanding"))]
+    #[allow(deprecated)]
     fn test_error_trait() {
         let e = SetLoggerError(());
         assert_eq!(e.description(), "set_logger() called multiple times");
     }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    #[allow(deprecated)]
+    fn test_parse_level_error() {
+        let e = "loud".parse::<LogLevelFilter>().unwrap_err();
+        assert_eq!("loud", e.level());
+        assert_eq!("invalid log level `loud`", e.to_string());
+        assert_eq!("invalid log level", e.description());
+    }
 }
@@ -0,0 +1,268 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Per-statement state for the macros which limit how often a statement logs.
+//
+// The state lives in a static declared by each macro call, so it has to be
+// made of atomics with constant initializers. Times are kept as milliseconds
+// since START, plus one so that zero can mean "never".
+//
+// Times are 64 bits wide where the target has 64-bit atomics. Elsewhere they
+// are usizes, which on 32-bit targets run out after about 49 days. They
+// saturate rather than wrap around, so from then on rate limited statements
+// log every message instead of getting stuck.
+
+use std::boxed::Box;
+use std::convert::TryFrom;
+use std::ptr;
+use std::sync::Once;
+#[cfg(target_has_atomic = "64")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[cfg(target_has_atomic = "64")]
+type Millis = u64;
+#[cfg(target_has_atomic = "64")]
+type AtomicMillis = AtomicU64;
+#[cfg(not(target_has_atomic = "64"))]
+type Millis = usize;
+#[cfg(not(target_has_atomic = "64"))]
+type AtomicMillis = AtomicUsize;
+
+static INIT: Once = Once::new();
+static mut START: *const Instant = ptr::null();
+
+fn now() -> Millis {
+    let start = unsafe {
+        INIT.call_once(|| {
+            START = Box::into_raw(Box::new(Instant::now()));
+        });
+        &*START
+    };
+    millis(start.elapsed()).saturating_add(1)
+}
+
+fn millis(duration: Duration) -> Millis {
+    let millis = duration.as_secs()
+                         .saturating_mul(1000)
+                         .saturating_add(u64::from(duration.subsec_millis()));
+    Millis::try_from(millis).unwrap_or(Millis::MAX)
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct RateLimit {
+    // the end of the current window, or 0 before the first record
+    until: AtomicMillis,
+    suppressed: AtomicUsize,
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[allow(clippy::declare_interior_mutable_const)]
+pub const __RATE_LIMIT_INIT: RateLimit = RateLimit {
+    until: AtomicMillis::new(0),
+    suppressed: AtomicUsize::new(0),
+};
+
+impl RateLimit {
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Returns the number of records suppressed since the last one if a record
+    // may be logged now, starting a new window of length interval.
+    #[doc(hidden)]
+    pub fn __check(&self, interval: Duration) -> Option<usize> {
+        let now = now();
+        let until = self.until.load(Ordering::SeqCst);
+        if (until != 0 && now < until) ||
+           self.until.compare_exchange(until, now.saturating_add(millis(interval)),
+                                       Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            self.suppressed.fetch_add(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(self.suppressed.swap(0, Ordering::SeqCst))
+    }
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Counter {
+    count: AtomicUsize,
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[allow(clippy::declare_interior_mutable_const)]
+pub const __COUNTER_INIT: Counter = Counter { count: AtomicUsize::new(0) };
+
+impl Counter {
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Counts a record, returning true for the first and every nth after it.
+    #[doc(hidden)]
+    pub fn __every(&self, n: usize) -> bool {
+        let n = if n == 0 { 1 } else { n };
+        self.count.fetch_add(1, Ordering::Relaxed).is_multiple_of(n)
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Counts a record, returning true for the first n. The count stops at n,
+    // so that it can't wrap around however long the program runs.
+    #[doc(hidden)]
+    pub fn __first(&self, n: usize) -> bool {
+        self.count.load(Ordering::Relaxed) < n &&
+            self.count.fetch_add(1, Ordering::Relaxed) < n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+    use std::thread;
+    use std::time::Duration;
+    use std::vec::Vec;
+
+    use super::{__COUNTER_INIT, __RATE_LIMIT_INIT};
+
+    #[test]
+    fn test_rate_limit() {
+        let limit = __RATE_LIMIT_INIT;
+        let interval = Duration::from_millis(200);
+        assert_eq!(Some(0), limit.__check(interval));
+        assert_eq!(None, limit.__check(interval));
+        assert_eq!(None, limit.__check(interval));
+        thread::sleep(Duration::from_millis(250));
+        assert_eq!(Some(2), limit.__check(interval));
+
+        let limit = __RATE_LIMIT_INIT;
+        assert_eq!(Some(0), limit.__check(Duration::from_secs(0)));
+        assert_eq!(Some(0), limit.__check(Duration::from_secs(0)));
+
+        // an interval too long to add to the clock doesn't overflow
+        let limit = __RATE_LIMIT_INIT;
+        assert_eq!(Some(0), limit.__check(Duration::from_secs(u64::MAX)));
+        assert_eq!(None, limit.__check(Duration::from_secs(u64::MAX)));
+    }
+
+    #[test]
+    fn test_every() {
+        let counter = __COUNTER_INIT;
+        let logged = (0..10).filter(|_| counter.__every(4)).count();
+        assert_eq!(3, logged);
+
+        let counter = __COUNTER_INIT;
+        assert!((0..3).all(|_| counter.__every(0)));
+    }
+
+    #[test]
+    fn test_first() {
+        let counter = __COUNTER_INIT;
+        assert_eq!(vec![true, true, false, false],
+                   (0..4).map(|_| counter.__first(2)).collect::<Vec<_>>());
+
+        let counter = __COUNTER_INIT;
+        assert!(!counter.__first(0));
+    }
+
+    #[test]
+    fn test_first_n_macros() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        for i in 0..5 {
+            log_once!(::LogLevel::Warn, "deprecated option {}", i);
+            log_first_n!(3, target: "fallback", ::LogLevel::Info, "using default {}", i);
+        }
+
+        let records = ::test::drain();
+        let messages = records.iter().map(|r| r.message()).collect::<Vec<_>>();
+        assert_eq!(vec!["deprecated option 0", "using default 0", "using default 1",
+                        "using default 2"],
+                   messages);
+        assert_eq!("fallback", records[1].target());
+    }
+
+    #[test]
+    fn test_every_n_macro() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        for i in 0..7 {
+            log_every_n!(3, ::LogLevel::Info, "processed {} items", i);
+        }
+
+        let records = ::test::drain();
+        let messages = records.iter().map(|r| r.message()).collect::<Vec<_>>();
+        assert_eq!(vec!["processed 0 items", "processed 3 items", "processed 6 items"],
+                   messages);
+    }
+
+    #[test]
+    fn test_rate_limited_macros() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        for i in 0..4 {
+            if i == 3 {
+                thread::sleep(Duration::from_millis(250));
+            }
+            warn_rate_limited!(Duration::from_millis(200), "storm {}", i);
+        }
+
+        let records = ::test::drain();
+        let summary = records.iter()
+                             .map(|r| (r.message(), r.key_values().to_vec()))
+                             .collect::<Vec<_>>();
+        assert_eq!(vec![("storm 0", vec![]),
+                        ("storm 3", vec![("suppressed".to_string(), "2".to_string())])],
+                   summary);
+    }
+
+    #[test]
+    fn test_level_rate_limited_macros() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        // test::init only enables up to Trace
+        let saved = ::max_level();
+        ::set_max_level(::LogLevelFilter::Trace3);
+        for _ in 0..2 {
+            critical_rate_limited!(Duration::from_secs(60), "limited");
+            notice_rate_limited!(Duration::from_secs(60), "limited");
+            trace2_rate_limited!(Duration::from_secs(60), "limited");
+            trace3_rate_limited!(Duration::from_secs(60), "limited");
+        }
+        ::set_max_level(saved);
+
+        let records = ::test::drain();
+        let levels = records.iter().map(|r| r.level()).collect::<Vec<_>>();
+        assert_eq!(vec![::LogLevel::Critical, ::LogLevel::Notice, ::LogLevel::Trace2,
+                        ::LogLevel::Trace3],
+                   levels);
+    }
+}
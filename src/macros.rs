@@ -12,28 +12,293 @@
 /// This macro will generically log with the specified `LogLevel` and `format!`
 /// based argument list.
 ///
+/// The format string may be preceded by a list of structured key-value pairs
+/// which are attached to the record. A value written as `key = value` is
+/// captured through its `Display` implementation, while `key = ?value` is
+/// captured through its `Debug` implementation. An error may be attached
+/// under the `err` key by writing `err = &e;`, followed by the rest of the
+/// arguments; see `LogRecord::error`.
+///
 /// The `max_level_*` features can be used to statically disable logging at
-/// various levels.
+/// various levels. So can the `LOG_STATIC_MAX_LEVEL` environment variable,
+/// which is read when the `log` crate is compiled: building with
+/// `LOG_STATIC_MAX_LEVEL=warn` compiles out all `info!`, `debug!` and `trace!`
+/// calls across the whole dependency tree. If both are present, the more
+/// restrictive level is used.
+///
+/// `LOG_STATIC_MAX_LEVEL` may also set levels for individual modules, in the
+/// form `warn,my_app=trace,hyper::client=off`. Calls in a module whose path
+/// starts with one of the given prefixes use the level of the longest such
+/// prefix instead of the global one, although the `max_level_*` features
+/// still apply.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel;
+///
+/// # fn main() {
+/// let path = "/etc/hosts";
+/// let count = 3;
+/// log!(LogLevel::Info, path = ?path, count = count, "opened file");
+/// # }
+/// ```
 #[macro_export]
 macro_rules! log {
-    (target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+    (target: $target:expr, $lvl:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (global) [] target: $target, $lvl, $($arg)+)
+    );
+    ($lvl:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (callsite) [] target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+/// Logs a message to a specific logger rather than the global one.
+///
+/// This takes a `&Log` followed by the same arguments as `log!`. The record is
+/// passed straight to the given logger, so the global maximum log level and
+/// per-target levels don't apply, although the `max_level_*` features and
+/// `LOG_STATIC_MAX_LEVEL` still do. The `error_to!`, `warn_to!`, `info_to!`,
+/// `debug_to!` and `trace_to!` macros work the same way.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::{LogLevel, NopLogger};
+///
+/// # fn main() {
+/// let logger = NopLogger;
+/// log_to!(&logger, LogLevel::Info, "plugin loaded");
+/// warn_to!(&logger, target: "plugin", "slow start: {}ms", 250);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! log_to {
+    ($logger:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (to $logger) [] target: $target, $lvl, $($arg)+)
+    );
+    ($logger:expr, $lvl:expr, $($arg:tt)+) => (
+        log_to!($logger, target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+//
+// Peels `key = value,` and `key = ?value,` pairs off the front of the
+// arguments to `log!` until the format string is reached.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_kv {
+    (@munch $sink:tt [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     err = $err:expr; $($arg:tt)+) => (
+        __log_kv!(@munch $sink [$($kvs)* ("err", $crate::kv::Value::from_error($err)),]
+                  target: $target, $lvl, $($arg)+)
+    );
+    (@munch $sink:tt [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     $key:ident = ? $value:expr, $($arg:tt)+) => (
+        __log_kv!(@munch $sink
+                  [$($kvs)* (stringify!($key), $crate::kv::Value::from_debug(&$value)),]
+                  target: $target, $lvl, $($arg)+)
+    );
+    (@munch $sink:tt [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     $key:ident = $value:expr, $($arg:tt)+) => (
+        __log_kv!(@munch $sink
+                  [$($kvs)* (stringify!($key), $crate::kv::Value::from_display(&$value)),]
+                  target: $target, $lvl, $($arg)+)
+    );
+    (@munch (global) [$($kvs:tt)*] target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) &&
+                lvl <= $crate::max_log_level() {
+            $crate::__log(lvl, $target, &_LOC, format_args!($($arg)+), &[$($kvs)*])
+        }
+    });
+    (@munch (callsite) [$($kvs:tt)*] target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _CALLSITE: $crate::Callsite = $crate::Callsite {
+            __loc: $crate::LogLocation {
+                __line: line!(),
+                __file: file!(),
+                __module_path: module_path!(),
+            },
+            __state: $crate::__CALLSITE_STATE_INIT,
+        };
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) && _CALLSITE.__interested(lvl) {
+            _CALLSITE.__log(lvl, format_args!($($arg)+), &[$($kvs)*])
+        }
+    });
+    (@munch (sampled $sample:expr) [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     $($arg:tt)+) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) &&
+                lvl <= $crate::max_log_level() && $crate::__enabled(lvl, $target) {
+            if let Some(rate) = $sample {
+                $crate::__log(lvl, $target, &_LOC, format_args!($($arg)+),
+                              &[("sample_rate", $crate::kv::Value::F64(rate)), $($kvs)*])
+            }
+        }
+    });
+    (@munch (rate_limited $interval:expr) [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     $($arg:tt)+) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        static _LIMIT: $crate::RateLimit = $crate::__RATE_LIMIT_INIT;
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) &&
+                lvl <= $crate::max_log_level() && $crate::__enabled(lvl, $target) {
+            match _LIMIT.__check($interval) {
+                Some(0) => {
+                    $crate::__log(lvl, $target, &_LOC, format_args!($($arg)+), &[$($kvs)*])
+                }
+                Some(suppressed) => {
+                    $crate::__log(lvl, $target, &_LOC, format_args!($($arg)+),
+                                  &[$($kvs)* ("suppressed", $crate::kv::Value::from(suppressed))])
+                }
+                None => {}
+            }
+        }
+    });
+    (@munch (when $cond:expr) [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     $($arg:tt)+) => ({
         static _LOC: $crate::LogLocation = $crate::LogLocation {
             __line: line!(),
             __file: file!(),
             __module_path: module_path!(),
         };
         let lvl = $lvl;
-        if lvl <= $crate::__static_max_level() && lvl <= $crate::max_log_level() {
-            $crate::__log(lvl, $target, &_LOC, format_args!($($arg)+))
+        if lvl <= $crate::__static_max_level_for(module_path!()) &&
+                lvl <= $crate::max_log_level() && $crate::__enabled(lvl, $target) && $cond {
+            $crate::__log(lvl, $target, &_LOC, format_args!($($arg)+), &[$($kvs)*])
+        }
+    });
+    (@munch (audit) [$($kvs:tt)*] target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        $crate::__log_audit($lvl, $target, &_LOC, format_args!($($arg)+), &[$($kvs)*])
+    });
+    (@munch (try) [$($kvs:tt)*] target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) &&
+                lvl <= $crate::max_log_level() {
+            $crate::__try_log(lvl, $target, &_LOC, format_args!($($arg)+), &[$($kvs)*])
+        } else {
+            Ok(())
+        }
+    });
+    (@munch (to $logger:expr) [$($kvs:tt)*] target: $target:expr, $lvl:expr,
+     $($arg:tt)+) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) {
+            $crate::__log_to($logger, lvl, $target, &_LOC, format_args!($($arg)+),
+                             &[$($kvs)*])
         }
     });
-    ($lvl:expr, $($arg:tt)+) => (log!(target: module_path!(), $lvl, $($arg)+))
 }
 
-/// Logs a message at the error level.
+/// Logs a message at the critical level.
 ///
 /// Logging at this level is disabled if the `max_level_off` feature is present.
 #[macro_export]
+macro_rules! critical {
+    (target: $target:expr, $($arg:tt)*) => (
+        log!(target: $target, $crate::LogLevel::Critical, $($arg)*);
+    );
+    ($($arg:tt)*) => (
+        log!($crate::LogLevel::Critical, $($arg)*);
+    )
+}
+
+/// Expands to a `&'static LogLocation` for the place where it is used.
+///
+/// This is used to pass the caller's location to the methods of `ResultExt`
+/// and `OptionExt`.
+#[macro_export]
+macro_rules! log_location {
+    () => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        &_LOC
+    })
+}
+
+/// Logs a message at the critical level, flushes the logger and aborts the
+/// process.
+///
+/// This takes the same arguments as `critical!`. The logger is flushed before
+/// aborting so that buffered records, including this one, are not lost. The
+/// process is aborted even if the message is filtered out, and the macro
+/// never returns, so it can be used wherever an expression of any type is
+/// expected.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # #[macro_use]
+/// # extern crate log;
+/// # fn main() {
+/// # let journal: Result<(), &str> = Err("bad checksum");
+/// if let Err(err) = journal {
+///     fatal!(target: "storage", "journal is corrupt: {}", err);
+/// }
+/// # }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! fatal {
+    (target: $target:expr, $($arg:tt)*) => ({
+        critical!(target: $target, $($arg)*);
+        $crate::__flush_and_abort()
+    });
+    ($($arg:tt)*) => ({
+        critical!($($arg)*);
+        $crate::__flush_and_abort()
+    })
+}
+
+/// Logs a message at the error level.
+///
+/// Logging at this level is disabled if any of the following features are
+/// present: `max_level_off` or `max_level_critical`.
+///
+/// When building in release mode (i.e., without the `debug_assertions` option),
+/// logging at this level is also disabled if any of the following features are
+/// present: `release_max_level_off` or `release_max_level_critical`.
+#[macro_export]
 macro_rules! error {
     (target: $target:expr, $($arg:tt)*) => (
         log!(target: $target, $crate::LogLevel::Error, $($arg)*);
@@ -46,11 +311,12 @@ macro_rules! error {
 /// Logs a message at the warn level.
 ///
 /// Logging at this level is disabled if any of the following features are
-/// present: `max_level_off` or `max_level_error`.
+/// present: `max_level_off`, `max_level_critical`, or `max_level_error`.
 ///
 /// When building in release mode (i.e., without the `debug_assertions` option),
 /// logging at this level is also disabled if any of the following features are
-/// present: `release_max_level_off` or `max_level_error`.
+/// present: `release_max_level_off`, `release_max_level_critical`, or
+/// `release_max_level_error`.
 #[macro_export]
 macro_rules! warn {
     (target: $target:expr, $($arg:tt)*) => (
@@ -61,15 +327,37 @@ macro_rules! warn {
     )
 }
 
+/// Logs a message at the notice level.
+///
+/// Logging at this level is disabled if any of the following features are
+/// present: `max_level_off`, `max_level_critical`, `max_level_error`, or
+/// `max_level_warn`.
+///
+/// When building in release mode (i.e., without the `debug_assertions` option),
+/// logging at this level is also disabled if any of the following features are
+/// present: `release_max_level_off`, `release_max_level_critical`,
+/// `release_max_level_error`, or `release_max_level_warn`.
+#[macro_export]
+macro_rules! notice {
+    (target: $target:expr, $($arg:tt)*) => (
+        log!(target: $target, $crate::LogLevel::Notice, $($arg)*);
+    );
+    ($($arg:tt)*) => (
+        log!($crate::LogLevel::Notice, $($arg)*);
+    )
+}
+
 /// Logs a message at the info level.
 ///
 /// Logging at this level is disabled if any of the following features are
-/// present: `max_level_off`, `max_level_error`, or `max_level_warn`.
+/// present: `max_level_off`, `max_level_critical`, `max_level_error`,
+/// `max_level_warn`, or `max_level_notice`.
 ///
 /// When building in release mode (i.e., without the `debug_assertions` option),
 /// logging at this level is also disabled if any of the following features are
-/// present: `release_max_level_off`, `release_max_level_error`, or
-/// `release_max_level_warn`.
+/// present: `release_max_level_off`, `release_max_level_critical`,
+/// `release_max_level_error`, `release_max_level_warn`, or
+/// `release_max_level_notice`.
 #[macro_export]
 macro_rules! info {
     (target: $target:expr, $($arg:tt)*) => (
@@ -83,13 +371,14 @@ macro_rules! info {
 /// Logs a message at the debug level.
 ///
 /// Logging at this level is disabled if any of the following features are
-/// present: `max_level_off`, `max_level_error`, `max_level_warn`, or
-/// `max_level_info`.
+/// present: `max_level_off`, `max_level_critical`, `max_level_error`,
+/// `max_level_warn`, `max_level_notice`, or `max_level_info`.
 ///
 /// When building in release mode (i.e., without the `debug_assertions` option),
 /// logging at this level is also disabled if any of the following features are
-/// present: `release_max_level_off`, `release_max_level_error`,
-/// `release_max_level_warn`, or `release_max_level_info`.
+/// present: `release_max_level_off`, `release_max_level_critical`,
+/// `release_max_level_error`, `release_max_level_warn`,
+/// `release_max_level_notice`, or `release_max_level_info`.
 #[macro_export]
 macro_rules! debug {
     (target: $target:expr, $($arg:tt)*) => (
@@ -100,16 +389,58 @@ macro_rules! debug {
     )
 }
 
+/// Logs an expression and its value at the debug level, and returns the value.
+///
+/// The message is the source text of the expression followed by the value's
+/// `Debug` output. The expression is evaluated exactly once, whether or not
+/// the message is logged, and its value is moved out again, so the macro can
+/// wrap any expression in place.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// # fn main() {
+/// let (width, height) = (3, 4);
+/// // logs "width * height = 12"
+/// let area = log_dbg!(width * height);
+/// let perimeter = 2 * log_dbg!(target: "geometry", width + height);
+/// # let _ = (area, perimeter);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! log_dbg {
+    (target: $target:expr, $val:expr) => (
+        match $val {
+            tmp => {
+                debug!(target: $target, "{} = {:?}", stringify!($val), tmp);
+                tmp
+            }
+        }
+    );
+    ($val:expr) => (
+        match $val {
+            tmp => {
+                debug!("{} = {:?}", stringify!($val), tmp);
+                tmp
+            }
+        }
+    )
+}
+
 /// Logs a message at the trace level.
 ///
 /// Logging at this level is disabled if any of the following features are
-/// present: `max_level_off`, `max_level_error`, `max_level_warn`,
-/// `max_level_info`, or `max_level_debug`.
+/// present: `max_level_off`, `max_level_critical`, `max_level_error`,
+/// `max_level_warn`, `max_level_notice`, `max_level_info`, or
+/// `max_level_debug`.
 ///
 /// When building in release mode (i.e., without the `debug_assertions` option),
 /// logging at this level is also disabled if any of the following features are
-/// present: `release_max_level_off`, `release_max_level_error`,
-/// `release_max_level_warn`, `release_max_level_info`, or
+/// present: `release_max_level_off`, `release_max_level_critical`,
+/// `release_max_level_error`, `release_max_level_warn`,
+/// `release_max_level_notice`, `release_max_level_info`, or
 /// `release_max_level_debug`.
 #[macro_export]
 macro_rules! trace {
@@ -121,35 +452,1053 @@ macro_rules! trace {
     )
 }
 
-/// Determines if a message logged at the specified level in that module will
-/// be logged.
+/// Logs a message at the trace2 level.
 ///
-/// This can be used to avoid expensive computation of log message arguments if
-/// the message would be ignored anyway.
+/// This is for output too verbose for `trace!`, so that it can be enabled
+/// separately.
+///
+/// Logging at this level is disabled if any of the following features are
+/// present: `max_level_off`, `max_level_critical`, `max_level_error`,
+/// `max_level_warn`, `max_level_notice`, `max_level_info`, `max_level_debug`,
+/// or `max_level_trace`.
+///
+/// When building in release mode (i.e., without the `debug_assertions` option),
+/// logging at this level is also disabled if any of the following features are
+/// present: `release_max_level_off`, `release_max_level_critical`,
+/// `release_max_level_error`, `release_max_level_warn`,
+/// `release_max_level_notice`, `release_max_level_info`,
+/// `release_max_level_debug`, or `release_max_level_trace`.
+#[macro_export]
+macro_rules! trace2 {
+    (target: $target:expr, $($arg:tt)*) => (
+        log!(target: $target, $crate::LogLevel::Trace2, $($arg)*);
+    );
+    ($($arg:tt)*) => (
+        log!($crate::LogLevel::Trace2, $($arg)*);
+    )
+}
+
+/// Logs a message at the trace3 level.
+///
+/// This is for output too verbose for `trace2!`, so that it can be enabled
+/// separately.
+///
+/// Logging at this level is disabled if any of the following features are
+/// present: `max_level_off`, `max_level_critical`, `max_level_error`,
+/// `max_level_warn`, `max_level_notice`, `max_level_info`, `max_level_debug`,
+/// `max_level_trace`, or `max_level_trace2`.
+///
+/// When building in release mode (i.e., without the `debug_assertions` option),
+/// logging at this level is also disabled if any of the following features are
+/// present: `release_max_level_off`, `release_max_level_critical`,
+/// `release_max_level_error`, `release_max_level_warn`,
+/// `release_max_level_notice`, `release_max_level_info`,
+/// `release_max_level_debug`, `release_max_level_trace`, or
+/// `release_max_level_trace2`.
+#[macro_export]
+macro_rules! trace3 {
+    (target: $target:expr, $($arg:tt)*) => (
+        log!(target: $target, $crate::LogLevel::Trace3, $($arg)*);
+    );
+    ($($arg:tt)*) => (
+        log!($crate::LogLevel::Trace3, $($arg)*);
+    )
+}
+
+/// Logs an audit message.
+///
+/// Audit messages record security-relevant events, such as logins and
+/// privilege changes, which must not be silenced by verbosity settings. They
+/// are logged at the notice level, but are not subject to the maximum log
+/// level, per-target levels, the `max_level_*` features or
+/// `LOG_STATIC_MAX_LEVEL`. Loggers can tell them apart with
+/// `LogMetadata::is_audit`.
+///
+/// This takes the same arguments as `info!`, including key-value pairs.
 ///
 /// # Examples
 ///
 /// ```rust
 /// # #[macro_use]
 /// # extern crate log;
-/// use log::LogLevel::Debug;
-///
-/// # fn foo() {
-/// if log_enabled!(Debug) {
-///     let data = expensive_call();
-///     debug!("expensive debug data: {} {}", data.x, data.y);
-/// }
+/// # fn main() {
+/// let user = "alice";
+/// audit!(target: "auth", user = user, "login succeeded");
 /// # }
-/// # struct Data { x: u32, y: u32 }
-/// # fn expensive_call() -> Data { Data { x: 0, y: 0 } }
-/// # fn main() {}
 /// ```
 #[macro_export]
-macro_rules! log_enabled {
-    (target: $target:expr, $lvl:expr) => ({
-        let lvl = $lvl;
-        lvl <= $crate::__static_max_level() && lvl <= $crate::max_log_level() &&
-            $crate::__enabled(lvl, $target)
-    });
-    ($lvl:expr) => (log_enabled!(target: module_path!(), $lvl))
+macro_rules! audit {
+    (target: $target:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (audit) [] target: $target, $crate::LogLevel::Notice, $($arg)+)
+    );
+    ($($arg:tt)+) => (
+        __log_kv!(@munch (audit) [] target: module_path!(), $crate::LogLevel::Notice, $($arg)+)
+    )
+}
+
+/// Logs a message at the critical level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! critical_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Critical, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Critical, $($arg)*);
+    )
+}
+
+/// Logs a message at the error level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! error_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Error, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Error, $($arg)*);
+    )
+}
+
+/// Logs a message at the warn level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! warn_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Warn, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Warn, $($arg)*);
+    )
+}
+
+/// Logs a message at the notice level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! notice_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Notice, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Notice, $($arg)*);
+    )
+}
+
+/// Logs a message at the info level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! info_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Info, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Info, $($arg)*);
+    )
+}
+
+/// Logs a message at the debug level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! debug_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Debug, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Debug, $($arg)*);
+    )
+}
+
+/// Logs a message at the trace level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! trace_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Trace, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Trace, $($arg)*);
+    )
+}
+
+/// Logs a message at the trace2 level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! trace2_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Trace2, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Trace2, $($arg)*);
+    )
+}
+
+/// Logs a message at the trace3 level to a specific logger.
+///
+/// See `log_to!`.
+#[macro_export]
+macro_rules! trace3_to {
+    ($logger:expr, target: $target:expr, $($arg:tt)*) => (
+        log_to!($logger, target: $target, $crate::LogLevel::Trace3, $($arg)*);
+    );
+    ($logger:expr, $($arg:tt)*) => (
+        log_to!($logger, $crate::LogLevel::Trace3, $($arg)*);
+    )
+}
+
+/// Logs a message, returning an error if the logger did not accept it.
+///
+/// This takes the same arguments as `log!`, and evaluates to a
+/// `Result<(), LogError>`. The record is passed to the logger's `try_log`
+/// method, so a logger which can lose records, for example because its queue
+/// is full, can report that to the caller. A record disabled by a level is
+/// not an error. The `try_critical!`, `try_error!`, `try_warn!`,
+/// `try_notice!`, `try_info!`, `try_debug!`, `try_trace!`, `try_trace2!` and
+/// `try_trace3!` macros work the same way.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel;
+///
+/// # fn main() {
+/// let user = "admin";
+/// if let Err(err) = try_log!(LogLevel::Warn, user = user, "permissions changed") {
+///     panic!("audit record lost: {}", err);
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! try_log {
+    (target: $target:expr, $lvl:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (try) [] target: $target, $lvl, $($arg)+)
+    );
+    ($lvl:expr, $($arg:tt)+) => (try_log!(target: module_path!(), $lvl, $($arg)+))
+}
+
+/// Logs a message at the critical level, returning an error if the logger
+/// did not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_critical {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Critical, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Critical, $($arg)*)
+    )
+}
+
+/// Logs a message at the error level, returning an error if the logger did
+/// not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_error {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Error, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Error, $($arg)*)
+    )
+}
+
+/// Logs a message at the warn level, returning an error if the logger did not
+/// accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_warn {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Warn, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Warn, $($arg)*)
+    )
+}
+
+/// Logs a message at the notice level, returning an error if the logger did
+/// not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_notice {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Notice, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Notice, $($arg)*)
+    )
+}
+
+/// Logs a message at the info level, returning an error if the logger did not
+/// accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_info {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Info, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Info, $($arg)*)
+    )
+}
+
+/// Logs a message at the debug level, returning an error if the logger did
+/// not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_debug {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Debug, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Debug, $($arg)*)
+    )
+}
+
+/// Logs a message at the trace level, returning an error if the logger did
+/// not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_trace {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Trace, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Trace, $($arg)*)
+    )
+}
+
+/// Logs a message at the trace2 level, returning an error if the logger did
+/// not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_trace2 {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Trace2, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Trace2, $($arg)*)
+    )
+}
+
+/// Logs a message at the trace3 level, returning an error if the logger did
+/// not accept it.
+///
+/// See `try_log!`.
+#[macro_export]
+macro_rules! try_trace3 {
+    (target: $target:expr, $($arg:tt)*) => (
+        try_log!(target: $target, $crate::LogLevel::Trace3, $($arg)*)
+    );
+    ($($arg:tt)*) => (
+        try_log!($crate::LogLevel::Trace3, $($arg)*)
+    )
+}
+
+/// Determines if a message logged at the specified level in that module will
+/// be logged.
+///
+/// This can be used to avoid expensive computation of log message arguments if
+/// the message would be ignored anyway.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel::Debug;
+///
+/// # fn foo() {
+/// if log_enabled!(Debug) {
+///     let data = expensive_call();
+///     debug!("expensive debug data: {} {}", data.x, data.y);
+/// }
+/// # }
+/// # struct Data { x: u32, y: u32 }
+/// # fn expensive_call() -> Data { Data { x: 0, y: 0 } }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! log_enabled {
+    (target: $target:expr, $lvl:expr) => ({
+        let lvl = $lvl;
+        lvl <= $crate::__static_max_level_for(module_path!()) &&
+            lvl <= $crate::max_log_level() && $crate::__enabled(lvl, $target)
+    });
+    ($lvl:expr) => (log_enabled!(target: module_path!(), $lvl))
+}
+
+/// Logs a hex dump of a byte slice.
+///
+/// This takes a level and a `&[u8]`, optionally preceded by a target like
+/// `log!`. Each line of the dump shows the offset of its first byte, 16 bytes
+/// in hex, and those bytes as ASCII, with unprintable ones shown as `.`.
+/// Large slices are split across several records of at most 256 bytes each,
+/// and every record carries the length of the whole slice under the `len`
+/// key. Nothing is logged for an empty slice.
+///
+/// The dump is only formatted if a message at this level and target would be
+/// logged, as checked by `log_enabled!`.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel;
+///
+/// # fn main() {
+/// let buf = [0u8; 512];
+/// let n = 42;
+/// log_hex!(target: "wire", LogLevel::Debug, &buf[..n]);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! log_hex {
+    (target: $target:expr, $lvl:expr, $bytes:expr) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        let lvl = $lvl;
+        if log_enabled!(target: $target, lvl) {
+            $crate::__log_hex(lvl, $target, &_LOC, $bytes)
+        }
+    });
+    ($lvl:expr, $bytes:expr) => (log_hex!(target: module_path!(), $lvl, $bytes))
+}
+
+/// Logs a hex dump of a byte slice at the trace level.
+///
+/// See `log_hex!`.
+#[macro_export]
+macro_rules! trace_hex {
+    (target: $target:expr, $bytes:expr) => (
+        log_hex!(target: $target, $crate::LogLevel::Trace, $bytes)
+    );
+    ($bytes:expr) => (log_hex!($crate::LogLevel::Trace, $bytes))
+}
+
+/// Enters a span, returning a guard which exits it when dropped.
+///
+/// A record with the message `enter <name>` is logged at the given level when
+/// the span is entered, and one with the message `exit <name>` when the guard
+/// is dropped. Every record logged on the current thread in between, including
+/// those two, carries the span's id. Spans may be nested.
+///
+/// The name must be a `&'static str`. Like `log!`, a target may be given,
+/// which defaults to the module path.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// use log::LogLevel;
+///
+/// fn query(sql: &str) {
+///     let _span = span!(LogLevel::Debug, "db_query");
+///     // logged with the id of the db_query span
+///     trace!("running {}", sql);
+/// }
+///
+/// fn main() {
+///     query("SELECT 1");
+/// }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! span {
+    (target: $target:expr, $lvl:expr, $name:expr) => ({
+        static _LOC: $crate::LogLocation = $crate::LogLocation {
+            __line: line!(),
+            __file: file!(),
+            __module_path: module_path!(),
+        };
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) &&
+                lvl <= $crate::max_log_level() {
+            $crate::Span::__enter(lvl, $target, $name, &_LOC)
+        } else {
+            $crate::Span::__disabled()
+        }
+    });
+    ($lvl:expr, $name:expr) => (span!(target: module_path!(), $lvl, $name))
+}
+
+/// Logs a sample of messages.
+///
+/// This takes a sampling rate between `0.0` and `1.0`, or `sampler:` followed
+/// by a `&Sampler`, and then the same arguments as `log!`. Only that fraction
+/// of the messages which would otherwise be logged are, spread evenly, and
+/// each carries the rate in a `sample_rate` key-value pair so that sinks can
+/// scale counts back up. Each statement given a rate counts its messages
+/// separately, while statements given the same `Sampler` share a count, and
+/// follow changes to its rate.
+///
+/// The `critical_sampled!`, `error_sampled!`, `warn_sampled!`,
+/// `notice_sampled!`, `info_sampled!`, `debug_sampled!`, `trace_sampled!`,
+/// `trace2_sampled!` and `trace3_sampled!` macros work the same way.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::Sampler;
+///
+/// # fn main() {
+/// // logs one packet in a hundred
+/// for seq in 0..1000 {
+///     trace_sampled!(0.01, "received packet {}", seq);
+/// }
+///
+/// let sampler = Sampler::new(0.1);
+/// debug_sampled!(sampler: &sampler, target: "cache", "cache miss");
+/// # }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! log_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (sampled $crate::Sampler::__sample($sampler)) []
+                  target: $target, $lvl, $($arg)+)
+    );
+    (sampler: $sampler:expr, $lvl:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: module_path!(), $lvl, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _SAMPLER: $crate::Sampler = $crate::__SAMPLER_INIT;
+        __log_kv!(@munch (sampled _SAMPLER.__sample_at($rate)) []
+                  target: $target, $lvl, $($arg)+)
+    });
+    ($rate:expr, $lvl:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the critical level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! critical_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Critical, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Critical, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Critical, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Critical, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the error level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! error_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Error, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Error, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Error, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Error, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the warn level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! warn_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Warn, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Warn, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Warn, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Warn, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the notice level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! notice_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Notice, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Notice, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Notice, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Notice, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the info level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! info_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Info, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Info, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Info, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Info, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the debug level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! debug_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Debug, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Debug, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Debug, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Debug, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the trace level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! trace_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Trace, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Trace, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Trace, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Trace, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the trace2 level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! trace2_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Trace2, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Trace2, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Trace2, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Trace2, $($arg)+)
+    )
+}
+
+/// Logs a sample of messages at the trace3 level.
+///
+/// See `log_sampled!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! trace3_sampled {
+    (sampler: $sampler:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, target: $target, $crate::LogLevel::Trace3, $($arg)+)
+    );
+    (sampler: $sampler:expr, $($arg:tt)+) => (
+        log_sampled!(sampler: $sampler, $crate::LogLevel::Trace3, $($arg)+)
+    );
+    ($rate:expr, target: $target:expr, $($arg:tt)+) => (
+        log_sampled!($rate, target: $target, $crate::LogLevel::Trace3, $($arg)+)
+    );
+    ($rate:expr, $($arg:tt)+) => (
+        log_sampled!($rate, $crate::LogLevel::Trace3, $($arg)+)
+    )
+}
+
+/// Logs a message at most once per interval.
+///
+/// This takes a `Duration` followed by the same arguments as `log!`. Once the
+/// statement has logged a message, any more it would log within the interval
+/// are dropped. The next message logged after the interval carries the number
+/// dropped in a `suppressed` key-value pair, if there were any, and starts a
+/// new interval. The count is only reported with that message, so messages
+/// dropped by a statement which never logs again are never reported. Each
+/// statement is limited separately.
+///
+/// The `critical_rate_limited!`, `error_rate_limited!`, `warn_rate_limited!`,
+/// `notice_rate_limited!`, `info_rate_limited!`, `debug_rate_limited!`,
+/// `trace_rate_limited!`, `trace2_rate_limited!` and `trace3_rate_limited!`
+/// macros work the same way.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use std::time::Duration;
+///
+/// # fn main() {
+/// # let attempts = 0;
+/// // logged at most once every five seconds, however often the
+/// // connection fails
+/// warn_rate_limited!(Duration::from_secs(5), "upstream unavailable, retry {}", attempts);
+/// # }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! log_rate_limited {
+    ($interval:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => (
+        __log_kv!(@munch (rate_limited $interval) [] target: $target, $lvl, $($arg)+)
+    );
+    ($interval:expr, $lvl:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+/// Logs a message at the critical level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! critical_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Critical, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Critical, $($arg)+)
+    )
+}
+
+/// Logs a message at the error level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! error_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Error, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Error, $($arg)+)
+    )
+}
+
+/// Logs a message at the warn level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! warn_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Warn, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Warn, $($arg)+)
+    )
+}
+
+/// Logs a message at the notice level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! notice_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Notice, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Notice, $($arg)+)
+    )
+}
+
+/// Logs a message at the info level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! info_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Info, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Info, $($arg)+)
+    )
+}
+
+/// Logs a message at the debug level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! debug_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Debug, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Debug, $($arg)+)
+    )
+}
+
+/// Logs a message at the trace level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! trace_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Trace, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Trace, $($arg)+)
+    )
+}
+
+/// Logs a message at the trace2 level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! trace2_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Trace2, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Trace2, $($arg)+)
+    )
+}
+
+/// Logs a message at the trace3 level at most once per interval.
+///
+/// See `log_rate_limited!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! trace3_rate_limited {
+    ($interval:expr, target: $target:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, target: $target, $crate::LogLevel::Trace3, $($arg)+)
+    );
+    ($interval:expr, $($arg:tt)+) => (
+        log_rate_limited!($interval, $crate::LogLevel::Trace3, $($arg)+)
+    )
+}
+
+/// Logs every nth message.
+///
+/// This takes a count `n` followed by the same arguments as `log!`. The
+/// statement logs the first time it runs with its level enabled, and then
+/// every nth time after that, keeping a count of its own. An `n` of `0` is
+/// treated as `1`.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel;
+///
+/// # fn main() {
+/// for n in 0..10000 {
+///     // logs items 0, 1000, 2000, ...
+///     log_every_n!(1000, LogLevel::Info, "processed {} items", n);
+/// }
+/// # }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! log_every_n {
+    ($n:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _COUNTER: $crate::Counter = $crate::__COUNTER_INIT;
+        __log_kv!(@munch (when _COUNTER.__every($n)) [] target: $target, $lvl, $($arg)+)
+    });
+    ($n:expr, $lvl:expr, $($arg:tt)+) => (
+        log_every_n!($n, target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+/// Logs only the first n messages.
+///
+/// This takes a count `n` followed by the same arguments as `log!`. The
+/// statement logs the first `n` times it runs with its level enabled, and
+/// never again, which suits warnings that would otherwise be repeated on
+/// every request.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel;
+///
+/// # fn main() {
+/// for attempt in 0..10 {
+///     log_first_n!(3, LogLevel::Warn, "no config file, using defaults ({})", attempt);
+/// }
+/// # }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! log_first_n {
+    ($n:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        static _COUNTER: $crate::Counter = $crate::__COUNTER_INIT;
+        __log_kv!(@munch (when _COUNTER.__first($n)) [] target: $target, $lvl, $($arg)+)
+    });
+    ($n:expr, $lvl:expr, $($arg:tt)+) => (
+        log_first_n!($n, target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+/// Logs only the first message.
+///
+/// This takes the same arguments as `log!`, and is the same as `log_first_n!`
+/// with a count of `1`.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::LogLevel;
+///
+/// # fn main() {
+/// log_once!(LogLevel::Warn, "`--legacy` is deprecated, use `--compat`");
+/// # }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! log_once {
+    (target: $target:expr, $lvl:expr, $($arg:tt)+) => (
+        log_first_n!(1, target: $target, $lvl, $($arg)+)
+    );
+    ($lvl:expr, $($arg:tt)+) => (
+        log_first_n!(1, target: module_path!(), $lvl, $($arg)+)
+    )
+}
+
+/// Logs a message from a signal handler.
+///
+/// This takes the same arguments as `log!`, except for key-value pairs. The
+/// record bypasses the logger and is written straight to a file descriptor
+/// without allocating or taking locks. See the `signal_safe` module for
+/// details.
+///
+/// This is only available on Unix.
+#[cfg(all(unix, not(feature = "freestanding")))]
+#[macro_export]
+macro_rules! log_signal_safe {
+    (target: $target:expr, $lvl:expr, $($arg:tt)+) => ({
+        let lvl = $lvl;
+        if lvl <= $crate::__static_max_level_for(module_path!()) {
+            $crate::signal_safe::log(lvl, $target, format_args!($($arg)+))
+        }
+    });
+    ($lvl:expr, $($arg:tt)+) => (log_signal_safe!(target: module_path!(), $lvl, $($arg)+))
+}
+
+/// Asserts that a record was captured by the `log::test` capture logger on
+/// the current thread.
+///
+/// The record must have the given level, and its message must contain the
+/// given string. A target may also be given, which must match exactly. If no
+/// captured record matches, this panics with a list of the records which were
+/// captured. The records are left in the buffer either way.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// fn main() {
+///     log::test::init().unwrap();
+///
+///     warn!(target: "storage", "disk full: {} bytes left", 0);
+///
+///     assert_logged!(Warn, "disk full");
+///     assert_logged!(target: "storage", Warn, "0 bytes left");
+///     assert_not_logged!(Error, "disk full");
+/// }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! assert_logged {
+    (target: $target:expr, $lvl:ident, $msg:expr) => (
+        $crate::test::__assert_logged(true, $crate::LogLevel::$lvl, Some($target), $msg)
+    );
+    ($lvl:ident, $msg:expr) => (
+        $crate::test::__assert_logged(true, $crate::LogLevel::$lvl, None, $msg)
+    );
+}
+
+/// Asserts that no matching record was captured by the `log::test` capture
+/// logger on the current thread.
+///
+/// This takes the same arguments as `assert_logged!`.
+#[cfg(not(feature = "freestanding"))]
+#[macro_export]
+macro_rules! assert_not_logged {
+    (target: $target:expr, $lvl:ident, $msg:expr) => (
+        $crate::test::__assert_logged(false, $crate::LogLevel::$lvl, Some($target), $msg)
+    );
+    ($lvl:ident, $msg:expr) => (
+        $crate::test::__assert_logged(false, $crate::LogLevel::$lvl, None, $msg)
+    );
 }
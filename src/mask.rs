@@ -0,0 +1,167 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::fmt;
+use core::ops::BitOr;
+
+use {LogLevel, LogLevelFilter};
+
+/// A set of log levels.
+///
+/// A `LogLevelFilter` enables every level up to a maximum, while a
+/// `LevelMask` can enable any combination of levels, such as warnings and
+/// trace messages but nothing in between. Masks are combined with `|`, and a
+/// level or level filter converts to the mask of the levels it enables.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{LevelMask, LogLevel, LogLevelFilter};
+///
+/// let mask = LevelMask::from(LogLevelFilter::Warn) | LogLevel::Trace.into();
+/// assert!(mask.contains(LogLevel::Error));
+/// assert!(!mask.contains(LogLevel::Info));
+/// assert!(mask.contains(LogLevel::Trace));
+/// assert_eq!(LogLevelFilter::Trace, mask.max_level());
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct LevelMask {
+    // bit n is set if the level whose number is n is in the mask
+    bits: u16,
+}
+
+impl LevelMask {
+    /// Returns a mask containing no levels.
+    #[inline]
+    pub fn empty() -> LevelMask {
+        LevelMask { bits: 0 }
+    }
+
+    /// Returns a mask containing every level.
+    #[inline]
+    pub fn all() -> LevelMask {
+        LevelMask::from(LogLevelFilter::max())
+    }
+
+    /// Returns true if the mask contains `level`.
+    #[inline]
+    pub fn contains(&self, level: LogLevel) -> bool {
+        self.bits & bit(level) != 0
+    }
+
+    /// Returns true if the mask contains no levels.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Adds `level` to the mask.
+    #[inline]
+    pub fn insert(&mut self, level: LogLevel) {
+        self.bits |= bit(level);
+    }
+
+    /// Removes `level` from the mask.
+    #[inline]
+    pub fn remove(&mut self, level: LogLevel) {
+        self.bits &= !bit(level);
+    }
+
+    /// Returns the most verbose level in the mask, or `LogLevelFilter::Off`
+    /// if it is empty.
+    ///
+    /// This is the lowest maximum log level which lets every level in the mask
+    /// through.
+    pub fn max_level(&self) -> LogLevelFilter {
+        let highest = 16 - self.bits.leading_zeros() as usize;
+        LogLevelFilter::from_usize(highest.saturating_sub(1)).unwrap_or(LogLevelFilter::Off)
+    }
+}
+
+#[inline]
+fn bit(level: LogLevel) -> u16 {
+    1 << level as usize
+}
+
+impl From<LogLevel> for LevelMask {
+    #[inline]
+    fn from(level: LogLevel) -> LevelMask {
+        LevelMask { bits: bit(level) }
+    }
+}
+
+/// Gives the mask of every level up to and including the filter.
+impl From<LogLevelFilter> for LevelMask {
+    #[inline]
+    fn from(filter: LogLevelFilter) -> LevelMask {
+        LevelMask { bits: (1 << (filter as usize + 1)) - 2 }
+    }
+}
+
+impl BitOr for LevelMask {
+    type Output = LevelMask;
+
+    #[inline]
+    fn bitor(self, other: LevelMask) -> LevelMask {
+        LevelMask { bits: self.bits | other.bits }
+    }
+}
+
+/// Lists the levels in the mask, as in `{Error, Trace}`.
+impl fmt::Debug for LevelMask {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut set = fmt.debug_set();
+        for level in LogLevel::iter().filter(|&level| self.contains(level)) {
+            set.entry(&level);
+        }
+        set.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {LogLevel, LogLevelFilter};
+    use super::LevelMask;
+
+    #[test]
+    fn test_level_mask() {
+        let mut mask = LevelMask::empty();
+        assert!(mask.is_empty());
+        assert_eq!(LogLevelFilter::Off, mask.max_level());
+
+        mask.insert(LogLevel::Warn);
+        mask.insert(LogLevel::Debug);
+        assert!(mask.contains(LogLevel::Warn));
+        assert!(!mask.contains(LogLevel::Error));
+        assert!(!mask.contains(LogLevel::Info));
+        assert_eq!(LogLevelFilter::Debug, mask.max_level());
+
+        mask.remove(LogLevel::Debug);
+        assert_eq!(LevelMask::from(LogLevel::Warn), mask);
+        assert_eq!(LogLevelFilter::Warn, mask.max_level());
+
+        let all = LevelMask::all();
+        assert!(LogLevel::iter().all(|level| all.contains(level)));
+        assert_eq!(LogLevelFilter::max(), all.max_level());
+        assert_eq!(LevelMask::empty(), LevelMask::from(LogLevelFilter::Off));
+
+        let info = LevelMask::from(LogLevelFilter::Info);
+        assert!(LogLevel::iter().all(|level| info.contains(level) == (level <= LogLevel::Info)));
+        let notice = LevelMask::from(LogLevelFilter::Warn) | LevelMask::from(LogLevel::Notice);
+        assert_eq!(info, notice | LevelMask::from(LogLevel::Info));
+    }
+
+    #[test]
+    #[cfg(not(feature = "freestanding"))]
+    fn test_level_mask_debug() {
+        let mask = LevelMask::from(LogLevel::Error) | LevelMask::from(LogLevel::Trace);
+        assert_eq!("{Error, Trace}", format!("{:?}", mask));
+    }
+}
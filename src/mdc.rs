@@ -0,0 +1,39 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The mapped diagnostic context.
+//!
+//! Each thread has a set of key-value pairs which is added to every record
+//! logged on that thread, after the pairs passed to the logging macro. A
+//! server can store a request id here once when it starts handling a request,
+//! and every record logged while handling it will carry the id. If a record
+//! already has a pair with the same key, the one passed to the macro wins.
+//!
+//! Values are rendered to strings when they are inserted.
+//!
+//! # Examples
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate log;
+//!
+//! fn handle(request_id: u64) {
+//!     log::mdc::insert("request_id", request_id);
+//!     // logged with request_id=17
+//!     info!("handling request");
+//!     log::mdc::remove("request_id");
+//! }
+//!
+//! fn main() {
+//!     handle(17);
+//! }
+//! ```
+
+pub use context::{insert, get, remove, clear};
@@ -0,0 +1,72 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::alloc::{self, Layout};
+use std::io::{self, Write};
+
+use {LogLevel, LogLocation, LogRecord};
+
+/// Installs an allocation error hook which logs the failed allocation as an
+/// `error!` record before the process aborts.
+///
+/// The record has the target `oom`, and is passed straight to the logger
+/// without going through the parts of the facade which allocate, so the
+/// thread's diagnostic context is not attached to it. Whether it actually
+/// makes it out depends on the logger not needing to allocate either. The
+/// logger is flushed, and the usual message is then written to standard
+/// error.
+///
+/// This replaces any previously installed allocation error hook. It is only
+/// available with the `oom_hook` feature, which requires a nightly compiler.
+pub fn install_oom_hook() {
+    alloc::set_alloc_error_hook(oom_hook);
+}
+
+fn oom_hook(layout: Layout) {
+    static LOC: LogLocation = LogLocation {
+        __line: line!(),
+        __file: file!(),
+        __module_path: module_path!(),
+    };
+
+    if LogLevel::Error <= ::max_log_level() {
+        if let Some(logger) = ::logger() {
+            logger.log(&LogRecord::builder()
+                                  .level(LogLevel::Error)
+                                  .target("oom")
+                                  .location(&LOC)
+                                  .args(format_args!("memory allocation of {} bytes failed",
+                                                     layout.size()))
+                                  .build());
+            logger.flush();
+        }
+    }
+
+    // stderr is unbuffered, so this doesn't allocate either
+    let _ = writeln!(io::stderr(), "memory allocation of {} bytes failed", layout.size());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Layout;
+
+    use LogLevel;
+    use super::oom_hook;
+
+    #[test]
+    fn test_oom_hook() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        oom_hook(Layout::from_size_align(1 << 40, 8).unwrap());
+        assert_logged!(target: "oom", Error, "memory allocation of 1099511627776 bytes failed");
+        assert_eq!(LogLevel::Error, ::test::drain()[0].level());
+    }
+}
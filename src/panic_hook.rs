@@ -0,0 +1,99 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::boxed::Box;
+use std::panic::{self, PanicHookInfo};
+use std::string::String;
+use std::sync::Once;
+use std::thread;
+
+static INSTALL: Once = Once::new();
+
+/// Installs a panic hook which logs panics as `error!` records.
+///
+/// Each panic is logged with the target `panic`, with the thread, payload and
+/// location in the message, and the location also in `file` and `line`
+/// key-value pairs. The logger is then flushed, and the panic is passed on to
+/// the previously installed hook, so the usual message is still printed.
+///
+/// Calling this more than once has no further effect.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate log;
+///
+/// fn main() {
+///     log::install_panic_hook();
+///     // any panic from here on is also logged
+/// }
+/// ```
+pub fn install_panic_hook() {
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            log_panic(info);
+            previous(info);
+        }));
+    });
+}
+
+fn log_panic(info: &PanicHookInfo) {
+    let payload = match info.payload().downcast_ref::<&'static str>() {
+        Some(payload) => *payload,
+        None => match info.payload().downcast_ref::<String>() {
+            Some(payload) => &**payload,
+            None => "Box<Any>",
+        },
+    };
+    let thread = thread::current();
+    let thread = thread.name().unwrap_or("<unnamed>");
+
+    match info.location() {
+        Some(location) => {
+            error!(target: "panic", file = location.file(), line = location.line(),
+                   "thread '{}' panicked at '{}', {}:{}",
+                   thread, payload, location.file(), location.line());
+        }
+        None => {
+            error!(target: "panic", "thread '{}' panicked at '{}'", thread, payload);
+        }
+    }
+    ::flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+    use std::string::ToString;
+
+    use LogLevel;
+    use super::install_panic_hook;
+
+    #[test]
+    fn test_panic_hook() {
+        ::test::init().unwrap();
+        ::test::clear();
+        install_panic_hook();
+        install_panic_hook();
+
+        let line = line!() + 1;
+        let _ = panic::catch_unwind(|| panic!("boom {}", 1));
+
+        let records = ::test::drain();
+        assert_eq!(1, records.len());
+        assert_eq!(LogLevel::Error, records[0].level());
+        assert_eq!("panic", records[0].target());
+        assert!(records[0].message().contains("panicked at 'boom 1', src/panic_hook.rs:"));
+        assert_eq!(&[("file".to_string(), file!().to_string()),
+                     ("line".to_string(), line.to_string())],
+                   &records[0].key_values()[..2]);
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::string::{String, ToString};
+
+use {ControlMessage, Log, LogError, LogMetadata, LogRecord};
+
+/// A logger which places the targets of its records under a prefix.
+///
+/// Each record passed to a `PrefixLogger` is forwarded to the wrapped logger
+/// with `prefix::` prepended to its target, or with the target replaced by
+/// `prefix` if it is empty. Giving each subsystem its own `PrefixLogger`, and
+/// logging to it with `log_to!`, puts the subsystem's records in a namespace
+/// of their own without every call site passing `target:`.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate log;
+/// use log::{NopLogger, PrefixLogger};
+///
+/// # fn main() {
+/// let db = PrefixLogger::new("myapp::db", NopLogger);
+/// // logged with the target "myapp::db::pool"
+/// info_to!(&db, target: "pool", "opened {} connections", 4);
+///
+/// let replica = db.child("replica");
+/// assert_eq!(replica.prefix(), "myapp::db::replica");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrefixLogger<L> {
+    prefix: String,
+    logger: L,
+}
+
+impl<L: Log> PrefixLogger<L> {
+    /// Creates a logger which forwards records to `logger` under `prefix`.
+    pub fn new(prefix: &str, logger: L) -> PrefixLogger<L> {
+        PrefixLogger {
+            prefix: prefix.to_string(),
+            logger,
+        }
+    }
+
+    /// Returns the prefix added to record targets.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns a reference to the wrapped logger.
+    pub fn get_ref(&self) -> &L {
+        &self.logger
+    }
+
+    /// Unwraps this `PrefixLogger`, returning the wrapped logger.
+    pub fn into_inner(self) -> L {
+        self.logger
+    }
+
+    /// Creates a logger for a nested namespace, with `name` appended to this
+    /// logger's prefix.
+    pub fn child(&self, name: &str) -> PrefixLogger<L>
+        where L: Clone
+    {
+        PrefixLogger {
+            prefix: self.target(name),
+            logger: self.logger.clone(),
+        }
+    }
+
+    fn target(&self, target: &str) -> String {
+        if target.is_empty() {
+            return self.prefix.clone();
+        }
+
+        let mut prefixed = String::with_capacity(self.prefix.len() + 2 + target.len());
+        prefixed.push_str(&self.prefix);
+        prefixed.push_str("::");
+        prefixed.push_str(target);
+        prefixed
+    }
+
+    // Calls f with a copy of record whose target is under the prefix.
+    fn with_prefix<F, T>(&self, record: &LogRecord, f: F) -> T
+        where F: FnOnce(&LogRecord) -> T
+    {
+        let target = self.target(record.target());
+        f(&LogRecord {
+            metadata: LogMetadata {
+                level: record.level(),
+                target: &target,
+                trace_id: record.metadata().trace_id(),
+                audit: record.is_audit(),
+            },
+            location: record.location,
+            args: record.args,
+            key_values: record.key_values,
+            scope: record.scope,
+            span_id: record.span_id,
+            timestamp: record.timestamp,
+            thread: record.thread,
+            pid: record.pid,
+            #[cfg(feature = "backtrace")]
+            backtrace: record.backtrace,
+        })
+    }
+}
+
+impl<L: Log> Log for PrefixLogger<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        let target = self.target(metadata.target());
+        self.logger.enabled(&LogMetadata {
+            level: metadata.level(),
+            target: &target,
+            trace_id: metadata.trace_id(),
+            audit: metadata.is_audit(),
+        })
+    }
+
+    fn log(&self, record: &LogRecord) {
+        self.with_prefix(record, |record| self.logger.log(record))
+    }
+
+    fn try_log(&self, record: &LogRecord) -> Result<(), LogError> {
+        self.with_prefix(record, |record| self.logger.try_log(record))
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+
+    fn reopen(&self) {
+        self.logger.reopen()
+    }
+
+    fn handle_control(&self, msg: ControlMessage) {
+        self.logger.handle_control(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::{String, ToString};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    use {Log, LogLevel, LogMetadata, LogRecord};
+    use super::PrefixLogger;
+
+    #[derive(Clone)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Collect {
+        fn enabled(&self, metadata: &LogMetadata) -> bool {
+            metadata.target().starts_with("app::net")
+        }
+
+        fn log(&self, record: &LogRecord) {
+            self.0.lock().unwrap().push(record.target().to_string());
+        }
+    }
+
+    #[test]
+    fn test_prefix() {
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let app = PrefixLogger::new("app", Collect(targets.clone()));
+        let net = app.child("net");
+        assert_eq!("app::net", net.prefix());
+
+        info_to!(&app, target: "db", "a");
+        info_to!(&app, target: "", "b");
+        info_to!(&net, target: "tcp", "c");
+        assert_eq!(vec!["app::db", "app", "app::net::tcp"], *targets.lock().unwrap());
+
+        let metadata = LogMetadata::builder().level(LogLevel::Info).target("db").build();
+        assert!(!app.enabled(&metadata));
+        assert!(net.enabled(&metadata));
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Rates are kept in parts per billion, so that they fit in an AtomicUsize.
+const SCALE: u64 = 1_000_000_000;
+
+/// Decides which of a stream of records to keep.
+///
+/// A `Sampler` keeps a fixed fraction of the records it is asked about, spread
+/// evenly rather than at random: at a rate of `0.25`, the first of every four
+/// records is kept. The rate may be changed at any time, so one sampler shared
+/// by a group of log statements lets them be turned up or down together.
+///
+/// The `log_sampled!` macro and its per-level forms take either a rate, in
+/// which case each statement has a sampler of its own, or a `Sampler`.
+///
+/// # Warning
+///
+/// The fields of this struct are public so that they may be initialized by the
+/// logging macros. They are subject to change at any time and should never be
+/// accessed directly.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::Sampler;
+///
+/// let sampler = Sampler::new(0.5);
+/// assert_eq!(vec![true, false, true, false],
+///            (0..4).map(|_| sampler.sample()).collect::<Vec<_>>());
+///
+/// sampler.set_rate(0.0);
+/// assert!(!sampler.sample());
+/// ```
+#[derive(Debug)]
+pub struct Sampler {
+    #[doc(hidden)]
+    pub __rate: AtomicUsize,
+    #[doc(hidden)]
+    pub __count: AtomicUsize,
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+#[allow(clippy::declare_interior_mutable_const)]
+pub const __SAMPLER_INIT: Sampler = Sampler {
+    __rate: AtomicUsize::new(0),
+    __count: AtomicUsize::new(0),
+};
+
+impl Sampler {
+    /// Creates a sampler which keeps the fraction `rate` of records.
+    ///
+    /// The rate is clamped to between `0.0` and `1.0`.
+    pub fn new(rate: f64) -> Sampler {
+        let sampler = __SAMPLER_INIT;
+        sampler.set_rate(rate);
+        sampler
+    }
+
+    /// Returns the fraction of records kept.
+    pub fn rate(&self) -> f64 {
+        self.__rate.load(Ordering::Relaxed) as f64 / SCALE as f64
+    }
+
+    /// Sets the fraction of records kept.
+    ///
+    /// The rate is clamped to between `0.0` and `1.0`.
+    pub fn set_rate(&self, rate: f64) {
+        self.__rate.store(to_parts(rate), Ordering::Relaxed);
+    }
+
+    /// Counts a record, returning whether it should be kept.
+    pub fn sample(&self) -> bool {
+        self.sample_parts(self.__rate.load(Ordering::Relaxed))
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Counts a record, returning the rate if it should be kept.
+    #[doc(hidden)]
+    pub fn __sample(&self) -> Option<f64> {
+        let parts = self.__rate.load(Ordering::Relaxed);
+        if self.sample_parts(parts) {
+            Some(parts as f64 / SCALE as f64)
+        } else {
+            None
+        }
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    //
+    // Like __sample, but at rate rather than the sampler's own rate.
+    #[doc(hidden)]
+    pub fn __sample_at(&self, rate: f64) -> Option<f64> {
+        let parts = to_parts(rate);
+        if self.sample_parts(parts) {
+            Some(parts as f64 / SCALE as f64)
+        } else {
+            None
+        }
+    }
+
+    // The nth record is kept if n * rate passes a whole number, which keeps
+    // the first record and spaces the rest evenly. The count is reduced first
+    // so the product can't overflow.
+    fn sample_parts(&self, parts: usize) -> bool {
+        let n = self.__count.fetch_add(1, Ordering::Relaxed) as u64 % SCALE;
+        n * parts as u64 % SCALE < parts as u64
+    }
+}
+
+fn to_parts(rate: f64) -> usize {
+    if rate >= 1.0 {
+        SCALE as usize
+    } else if rate > 0.0 {
+        (rate * SCALE as f64 + 0.5) as usize
+    } else {
+        // also NaN
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    use super::Sampler;
+
+    #[test]
+    fn test_sampler() {
+        let sampler = Sampler::new(0.25);
+        assert_eq!(0.25, sampler.rate());
+        let kept = (0..100).filter(|_| sampler.sample()).count();
+        assert_eq!(25, kept);
+
+        sampler.set_rate(0.01);
+        assert_eq!(0.01, sampler.rate());
+        assert_eq!(1, (0..100).filter(|_| sampler.sample()).count());
+
+        sampler.set_rate(2.0);
+        assert_eq!(1.0, sampler.rate());
+        assert!((0..10).all(|_| sampler.sample()));
+        sampler.set_rate(-1.0);
+        assert!(!(0..10).any(|_| sampler.sample()));
+    }
+
+    #[test]
+    fn test_sampled_macros() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        for i in 0..10 {
+            trace_sampled!(0.5, "sampled {}", i);
+        }
+        let sampler = Sampler::new(0.25);
+        for i in 0..6 {
+            info_sampled!(sampler: &sampler, target: "sampled", "sampled {}", i);
+        }
+
+        let records = ::test::drain();
+        let messages = records.iter().map(|r| r.message()).collect::<Vec<_>>();
+        assert_eq!(vec!["sampled 0", "sampled 2", "sampled 4", "sampled 6", "sampled 8",
+                        "sampled 0", "sampled 4"],
+                   messages);
+        assert_eq!(("sample_rate".to_string(), "0.5".to_string()), records[0].key_values()[0]);
+        assert_eq!("sampled", records[6].target());
+    }
+
+    #[test]
+    fn test_level_sampled_macros() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        critical_sampled!(1.0, "sampled");
+        notice_sampled!(1.0, "sampled");
+        // test::init only enables up to Trace
+        let saved = ::max_level();
+        ::set_max_level(::LogLevelFilter::Trace3);
+        trace2_sampled!(1.0, "sampled");
+        trace3_sampled!(1.0, "sampled");
+        ::set_max_level(saved);
+
+        let records = ::test::drain();
+        let levels = records.iter().map(|r| r.level()).collect::<Vec<_>>();
+        assert_eq!(vec![::LogLevel::Critical, ::LogLevel::Notice, ::LogLevel::Trace2,
+                        ::LogLevel::Trace3],
+                   levels);
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Conversions between LogLevel and the severities of other logging systems,
+// so that loggers writing to those systems agree on how levels map.
+//
+// None of those systems has anything below trace, so Trace2 and Trace3 always
+// map in the same way as Trace, and nothing maps back to them.
+
+use LogLevel;
+
+impl LogLevel {
+    /// Returns the syslog severity of the level, as defined by RFC 5424.
+    ///
+    /// `Critical` is 2, `Error` is 3, `Warn` is 4, `Notice` is 5, `Info` is 6,
+    /// and `Debug` and the trace levels are all 7.
+    pub fn to_syslog_severity(&self) -> u8 {
+        match *self {
+            LogLevel::Critical => 2,
+            LogLevel::Error => 3,
+            LogLevel::Warn => 4,
+            LogLevel::Notice => 5,
+            LogLevel::Info => 6,
+            LogLevel::Debug | LogLevel::Trace | LogLevel::Trace2 | LogLevel::Trace3 => 7,
+        }
+    }
+
+    /// Converts a syslog severity, as defined by RFC 5424, to a level.
+    ///
+    /// The severities from 0 for emergency to 2 for critical become
+    /// `Critical`. Returns `None` for severities above 7.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevel;
+    ///
+    /// assert_eq!(Some(LogLevel::Critical), LogLevel::from_syslog_severity(0));
+    /// assert_eq!(Some(LogLevel::Debug), LogLevel::from_syslog_severity(7));
+    /// assert_eq!(None, LogLevel::from_syslog_severity(8));
+    /// ```
+    pub fn from_syslog_severity(severity: u8) -> Option<LogLevel> {
+        match severity {
+            0..=2 => Some(LogLevel::Critical),
+            3 => Some(LogLevel::Error),
+            4 => Some(LogLevel::Warn),
+            5 => Some(LogLevel::Notice),
+            6 => Some(LogLevel::Info),
+            7 => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the `PRIORITY` field of a systemd journal entry
+    /// for the level.
+    ///
+    /// The journal uses the syslog severities, so this is the same as
+    /// `to_syslog_severity`.
+    pub fn to_journald_priority(&self) -> u8 {
+        self.to_syslog_severity()
+    }
+
+    /// Converts the value of the `PRIORITY` field of a systemd journal entry
+    /// to a level.
+    ///
+    /// The journal uses the syslog severities, so this is the same as
+    /// `from_syslog_severity`. Returns `None` for priorities above 7.
+    pub fn from_journald_priority(priority: u8) -> Option<LogLevel> {
+        LogLevel::from_syslog_severity(priority)
+    }
+
+    /// Returns the Windows Event Log event type of the level.
+    ///
+    /// The Event Log only has three event types for ordinary events, so
+    /// `Critical` and `Error` are `EVENTLOG_ERROR_TYPE` (1), `Warn` is
+    /// `EVENTLOG_WARNING_TYPE` (2), and the other levels are all
+    /// `EVENTLOG_INFORMATION_TYPE` (4).
+    ///
+    /// This is only available with the `windows` feature.
+    #[cfg(feature = "windows")]
+    pub fn to_event_log_type(&self) -> u16 {
+        match *self {
+            LogLevel::Critical | LogLevel::Error => 0x0001,
+            LogLevel::Warn => 0x0002,
+            _ => 0x0004,
+        }
+    }
+
+    /// Converts a Windows Event Log event type to a level.
+    ///
+    /// Information events become `Info`. Returns `None` for the audit event
+    /// types, which don't have a level, and for anything else which isn't an
+    /// event type.
+    ///
+    /// This is only available with the `windows` feature.
+    #[cfg(feature = "windows")]
+    pub fn from_event_log_type(event_type: u16) -> Option<LogLevel> {
+        match event_type {
+            0x0001 => Some(LogLevel::Error),
+            0x0002 => Some(LogLevel::Warn),
+            // EVENTLOG_SUCCESS is 0, and is also informational
+            0x0000 | 0x0004 => Some(LogLevel::Info),
+            _ => None,
+        }
+    }
+
+    /// Returns the Android `android_LogPriority` of the level.
+    ///
+    /// The trace levels are `ANDROID_LOG_VERBOSE` (2), `Debug` is
+    /// `ANDROID_LOG_DEBUG` (3), and so on up to `Critical`, which is
+    /// `ANDROID_LOG_FATAL` (7). There is no notice priority, so `Notice` is
+    /// `ANDROID_LOG_INFO` (4), like `Info`.
+    pub fn to_android_priority(&self) -> u8 {
+        match *self {
+            LogLevel::Critical => 7,
+            LogLevel::Error => 6,
+            LogLevel::Warn => 5,
+            LogLevel::Notice | LogLevel::Info => 4,
+            LogLevel::Debug => 3,
+            LogLevel::Trace | LogLevel::Trace2 | LogLevel::Trace3 => 2,
+        }
+    }
+
+    /// Converts an Android `android_LogPriority` to a level.
+    ///
+    /// Returns `None` for `ANDROID_LOG_UNKNOWN`, `ANDROID_LOG_DEFAULT` and
+    /// `ANDROID_LOG_SILENT`, which aren't priorities of messages.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevel;
+    ///
+    /// assert_eq!(Some(LogLevel::Trace), LogLevel::from_android_priority(2));
+    /// assert_eq!(Some(LogLevel::Critical), LogLevel::from_android_priority(7));
+    /// assert_eq!(None, LogLevel::from_android_priority(8));
+    /// ```
+    pub fn from_android_priority(priority: u8) -> Option<LogLevel> {
+        match priority {
+            2 => Some(LogLevel::Trace),
+            3 => Some(LogLevel::Debug),
+            4 => Some(LogLevel::Info),
+            5 => Some(LogLevel::Warn),
+            6 => Some(LogLevel::Error),
+            7 => Some(LogLevel::Critical),
+            _ => None,
+        }
+    }
+
+    /// Returns the Apple unified logging `os_log_type_t` of the level.
+    ///
+    /// `Critical` is `OS_LOG_TYPE_FAULT` (0x11), `Error` is
+    /// `OS_LOG_TYPE_ERROR` (0x10), `Info` is `OS_LOG_TYPE_INFO` (0x01), and
+    /// `Debug` and the trace levels are all `OS_LOG_TYPE_DEBUG` (0x02). There
+    /// is no warning type, so `Warn` and `Notice` are `OS_LOG_TYPE_DEFAULT`
+    /// (0x00), which sits between info and error and is always persisted.
+    pub fn to_os_log_type(&self) -> u8 {
+        match *self {
+            LogLevel::Critical => 0x11,
+            LogLevel::Error => 0x10,
+            LogLevel::Warn | LogLevel::Notice => 0x00,
+            LogLevel::Info => 0x01,
+            LogLevel::Debug | LogLevel::Trace | LogLevel::Trace2 | LogLevel::Trace3 => 0x02,
+        }
+    }
+
+    /// Returns the OpenTelemetry severity number of the level.
+    ///
+    /// OpenTelemetry gives each of its severities a range of four numbers, and
+    /// the level is the first number of the matching range: the trace levels
+    /// are 1, `Debug` is 5, `Info` is 9, `Warn` is 13, `Error` is 17 and
+    /// `Critical` is 21, the first of the fatal range. `Notice` is 10, the
+    /// second of the info range, as OpenTelemetry maps the syslog notice
+    /// severity.
+    pub fn to_otel_severity(&self) -> u8 {
+        match *self {
+            LogLevel::Critical => 21,
+            LogLevel::Error => 17,
+            LogLevel::Warn => 13,
+            LogLevel::Notice => 10,
+            LogLevel::Info => 9,
+            LogLevel::Debug => 5,
+            LogLevel::Trace | LogLevel::Trace2 | LogLevel::Trace3 => 1,
+        }
+    }
+
+    /// Returns the Python `logging` level of the level.
+    ///
+    /// `Debug` is `DEBUG` (10), `Info` is `INFO` (20), `Warn` is `WARNING`
+    /// (30), `Error` is `ERROR` (40) and `Critical` is `CRITICAL` (50). Python
+    /// has no trace level, so the trace levels are 5, which is the number
+    /// usually given to one added with `logging.addLevelName`. Likewise,
+    /// `Notice` is 25, halfway between `INFO` and `WARNING`.
+    pub fn to_python_level(&self) -> u32 {
+        match *self {
+            LogLevel::Critical => 50,
+            LogLevel::Error => 40,
+            LogLevel::Warn => 30,
+            LogLevel::Notice => 25,
+            LogLevel::Info => 20,
+            LogLevel::Debug => 10,
+            LogLevel::Trace | LogLevel::Trace2 | LogLevel::Trace3 => 5,
+        }
+    }
+
+    /// Converts a Python `logging` level to a level.
+    ///
+    /// Python programs may define levels of their own, so each level covers
+    /// the numbers up to the next one: anything above `CRITICAL` (50) becomes
+    /// `Critical`, and anything between 1 and 9 becomes `Trace`. Returns
+    /// `None` for `NOTSET` (0).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevel;
+    ///
+    /// assert_eq!(Some(LogLevel::Warn), LogLevel::from_python_level(30));
+    /// assert_eq!(Some(LogLevel::Info), LogLevel::from_python_level(22));
+    /// assert_eq!(Some(LogLevel::Critical), LogLevel::from_python_level(50));
+    /// ```
+    pub fn from_python_level(level: u32) -> Option<LogLevel> {
+        if level >= 50 {
+            Some(LogLevel::Critical)
+        } else if level >= 40 {
+            Some(LogLevel::Error)
+        } else if level >= 30 {
+            Some(LogLevel::Warn)
+        } else if level >= 25 {
+            Some(LogLevel::Notice)
+        } else if level >= 20 {
+            Some(LogLevel::Info)
+        } else if level >= 10 {
+            Some(LogLevel::Debug)
+        } else if level > 0 {
+            Some(LogLevel::Trace)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the log4j level of the level, as returned by `intLevel`.
+    ///
+    /// `Critical` is `FATAL` (100), `Error` is `ERROR` (200), `Warn` is `WARN`
+    /// (300), `Info` is `INFO` (400), `Debug` is `DEBUG` (500) and the trace
+    /// levels are `TRACE` (600). log4j has no notice level, so `Notice` is
+    /// 350, halfway between `WARN` and `INFO`.
+    pub fn to_log4j_level(&self) -> u32 {
+        match *self {
+            LogLevel::Critical => 100,
+            LogLevel::Error => 200,
+            LogLevel::Warn => 300,
+            LogLevel::Notice => 350,
+            LogLevel::Info => 400,
+            LogLevel::Debug => 500,
+            LogLevel::Trace | LogLevel::Trace2 | LogLevel::Trace3 => 600,
+        }
+    }
+
+    /// Converts a log4j level, as returned by `intLevel`, to a level.
+    ///
+    /// As with `from_python_level`, each level covers the custom levels
+    /// between it and the next more severe one, so 450 becomes `Debug`, and
+    /// `ALL` becomes `Trace`. Returns `None` for `OFF` (0).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LogLevel;
+    ///
+    /// assert_eq!(Some(LogLevel::Critical), LogLevel::from_log4j_level(100));
+    /// assert_eq!(Some(LogLevel::Debug), LogLevel::from_log4j_level(500));
+    /// assert_eq!(None, LogLevel::from_log4j_level(0));
+    /// ```
+    pub fn from_log4j_level(level: u32) -> Option<LogLevel> {
+        if level == 0 {
+            None
+        } else if level <= 100 {
+            Some(LogLevel::Critical)
+        } else if level <= 200 {
+            Some(LogLevel::Error)
+        } else if level <= 300 {
+            Some(LogLevel::Warn)
+        } else if level <= 350 {
+            Some(LogLevel::Notice)
+        } else if level <= 400 {
+            Some(LogLevel::Info)
+        } else if level <= 500 {
+            Some(LogLevel::Debug)
+        } else {
+            Some(LogLevel::Trace)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use LogLevel;
+
+    #[test]
+    fn test_syslog_severity() {
+        assert_eq!(3, LogLevel::Error.to_syslog_severity());
+        assert_eq!(4, LogLevel::Warn.to_syslog_severity());
+        assert_eq!(6, LogLevel::Info.to_syslog_severity());
+        assert_eq!(7, LogLevel::Trace.to_syslog_severity());
+
+        for level in LogLevel::iter().filter(|&level| level < LogLevel::Trace) {
+            assert_eq!(Some(level), LogLevel::from_syslog_severity(level.to_syslog_severity()));
+        }
+        assert_eq!(Some(LogLevel::Critical), LogLevel::from_syslog_severity(0));
+        assert_eq!(Some(LogLevel::Notice), LogLevel::from_syslog_severity(5));
+        assert_eq!(None, LogLevel::from_syslog_severity(8));
+    }
+
+    #[test]
+    fn test_journald_priority() {
+        assert_eq!(3, LogLevel::Error.to_journald_priority());
+        assert_eq!(7, LogLevel::Debug.to_journald_priority());
+        assert_eq!(Some(LogLevel::Warn), LogLevel::from_journald_priority(4));
+        assert_eq!(Some(LogLevel::Critical), LogLevel::from_journald_priority(1));
+        assert_eq!(None, LogLevel::from_journald_priority(10));
+    }
+
+    #[test]
+    fn test_android_priority() {
+        for level in LogLevel::iter().filter(|&l| l <= LogLevel::Trace && l != LogLevel::Notice) {
+            assert_eq!(Some(level), LogLevel::from_android_priority(level.to_android_priority()));
+        }
+        assert_eq!(2, LogLevel::Trace.to_android_priority());
+        assert_eq!(2, LogLevel::Trace3.to_android_priority());
+        assert_eq!(4, LogLevel::Notice.to_android_priority());
+        assert_eq!(6, LogLevel::Error.to_android_priority());
+        assert_eq!(7, LogLevel::Critical.to_android_priority());
+        assert_eq!(None, LogLevel::from_android_priority(1));
+    }
+
+    #[test]
+    fn test_os_log_type() {
+        assert_eq!(0x11, LogLevel::Critical.to_os_log_type());
+        assert_eq!(0x10, LogLevel::Error.to_os_log_type());
+        assert_eq!(0x00, LogLevel::Warn.to_os_log_type());
+        assert_eq!(0x00, LogLevel::Notice.to_os_log_type());
+        assert_eq!(0x01, LogLevel::Info.to_os_log_type());
+        assert_eq!(0x02, LogLevel::Debug.to_os_log_type());
+        assert_eq!(0x02, LogLevel::Trace.to_os_log_type());
+        assert_eq!(0x02, LogLevel::Trace2.to_os_log_type());
+    }
+
+    #[test]
+    fn test_otel_severity() {
+        let severities = LogLevel::iter().map(|level| level.to_otel_severity());
+        assert!(severities.eq([21, 17, 13, 10, 9, 5, 1, 1, 1].iter().cloned()));
+    }
+
+    #[test]
+    fn test_python_level() {
+        for level in LogLevel::iter().filter(|&level| level <= LogLevel::Trace) {
+            assert_eq!(Some(level), LogLevel::from_python_level(level.to_python_level()));
+        }
+        assert_eq!(40, LogLevel::Error.to_python_level());
+        assert_eq!(10, LogLevel::Debug.to_python_level());
+        assert_eq!(Some(LogLevel::Debug), LogLevel::from_python_level(15));
+        assert_eq!(Some(LogLevel::Info), LogLevel::from_python_level(24));
+        assert_eq!(Some(LogLevel::Critical), LogLevel::from_python_level(60));
+        assert_eq!(None, LogLevel::from_python_level(0));
+    }
+
+    #[test]
+    fn test_log4j_level() {
+        for level in LogLevel::iter().filter(|&level| level <= LogLevel::Trace) {
+            assert_eq!(Some(level), LogLevel::from_log4j_level(level.to_log4j_level()));
+        }
+        assert_eq!(200, LogLevel::Error.to_log4j_level());
+        assert_eq!(600, LogLevel::Trace.to_log4j_level());
+        assert_eq!(600, LogLevel::Trace2.to_log4j_level());
+        assert_eq!(Some(LogLevel::Error), LogLevel::from_log4j_level(150));
+        assert_eq!(Some(LogLevel::Info), LogLevel::from_log4j_level(351));
+        assert_eq!(Some(LogLevel::Trace), LogLevel::from_log4j_level(u32::MAX));
+        assert_eq!(None, LogLevel::from_log4j_level(0));
+    }
+
+    #[test]
+    #[cfg(feature = "windows")]
+    fn test_event_log_type() {
+        assert_eq!(1, LogLevel::Critical.to_event_log_type());
+        assert_eq!(1, LogLevel::Error.to_event_log_type());
+        assert_eq!(2, LogLevel::Warn.to_event_log_type());
+        assert_eq!(4, LogLevel::Info.to_event_log_type());
+        assert_eq!(4, LogLevel::Trace3.to_event_log_type());
+        assert_eq!(Some(LogLevel::Warn), LogLevel::from_event_log_type(2));
+        assert_eq!(Some(LogLevel::Info), LogLevel::from_event_log_type(0));
+        assert_eq!(None, LogLevel::from_event_log_type(0x0008));
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::raw::c_int;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::thread;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use ControlMessage;
+
+const SIGHUP: c_int = 1;
+const SIG_ERR: usize = !0;
+
+extern "C" {
+    fn pipe(fds: *mut c_int) -> c_int;
+    // handlers are passed as usize, since the old one may be SIG_DFL or SIG_IGN
+    fn signal(signum: c_int, handler: usize) -> usize;
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+// The write end of the pipe to the worker thread.
+static WRITE_FD: AtomicUsize = AtomicUsize::new(0);
+
+// Set while a byte is in the pipe and the worker hasn't handled it yet, so the
+// pipe never fills up and the signal handler never blocks.
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Installs a `SIGHUP` handler which flushes the global logger and asks it to
+/// reopen its files.
+///
+/// This is the signal logrotate and similar tools send a daemon once they have
+/// moved its log files aside. Flushing and reopening can't be done from the
+/// signal handler itself, so the handler wakes a background thread which
+/// sends the logger `ControlMessage::Flush` and then `ControlMessage::Reopen`.
+/// Signals arriving while the thread is busy are coalesced.
+///
+/// This replaces any `SIGHUP` handler already installed, and calling it more
+/// than once has no further effect. Returns an error if the pipe to the
+/// thread can't be created, the thread can't be spawned, or the handler
+/// can't be installed.
+///
+/// This is only available on Unix.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate log;
+///
+/// fn main() {
+///     log::install_sighup_handler().unwrap();
+///     // `kill -HUP` now makes the logger reopen its files
+/// }
+/// ```
+pub fn install_sighup_handler() -> io::Result<()> {
+    if INSTALLED.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Ok(());
+    }
+
+    let result = install();
+    if result.is_err() {
+        INSTALLED.store(false, Ordering::SeqCst);
+    }
+    result
+}
+
+fn install() -> io::Result<()> {
+    let mut fds = [0 as c_int; 2];
+    if unsafe { pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let read = unsafe { File::from_raw_fd(fds[0]) };
+    let write = unsafe { File::from_raw_fd(fds[1]) };
+
+    // The handler is installed before the thread is spawned, so a failure
+    // leaves nothing running and install_sighup_handler can be retried.
+    WRITE_FD.store(fds[1] as usize, Ordering::SeqCst);
+    let old = unsafe { signal(SIGHUP, on_sighup as extern "C" fn(c_int) as usize) };
+    if old == SIG_ERR {
+        return Err(io::Error::last_os_error());
+    }
+
+    let spawned = thread::Builder::new()
+                      .name("log-sighup".into())
+                      .spawn(move || run(read));
+    if let Err(e) = spawned {
+        // put the old handler back before the pipe is closed under it
+        unsafe { signal(SIGHUP, old) };
+        PENDING.store(false, Ordering::SeqCst);
+        return Err(e);
+    }
+
+    // the handler owns the write end from here on
+    mem::forget(write);
+    Ok(())
+}
+
+fn run(mut read: File) {
+    let mut byte = [0; 1];
+    loop {
+        match read.read(&mut byte) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        }
+        PENDING.store(false, Ordering::SeqCst);
+        ::control(ControlMessage::Flush);
+        ::control(ControlMessage::Reopen);
+    }
+}
+
+extern "C" fn on_sighup(_: c_int) {
+    if PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // write(2) is async-signal-safe, and File adds nothing on top of it
+    let mut file = unsafe { File::from_raw_fd(WRITE_FD.load(Ordering::SeqCst) as RawFd) };
+    let _ = file.write(&[1]);
+    mem::forget(file);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_int;
+    use std::thread;
+    use std::time::Duration;
+
+    use core::sync::atomic::Ordering;
+
+    use super::{install_sighup_handler, PENDING, SIGHUP};
+
+    extern "C" {
+        fn raise(signum: c_int) -> c_int;
+    }
+
+    #[test]
+    fn test_sighup_handler() {
+        install_sighup_handler().unwrap();
+        install_sighup_handler().unwrap();
+
+        // without the handler, this would terminate the test process
+        for _ in 0..3 {
+            assert_eq!(0, unsafe { raise(SIGHUP) });
+        }
+        for _ in 0..500 {
+            if !PENDING.load(Ordering::SeqCst) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("SIGHUP was not handled");
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Logging from signal handlers.
+//!
+//! Signal handlers may only call async-signal-safe functions, which rules out
+//! anything that allocates or takes a lock, and so rules out calling the
+//! installed logger. The `log_signal_safe!` macro instead formats the record
+//! into a fixed-size buffer on the stack and writes it to a file descriptor
+//! with a single `write` call. Nothing is allocated, no locks are taken, and
+//! the logger refcount is not touched. Records are still subject to the
+//! global maximum log level.
+//!
+//! Records are written as `LEVEL [target] message` lines, and are truncated
+//! to 512 bytes. They go to standard error unless another descriptor is set
+//! with `set_fd`.
+//!
+//! The arguments are formatted through their `Display` and `Debug`
+//! implementations from inside the signal handler, so only types whose
+//! implementations don't allocate, such as integers and string slices, should
+//! be passed.
+//!
+//! This is only available on Unix.
+//!
+//! # Examples
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate log;
+//!
+//! use log::LogLevel;
+//!
+//! extern "C" fn on_sigsegv(signal: i32) {
+//!     log_signal_safe!(LogLevel::Error, "received signal {}", signal);
+//! }
+//! # fn main() {}
+//! ```
+
+use std::fs::File;
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::{FromRawFd, RawFd};
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use LogLevel;
+
+const BUF_SIZE: usize = 512;
+
+// One more than the descriptor records are written to, or 0 for standard
+// error. It doesn't guard any other data, so Relaxed is enough.
+static FD: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the file descriptor records are written to.
+///
+/// The descriptor must stay open for as long as records may be logged. The
+/// default is standard error.
+pub fn set_fd(fd: RawFd) {
+    FD.store(fd as usize + 1, Ordering::Relaxed);
+}
+
+/// Writes a record to the signal-safe file descriptor.
+///
+/// This is the function behind `log_signal_safe!`, which should normally be
+/// used instead.
+pub fn log(level: LogLevel, target: &str, args: fmt::Arguments) {
+    if level > ::max_log_level() {
+        return;
+    }
+
+    let mut buf = Buf {
+        bytes: [0; BUF_SIZE],
+        len: 0,
+    };
+    let _ = fmt::write(&mut buf, format_args!("{:<5} [{}] {}", level, target, args));
+    buf.truncate_for_newline();
+    buf.bytes[buf.len] = b'\n';
+
+    let fd = match FD.load(Ordering::Relaxed) {
+        0 => 2,
+        fd => (fd - 1) as RawFd,
+    };
+    let file = unsafe { File::from_raw_fd(fd) };
+    let _ = (&file).write(&buf.bytes[..buf.len + 1]);
+    // the descriptor isn't ours to close
+    mem::forget(file);
+}
+
+struct Buf {
+    bytes: [u8; BUF_SIZE],
+    len: usize,
+}
+
+impl Buf {
+    // Leaves room for the trailing newline, without splitting a character.
+    fn truncate_for_newline(&mut self) {
+        if self.len == BUF_SIZE {
+            self.len -= 1;
+            while self.len > 0 && self.bytes[self.len] & 0xc0 == 0x80 {
+                self.len -= 1;
+            }
+        }
+    }
+}
+
+impl fmt::Write for Buf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = BUF_SIZE - self.len;
+        let mut n = s.len();
+        if n > room {
+            // only copy whole characters
+            n = room;
+            while !s.is_char_boundary(n) {
+                n -= 1;
+            }
+        }
+        self.bytes[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        if n < s.len() { Err(fmt::Error) } else { Ok(()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+    use std::string::String;
+    use std::vec::Vec;
+    use std::{cmp, env, fs};
+
+    use {LogLevel, LogLevelFilter};
+    use super::{log, set_fd};
+
+    #[test]
+    fn test_signal_safe() {
+        // only ever raises the level, so that tests running at the same time
+        // still see everything they log
+        let saved = ::max_log_level();
+        ::set_max_level(cmp::max(saved, LogLevelFilter::Info));
+        let path = env::temp_dir().join("log-signal-safe-test.log");
+        let file = File::create(&path).unwrap();
+        set_fd(file.as_raw_fd());
+
+        log_signal_safe!(LogLevel::Error, "signal {}", 11);
+        log_signal_safe!(target: "crash", LogLevel::Warn, "dumping core");
+        let long = core::iter::repeat_n("é", 300).collect::<String>();
+        log(LogLevel::Info, "long", format_args!("{}", long));
+        set_fd(2);
+        ::set_max_level(saved);
+
+        let mut written = String::new();
+        File::open(&path).unwrap().read_to_string(&mut written).unwrap();
+        let lines = written.lines().collect::<Vec<_>>();
+        assert_eq!(format!("ERROR [{}] signal 11", module_path!()), lines[0]);
+        assert_eq!("WARN  [crash] dumping core", lines[1]);
+        assert!(lines[2].starts_with("INFO  [long] éé"));
+        assert!(lines[2].len() <= 511);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::boxed::Box;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use core::fmt;
+
+use {Log, LogLevelFilter, LogMetadata, LogRecord, SetLoggerError};
+use kv::{Value, Visitor};
+
+/// A minimal logger which writes records to standard error.
+///
+/// Each record is written on its own line, as a UTC timestamp followed by the
+/// level, the target, the message, and any key-value pairs:
+///
+/// ```text
+/// 2015-10-17T12:34:56.789Z INFO  [my_app] listening port=8080
+/// ```
+///
+/// This is only available with the `simple_logger` feature.
+#[derive(Debug)]
+pub struct SimpleLogger {
+    level: LogLevelFilter,
+}
+
+impl SimpleLogger {
+    /// Creates a logger which logs messages at `level` and below.
+    pub fn new(level: LogLevelFilter) -> SimpleLogger {
+        SimpleLogger { level }
+    }
+}
+
+impl Log for SimpleLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.is_audit() || metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let stderr = io::stderr();
+        let mut stderr = stderr.lock();
+        let time = record.timestamp().unwrap_or_else(SystemTime::now);
+        let _ = write!(stderr, "{} {:<5} [{}] {}", Timestamp(time),
+                       record.level(), record.target(), record.args());
+        record.key_values().visit(&mut KeyValueWriter(&mut stderr));
+        let _ = writeln!(stderr);
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+struct KeyValueWriter<'a, W: Write + 'a>(&'a mut W);
+
+impl<'a, W: Write> Visitor for KeyValueWriter<'a, W> {
+    fn visit_pair(&mut self, key: &str, value: Value) {
+        let _ = write!(self.0, " {}={}", key, value);
+    }
+}
+
+// Formats as RFC 3339 in UTC, with millisecond precision.
+struct Timestamp(SystemTime);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let since_epoch = match self.0.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => since_epoch,
+            Err(_) => return write!(fmt, "1970-01-01T00:00:00.000Z"),
+        };
+        let secs = since_epoch.as_secs();
+        let millis = since_epoch.subsec_millis();
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let secs_of_day = secs % 86400;
+        write!(fmt, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+               year, month, day, secs_of_day / 3600, secs_of_day / 60 % 60,
+               secs_of_day % 60, millis)
+    }
+}
+
+// Converts days since 1970-01-01 to a (year, month, day) date in the
+// proleptic Gregorian calendar.
+// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + (if month <= 2 { 1 } else { 0 });
+    (year, month, day)
+}
+
+/// Installs a `SimpleLogger` as the global logger.
+///
+/// The logger writes messages at `level` and below to standard error, and
+/// the global maximum log level is set to `level`.
+///
+/// This is only available with the `simple_logger` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate log;
+///
+/// use log::LogLevelFilter;
+///
+/// fn main() {
+///     log::init_simple(LogLevelFilter::Info).unwrap();
+///     info!("starting up");
+/// }
+/// ```
+pub fn init_simple(level: LogLevelFilter) -> Result<(), SetLoggerError> {
+    ::set_boxed_logger(|max_log_level| {
+        max_log_level.set(level);
+        Box::new(SimpleLogger::new(level))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{civil_from_days, Timestamp};
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11016));
+        assert_eq!((2015, 12, 31), civil_from_days(16800));
+        assert_eq!((1969, 12, 31), civil_from_days(-1));
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let time = UNIX_EPOCH + Duration::new(1445085296, 789000000);
+        assert_eq!("2015-10-17T12:34:56.789Z", Timestamp(time).to_string());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use {LogLevel, LogLocation};
+
+// Span ids only need to be unique, so the counter doesn't order anything.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CURRENT: Cell<Option<u64>> = const { Cell::new(None) }
+}
+
+/// Returns the id of the innermost span entered on the current thread, if
+/// any.
+pub fn current_span_id() -> Option<u64> {
+    CURRENT.try_with(|current| current.get()).unwrap_or(None)
+}
+
+/// A guard for a span entered with the `span!` macro.
+///
+/// Records logged on the current thread while the guard is alive carry the
+/// span's id, which is available from `LogRecord::span_id`. A record is
+/// logged when the span is entered and another when the guard is dropped.
+///
+/// If the span's level is disabled, no records are logged, the span has no id,
+/// and records logged inside it carry the id of the enclosing span.
+#[must_use]
+#[derive(Debug)]
+pub struct Span {
+    entered: Option<Entered>,
+    _not_send: PhantomData<*const ()>,
+}
+
+#[derive(Debug)]
+struct Entered {
+    id: u64,
+    parent: Option<u64>,
+    level: LogLevel,
+    target: &'static str,
+    name: &'static str,
+    loc: &'static LogLocation,
+}
+
+impl Span {
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    #[doc(hidden)]
+    pub fn __enter(level: LogLevel, target: &'static str, name: &'static str,
+                   loc: &'static LogLocation) -> Span {
+        if !::__enabled(level, target) {
+            return Span::__disabled();
+        }
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed) as u64 + 1;
+        let parent = CURRENT.with(|current| current.replace(Some(id)));
+        ::__log(level, target, loc, format_args!("enter {}", name), &[]);
+        Span {
+            entered: Some(Entered {
+                id,
+                parent,
+                level,
+                target,
+                name,
+                loc,
+            }),
+            _not_send: PhantomData,
+        }
+    }
+
+    // WARNING
+    // This is not considered part of the crate's public API. It is subject to
+    // change at any time.
+    #[doc(hidden)]
+    pub fn __disabled() -> Span {
+        Span {
+            entered: None,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns the span's id, or `None` if its level is disabled.
+    pub fn id(&self) -> Option<u64> {
+        self.entered.as_ref().map(|entered| entered.id)
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(ref entered) = self.entered {
+            ::__log(entered.level, entered.target, entered.loc,
+                    format_args!("exit {}", entered.name), &[]);
+            let _ = CURRENT.try_with(|current| current.set(entered.parent));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use LogLevel;
+    use super::current_span_id;
+
+    #[test]
+    fn test_span() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        assert_eq!(None, current_span_id());
+        let (outer_id, inner_id) = {
+            let outer = span!(LogLevel::Debug, "request");
+            let outer_id = outer.id();
+            assert!(outer_id.is_some());
+            assert_eq!(outer_id, current_span_id());
+            let inner_id = {
+                let inner = span!(target: "db", LogLevel::Debug, "query");
+                info!("querying");
+                inner.id()
+            };
+            assert!(inner_id.is_some() && inner_id != outer_id);
+            assert_eq!(outer_id, current_span_id());
+            (outer_id, inner_id)
+        };
+        assert_eq!(None, current_span_id());
+
+        let records = ::test::drain();
+        let summary = records.iter()
+                             .map(|r| (r.level(), r.target(), r.message(), r.span_id()))
+                             .collect::<Vec<_>>();
+        assert_eq!(vec![(LogLevel::Debug, module_path!(), "enter request", outer_id),
+                        (LogLevel::Debug, "db", "enter query", inner_id),
+                        (LogLevel::Info, module_path!(), "querying", inner_id),
+                        (LogLevel::Debug, "db", "exit query", inner_id),
+                        (LogLevel::Debug, module_path!(), "exit request", outer_id)],
+                   summary);
+    }
+
+    #[test]
+    fn test_disabled_span() {
+        ::test::init().unwrap();
+        ::test::clear();
+        ::set_target_max_level("span_disabled", ::LogLevelFilter::Info);
+
+        let outer = span!(target: "span_enabled", LogLevel::Debug, "outer");
+        let inner = span!(target: "span_disabled", LogLevel::Debug, "inner");
+        assert_eq!(None, inner.id());
+        assert_eq!(outer.id(), current_span_id());
+        drop(inner);
+        drop(outer);
+
+        ::remove_target_max_level("span_disabled");
+        assert_eq!(2, ::test::drain().len());
+    }
+}
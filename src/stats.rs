@@ -0,0 +1,205 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Counts of the records passed to loggers, for the statistics feature.
+//
+// The per-level counts are plain atomics. The per-target counts need a lock
+// and an allocation for each new target, so they are only kept once
+// count_targets has been called, and the table is allocated then, in the
+// same way as the per-target levels.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(feature = "freestanding"))]
+use core::sync::atomic::AtomicBool;
+#[cfg(not(feature = "freestanding"))]
+use std::boxed::Box;
+#[cfg(not(feature = "freestanding"))]
+use std::collections::BTreeMap;
+#[cfg(not(feature = "freestanding"))]
+use std::ptr;
+#[cfg(not(feature = "freestanding"))]
+use std::string::{String, ToString};
+#[cfg(not(feature = "freestanding"))]
+use std::sync::{Mutex, MutexGuard, Once};
+#[cfg(not(feature = "freestanding"))]
+use std::vec::Vec;
+
+use LogLevel;
+
+// The number of records logged at each level, indexed by level - 1. These
+// are only statistics, so Relaxed is enough.
+static LOGGED_RECORDS: [AtomicUsize; 9] = [AtomicUsize::new(0), AtomicUsize::new(0),
+                                           AtomicUsize::new(0), AtomicUsize::new(0),
+                                           AtomicUsize::new(0), AtomicUsize::new(0),
+                                           AtomicUsize::new(0), AtomicUsize::new(0),
+                                           AtomicUsize::new(0)];
+
+#[cfg(not(feature = "freestanding"))]
+static TARGETS_ACTIVE: AtomicBool = AtomicBool::new(false);
+#[cfg(not(feature = "freestanding"))]
+static INIT: Once = Once::new();
+#[cfg(not(feature = "freestanding"))]
+static mut TARGETS: *const Mutex<BTreeMap<String, [u64; 9]>> = ptr::null();
+
+#[cfg(not(feature = "freestanding"))]
+fn targets() -> MutexGuard<'static, BTreeMap<String, [u64; 9]>> {
+    let targets = unsafe {
+        INIT.call_once(|| {
+            TARGETS = Box::into_raw(Box::new(Mutex::new(BTreeMap::new())));
+        });
+        &*TARGETS
+    };
+    match targets.lock() {
+        Ok(targets) => targets,
+        Err(err) => err.into_inner(),
+    }
+}
+
+// Counts a record passed to a logger.
+pub fn record(level: LogLevel, target: &str) {
+    LOGGED_RECORDS[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+    record_target(level, target);
+}
+
+#[cfg(not(feature = "freestanding"))]
+fn record_target(level: LogLevel, target: &str) {
+    if !TARGETS_ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut targets = targets();
+    if let Some(counts) = targets.get_mut(target) {
+        counts[level as usize - 1] += 1;
+        return;
+    }
+    let mut counts = [0; 9];
+    counts[level as usize - 1] = 1;
+    targets.insert(target.to_string(), counts);
+}
+
+#[cfg(feature = "freestanding")]
+#[inline(always)]
+fn record_target(_: LogLevel, _: &str) {}
+
+/// Counts of records passed to loggers.
+///
+/// This is returned by `statistics` and `target_statistics`.
+///
+/// This is only available with the `statistics` feature.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Statistics {
+    counts: [u64; 9],
+}
+
+impl Statistics {
+    /// Returns the number of records logged at `level`.
+    pub fn level(&self, level: LogLevel) -> u64 {
+        self.counts[level as usize - 1]
+    }
+
+    /// Returns the number of records logged at any level.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Returns the number of records logged since the program started, by level.
+///
+/// A record is counted when the logging macros pass it to a logger, whether
+/// the installed one or one given to `log_to!`. The logger may still discard
+/// it. Comparing two snapshots gives the rate at which, say, errors are being
+/// logged, without parsing any log output.
+///
+/// This is only available with the `statistics` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::LogLevel;
+///
+/// let before = log::statistics();
+/// // ...
+/// let errors = log::statistics().level(LogLevel::Error) - before.level(LogLevel::Error);
+/// # let _ = errors;
+/// ```
+pub fn statistics() -> Statistics {
+    let mut counts = [0; 9];
+    for (count, logged) in counts.iter_mut().zip(LOGGED_RECORDS.iter()) {
+        *count = logged.load(Ordering::Relaxed) as u64;
+    }
+    Statistics { counts }
+}
+
+/// Starts or stops counting records by target as well as by level.
+///
+/// Counting by target takes a lock for each record, so it is off by default.
+/// Counts already taken are kept while it is off.
+///
+/// This is only available with the `statistics` feature and the standard
+/// library.
+#[cfg(not(feature = "freestanding"))]
+pub fn count_targets(enabled: bool) {
+    TARGETS_ACTIVE.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the number of records logged for each target while `count_targets`
+/// was on, sorted by target.
+///
+/// This is only available with the `statistics` feature and the standard
+/// library.
+///
+/// # Examples
+///
+/// ```rust
+/// log::count_targets(true);
+/// // ...
+/// for (target, stats) in log::target_statistics() {
+///     println!("{}: {} records", target, stats.total());
+/// }
+/// ```
+#[cfg(not(feature = "freestanding"))]
+pub fn target_statistics() -> Vec<(String, Statistics)> {
+    targets().iter()
+             .map(|(target, &counts)| (target.clone(), Statistics { counts }))
+             .collect()
+}
+
+#[cfg(all(test, not(feature = "freestanding")))]
+mod tests {
+    use LogLevel;
+    use super::{count_targets, statistics, target_statistics};
+
+    #[test]
+    fn test_statistics() {
+        ::test::init().unwrap();
+        ::test::clear();
+
+        let before = statistics();
+        count_targets(true);
+        error!(target: "stats", "a");
+        info!(target: "stats", "b");
+        info!(target: "stats", "c");
+        count_targets(false);
+        info!(target: "stats", "d");
+        ::test::clear();
+
+        let after = statistics();
+        assert!(after.level(LogLevel::Error) > before.level(LogLevel::Error));
+        assert!(after.total() >= before.total() + 4);
+
+        let stats = target_statistics().into_iter()
+                                       .find(|(target, _)| target == "stats")
+                                       .map(|(_, stats)| stats)
+                                       .unwrap();
+        assert_eq!(1, stats.level(LogLevel::Error));
+        assert_eq!(2, stats.level(LogLevel::Info));
+        assert_eq!(3, stats.total());
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The per-target maximum log levels.
+//
+// Each entry holds the set of levels enabled for a prefix, which matches the
+// target of the same name and the targets inside it, so `app::db` covers
+// `app::db::pool` but not `app::dbx`. A maximum level is stored as the mask
+// of every level up to it, so the two can be mixed freely.
+//
+// The table is allocated the first time a level is set and never freed. It is
+// kept sorted by descending prefix length, so the first matching entry is the
+// most specific one. ACTIVE is set whenever the table is non-empty, so that
+// programs which never use per-target levels don't pay for the lock.
+
+use std::boxed::Box;
+use std::ptr;
+use std::string::{String, ToString};
+use std::sync::{Once, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::vec::Vec;
+
+use {LevelMask, LogLevel, LogLevelFilter};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static INIT: Once = Once::new();
+static mut TABLE: *const RwLock<Vec<(String, LevelMask)>> = ptr::null();
+
+fn table() -> &'static RwLock<Vec<(String, LevelMask)>> {
+    unsafe {
+        INIT.call_once(|| {
+            TABLE = Box::into_raw(Box::new(RwLock::new(Vec::new())));
+        });
+        &*TABLE
+    }
+}
+
+fn update<F>(f: F)
+    where F: FnOnce(&mut Vec<(String, LevelMask)>)
+{
+    let mut table = match table().write() {
+        Ok(table) => table,
+        Err(err) => err.into_inner(),
+    };
+    f(&mut table);
+    ACTIVE.store(!table.is_empty(), Ordering::Release);
+    ::callsite::invalidate();
+}
+
+/// Sets the maximum log level for the target `prefix` and all targets inside
+/// it.
+///
+/// Log messages whose target is `prefix` or starts with `prefix::` and whose
+/// level is higher than `level` are discarded before the logger is consulted.
+/// If more than one prefix matches a target, the longest one is used. Targets
+/// that don't match any prefix are only subject to the global maximum log
+/// level.
+///
+/// This cannot make a message pass the global maximum log level filter, so
+/// `max_log_level` should be set to the most verbose level needed by any
+/// target.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::LogLevelFilter;
+///
+/// // only let warnings and errors from hyper's client through
+/// log::set_target_max_level("hyper::client", LogLevelFilter::Warn);
+/// ```
+pub fn set_target_max_level(prefix: &str, level: LogLevelFilter) {
+    set_target_levels(prefix, LevelMask::from(level))
+}
+
+/// Sets the levels enabled for the target `prefix` and all targets inside it.
+///
+/// This is like `set_target_max_level`, but lets through any set of levels
+/// rather than every level up to a maximum. It replaces any maximum level set
+/// for the same prefix.
+///
+/// The global maximum log level still applies, so `max_log_level` must be at
+/// least `levels.max_level()` for every level in the set to get through.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{LevelMask, LogLevel, LogLevelFilter};
+///
+/// // warnings and errors, plus trace messages, but nothing in between
+/// let levels = LevelMask::from(LogLevelFilter::Warn) | LevelMask::from(LogLevel::Trace);
+/// log::set_target_levels("hyper::client", levels);
+/// ```
+pub fn set_target_levels(prefix: &str, levels: LevelMask) {
+    update(|table| {
+        match table.iter().position(|(p, _)| p == prefix) {
+            Some(idx) => table[idx].1 = levels,
+            None => {
+                let idx = table.iter()
+                               .position(|(p, _)| p.len() < prefix.len())
+                               .unwrap_or(table.len());
+                table.insert(idx, (prefix.to_string(), levels));
+            }
+        }
+    })
+}
+
+/// Removes the levels set for `prefix` by `set_target_max_level` or
+/// `set_target_levels`.
+pub fn remove_target_max_level(prefix: &str) {
+    update(|table| table.retain(|(p, _)| p != prefix))
+}
+
+/// Removes all per-target maximum log levels.
+pub fn clear_target_max_levels() {
+    update(|table| table.clear())
+}
+
+/// Returns the maximum log level set for the most specific prefix of
+/// `target`, or `None` if no prefix of it has a level set.
+///
+/// If the prefix was given a set of levels with `set_target_levels`, this is
+/// the most verbose level in the set.
+pub fn target_max_level(target: &str) -> Option<LogLevelFilter> {
+    target_levels(target).map(|levels| levels.max_level())
+}
+
+/// Returns the levels enabled for the most specific prefix of `target`, or
+/// `None` if no prefix of it has levels set.
+pub fn target_levels(target: &str) -> Option<LevelMask> {
+    if !ACTIVE.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let table = match table().read() {
+        Ok(table) => table,
+        Err(err) => err.into_inner(),
+    };
+    table.iter()
+         .find(|&(prefix, _)| ::module_matches(prefix, target))
+         .map(|&(_, levels)| levels)
+}
+
+pub fn enabled(level: LogLevel, target: &str) -> bool {
+    match target_levels(target) {
+        Some(levels) => levels.contains(level),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {LevelMask, LogLevel, LogLevelFilter};
+    use super::{enabled, set_target_max_level, set_target_levels, remove_target_max_level,
+                target_max_level, target_levels};
+
+    #[test]
+    fn test_target_max_level() {
+        set_target_max_level("targets_test", LogLevelFilter::Warn);
+        set_target_max_level("targets_test::inner", LogLevelFilter::Debug);
+        set_target_max_level("targets_test::inner::quiet", LogLevelFilter::Off);
+
+        assert_eq!(Some(LogLevelFilter::Warn), target_max_level("targets_test"));
+        assert_eq!(Some(LogLevelFilter::Debug),
+                   target_max_level("targets_test::inner::loud"));
+        assert_eq!(Some(LogLevelFilter::Off),
+                   target_max_level("targets_test::inner::quiet"));
+        assert_eq!(None, target_max_level("other"));
+
+        assert!(enabled(LogLevel::Warn, "targets_test::outer"));
+        assert!(!enabled(LogLevel::Info, "targets_test::outer"));
+        assert!(enabled(LogLevel::Debug, "targets_test::inner"));
+        assert!(!enabled(LogLevel::Error, "targets_test::inner::quiet"));
+        assert!(enabled(LogLevel::Trace, "other"));
+
+        set_target_max_level("targets_test", LogLevelFilter::Trace);
+        assert!(enabled(LogLevel::Trace, "targets_test::outer"));
+
+        remove_target_max_level("targets_test::inner");
+        assert_eq!(Some(LogLevelFilter::Trace),
+                   target_max_level("targets_test::inner::loud"));
+
+        remove_target_max_level("targets_test");
+        remove_target_max_level("targets_test::inner::quiet");
+        assert_eq!(None, target_max_level("targets_test"));
+    }
+
+    #[test]
+    fn test_target_levels() {
+        let levels = LevelMask::from(LogLevel::Warn) | LevelMask::from(LogLevel::Trace);
+        set_target_levels("targets_mask_test", levels);
+
+        assert_eq!(Some(levels), target_levels("targets_mask_test::inner"));
+        assert_eq!(Some(LogLevelFilter::Trace), target_max_level("targets_mask_test"));
+        assert!(!enabled(LogLevel::Error, "targets_mask_test"));
+        assert!(enabled(LogLevel::Warn, "targets_mask_test"));
+        assert!(!enabled(LogLevel::Info, "targets_mask_test"));
+        assert!(!enabled(LogLevel::Debug, "targets_mask_test"));
+        assert!(enabled(LogLevel::Trace, "targets_mask_test"));
+
+        set_target_max_level("targets_mask_test", LogLevelFilter::Info);
+        assert_eq!(Some(LevelMask::from(LogLevelFilter::Info)),
+                   target_levels("targets_mask_test"));
+        assert!(enabled(LogLevel::Error, "targets_mask_test"));
+        assert!(!enabled(LogLevel::Trace, "targets_mask_test"));
+
+        remove_target_max_level("targets_mask_test");
+        assert_eq!(None, target_levels("targets_mask_test"));
+    }
+
+    #[test]
+    fn test_sibling_target() {
+        set_target_max_level("targets_sibling_test::db", LogLevelFilter::Warn);
+
+        assert_eq!(Some(LogLevelFilter::Warn),
+                   target_max_level("targets_sibling_test::db::pool"));
+        assert_eq!(None, target_max_level("targets_sibling_test::dbx"));
+        assert!(enabled(LogLevel::Info, "targets_sibling_test::dbx"));
+        assert!(!enabled(LogLevel::Info, "targets_sibling_test::db"));
+
+        remove_target_max_level("targets_sibling_test::db");
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Capturing log records in tests.
+//!
+//! `init` installs a `CaptureLogger` as the global logger. It keeps every
+//! record in a buffer belonging to the thread that logged it, so tests running
+//! in parallel each only see their own records. `drain` and `with_records`
+//! then give access to the records logged so far on the current thread.
+//!
+//! # Examples
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate log;
+//!
+//! use log::LogLevel;
+//!
+//! fn open(path: &str) {
+//!     warn!("{} does not exist", path);
+//! }
+//!
+//! fn main() {
+//!     log::test::init().unwrap();
+//!
+//!     open("/etc/missing");
+//!
+//!     let records = log::test::drain();
+//!     assert_eq!(1, records.len());
+//!     assert_eq!(LogLevel::Warn, records[0].level());
+//!     assert_eq!("/etc/missing does not exist", records[0].message());
+//! }
+//! ```
+
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::mem;
+use std::string::String;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::vec::Vec;
+
+use {Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord, OwnedLogRecord,
+     SetLoggerError};
+
+thread_local! {
+    static RECORDS: RefCell<Vec<OwnedLogRecord>> = const { RefCell::new(Vec::new()) }
+}
+
+static INIT: Once = Once::new();
+// set by INIT if the CaptureLogger was installed
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A logger which captures every record into a per-thread buffer.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CaptureLogger;
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let record = record.to_owned();
+        // the buffer is gone if this thread is exiting, and is borrowed if
+        // this is a panic being logged from inside with_records
+        let _ = RECORDS.try_with(|records| {
+            if let Ok(mut records) = records.try_borrow_mut() {
+                records.push(record);
+            }
+        });
+    }
+}
+
+/// Installs a `CaptureLogger` as the global logger, and sets the maximum log
+/// level to `Trace`.
+///
+/// This may be called any number of times, so every test can call it
+/// without coordinating with the others. Returns an error if a different
+/// logger has already been installed.
+pub fn init() -> Result<(), SetLoggerError> {
+    // callers racing with the first one wait for it to finish installing
+    INIT.call_once(|| {
+        let result = ::set_boxed_logger(|max_log_level| {
+            max_log_level.set(LogLevelFilter::Trace);
+            Box::new(CaptureLogger)
+        });
+        INSTALLED.store(result.is_ok(), Ordering::SeqCst);
+    });
+
+    if INSTALLED.load(Ordering::SeqCst) {
+        Ok(())
+    } else {
+        Err(SetLoggerError(()))
+    }
+}
+
+/// Removes and returns the records captured on the current thread.
+pub fn drain() -> Vec<OwnedLogRecord> {
+    RECORDS.with(|records| mem::take(&mut *records.borrow_mut()))
+}
+
+/// Calls `f` with the records captured on the current thread, without
+/// removing them.
+pub fn with_records<F, T>(f: F) -> T
+    where F: FnOnce(&[OwnedLogRecord]) -> T
+{
+    RECORDS.with(|records| f(&records.borrow()))
+}
+
+/// Discards the records captured on the current thread.
+pub fn clear() {
+    RECORDS.with(|records| records.borrow_mut().clear())
+}
+
+// WARNING
+// This is not considered part of the crate's public API. It is subject to
+// change at any time.
+#[doc(hidden)]
+pub fn __assert_logged(expected: bool, level: LogLevel, target: Option<&str>,
+                       message: &str) {
+    with_records(|records| {
+        let found = records.iter().any(|r| {
+            r.level() == level && target.is_none_or(|t| r.target() == t) &&
+                r.message().contains(message)
+        });
+        if found == expected {
+            return;
+        }
+
+        let mut msg = String::new();
+        let no = if expected { "" } else { "no " };
+        let _ = write!(msg, "expected {}a {} record", no, level);
+        if let Some(target) = target {
+            let _ = write!(msg, " with target `{}`", target);
+        }
+        let _ = write!(msg, " containing {:?}\ncaptured records:", message);
+        if records.is_empty() {
+            msg.push_str(" none");
+        }
+        for r in records {
+            let marker = if r.level() == level { '>' } else { ' ' };
+            let _ = write!(msg, "\n  {} {:<5} [{}] {}",
+                           marker, r.level(), r.target(), r.message());
+        }
+        panic!("{}", msg);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use LogLevel;
+    use super::{init, drain, with_records, clear};
+
+    #[test]
+    fn test_capture() {
+        init().unwrap();
+        init().unwrap();
+        clear();
+
+        warn!(target: "capture", "disk {}", "full");
+        debug!(target: "capture", "checked");
+        assert_eq!(2, with_records(|records| records.len()));
+
+        let records = drain();
+        assert_eq!(LogLevel::Warn, records[0].level());
+        assert_eq!("capture", records[0].target());
+        assert_eq!("disk full", records[0].message());
+        assert_eq!(LogLevel::Debug, records[1].level());
+        assert!(drain().is_empty());
+    }
+
+    #[test]
+    fn test_capture_per_thread() {
+        init().unwrap();
+        clear();
+
+        info!("on the test thread");
+        thread::spawn(|| {
+            info!("on another thread");
+            assert_eq!(1, drain().len());
+        }).join().unwrap();
+
+        let records = drain();
+        assert_eq!(1, records.len());
+        assert_eq!("on the test thread", records[0].message());
+    }
+
+    #[test]
+    fn test_assert_logged() {
+        init().unwrap();
+        clear();
+
+        error!(target: "assert", "disk full");
+        assert_logged!(Error, "disk");
+        assert_logged!(target: "assert", Error, "full");
+        assert_not_logged!(Warn, "disk");
+        assert_not_logged!(target: "other", Error, "disk");
+    }
+
+    #[test]
+    fn test_assert_logged_message() {
+        use std::panic;
+        use std::string::String;
+
+        init().unwrap();
+        clear();
+
+        info!(target: "assert", "starting");
+        warn!(target: "assert", "disk almost full");
+        let err = panic::catch_unwind(|| assert_logged!(Warn, "disk full")).unwrap_err();
+        assert_eq!("expected a WARN record containing \"disk full\"\n\
+                    captured records:\n    \
+                    INFO  [assert] starting\n  > \
+                    WARN  [assert] disk almost full",
+                   *err.downcast::<String>().unwrap());
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn test_capture_timestamp() {
+        use std::time::SystemTime;
+
+        init().unwrap();
+        clear();
+
+        let before = SystemTime::now();
+        info!("stamped");
+        let after = SystemTime::now();
+        let timestamp = drain()[0].timestamp().unwrap();
+        assert!(before <= timestamp && timestamp <= after);
+    }
+
+    #[cfg(feature = "backtrace")]
+    #[test]
+    fn test_capture_backtrace() {
+        init().unwrap();
+        clear();
+
+        error!("failed");
+        warn!("retrying");
+        let records = drain();
+        assert!(!records[0].backtrace().unwrap().frames().is_empty());
+        assert!(records[1].backtrace().is_none());
+    }
+}
@@ -0,0 +1,52 @@
+#![cfg(not(feature = "freestanding"))]
+
+#[macro_use] extern crate log;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use log::{LogLevelFilter, Log, LogRecord, LogMetadata};
+
+struct Logger(Arc<Mutex<Vec<String>>>);
+
+impl Log for Logger {
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let message = record.args().to_string();
+        self.0.lock().unwrap().push(message.clone());
+        if message == "b" {
+            // logged by another thread while the early records are replayed
+            thread::spawn(|| info!("live")).join().unwrap();
+        }
+    }
+}
+
+#[test]
+fn early_records_are_replayed() {
+    log::buffer_early_records(4, LogLevelFilter::Debug);
+    debug!("a");
+    trace!("not kept");
+    info!("b");
+    info!(target: "noisy", "filtered by target");
+    info!("c");
+    let dropped = log::dropped_records().total();
+    warn!("over capacity");
+    assert_eq!(dropped + 1, log::dropped_records().total());
+
+    // per-target levels set before the logger is installed still apply
+    log::set_target_max_level("noisy", LogLevelFilter::Warn);
+
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let logger = Logger(lines.clone());
+    log::set_boxed_logger(|max| {
+        max.set(LogLevelFilter::Info);
+        Box::new(logger)
+    }).unwrap();
+    info!("d");
+
+    // debug records are above the level set by the logger, and the record
+    // logged during the replay comes after every buffered one
+    assert_eq!(vec!["b", "c", "live", "d"], *lines.lock().unwrap());
+}
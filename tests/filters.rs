@@ -1,9 +1,21 @@
 #[macro_use] extern crate log;
 
 use std::sync::{Arc, Mutex};
-use log::{LogLevel, set_logger, LogLevelFilter, Log, LogRecord, LogMetadata};
+use log::{LogLevel, LogLevelFilter, Log, LogRecord, LogMetadata};
+#[cfg(not(feature = "freestanding"))]
+use log::set_boxed_logger;
+#[cfg(all(feature = "freestanding", not(feature = "racy")))]
+use log::set_logger;
 use log::MaxLogLevelFilter;
 
+// This test installs the logger from a single thread.
+#[cfg(feature = "racy")]
+fn set_logger<M>(make_logger: M) -> Result<(), log::SetLoggerError>
+    where M: FnOnce(MaxLogLevelFilter) -> &'static dyn Log
+{
+    unsafe { log::set_logger_racy(make_logger) }
+}
+
 struct State {
     last_log: Mutex<Option<LogLevel>>,
     filter: MaxLogLevelFilter,
@@ -24,7 +36,7 @@ impl Log for Logger {
 #[cfg(not(feature = "freestanding"))]
 fn main() {
     let mut a = None;
-    set_logger(|max| {
+    set_boxed_logger(|max| {
         let me = Arc::new(State {
             last_log: Mutex::new(None),
             filter: max,
@@ -35,54 +47,95 @@ fn main() {
     let a = a.unwrap();
 
     test(&a, LogLevelFilter::Off);
+    test(&a, LogLevelFilter::Critical);
     test(&a, LogLevelFilter::Error);
     test(&a, LogLevelFilter::Warn);
+    test(&a, LogLevelFilter::Notice);
     test(&a, LogLevelFilter::Info);
     test(&a, LogLevelFilter::Debug);
     test(&a, LogLevelFilter::Trace);
+    test(&a, LogLevelFilter::Trace2);
+    test(&a, LogLevelFilter::Trace3);
+
+    #[cfg(not(feature = "leak_logger"))]
+    test_swap_and_shutdown(&a);
+}
+
+#[cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+fn test_swap_and_shutdown(a: &State) {
+    let mut b = None;
+    let old = log::swap_logger(|max| {
+        let me = Arc::new(State {
+            last_log: Mutex::new(None),
+            filter: max,
+        });
+        b = Some(me.clone());
+        Box::new(Logger(me))
+    }).unwrap();
+    let b = b.unwrap();
+    error!("");
+    last(a, None);
+    last(&b, Some(LogLevel::Error));
+    drop(old);
+
+    let logger = log::shutdown_logger().unwrap();
+    error!("");
+    last(&b, None);
+    // passes the maximum log level, but there's no logger
+    log::set_max_level(LogLevelFilter::Error);
+    let dropped = log::dropped_records();
+    error!("");
+    assert_eq!(dropped.level(LogLevel::Error) + 1,
+               log::dropped_records().level(LogLevel::Error));
+    assert!(log::shutdown_logger().is_err());
+    drop(logger);
 }
 
 #[cfg(feature = "freestanding")]
 fn main() {
-    use std::cell::UnsafeCell;
-    use std::mem;
-
     let mut a = None;
-    let mut b = None;
-    let mut c: *mut Log = unsafe {mem::zeroed()};
-    let d: *const &'static Log = unsafe {mem::transmute(&c)};
     set_logger(|max| {
         let me = Arc::new(State {
             last_log: Mutex::new(None),
             filter: max,
         });
         a = Some(me.clone());
-        b = Some(UnsafeCell::new(Logger(me)));
-        c = b.as_ref().unwrap().get();
-        d
+        unsafe { &*Box::into_raw(Box::new(Logger(me))) }
     }).unwrap();
     let a = a.unwrap();
 
     test(&a, LogLevelFilter::Off);
+    test(&a, LogLevelFilter::Critical);
     test(&a, LogLevelFilter::Error);
     test(&a, LogLevelFilter::Warn);
+    test(&a, LogLevelFilter::Notice);
     test(&a, LogLevelFilter::Info);
     test(&a, LogLevelFilter::Debug);
     test(&a, LogLevelFilter::Trace);
+    test(&a, LogLevelFilter::Trace2);
+    test(&a, LogLevelFilter::Trace3);
 }
 
 fn test(a: &State, filter: LogLevelFilter) {
     a.filter.set(filter);
+    critical!("");
+    last(a, t(LogLevel::Critical, filter));
     error!("");
-    last(&a, t(LogLevel::Error, filter));
+    last(a, t(LogLevel::Error, filter));
     warn!("");
-    last(&a, t(LogLevel::Warn, filter));
+    last(a, t(LogLevel::Warn, filter));
+    notice!("");
+    last(a, t(LogLevel::Notice, filter));
     info!("");
-    last(&a, t(LogLevel::Info, filter));
+    last(a, t(LogLevel::Info, filter));
     debug!("");
-    last(&a, t(LogLevel::Debug, filter));
+    last(a, t(LogLevel::Debug, filter));
     trace!("");
-    last(&a, t(LogLevel::Trace, filter));
+    last(a, t(LogLevel::Trace, filter));
+    trace2!("");
+    last(a, t(LogLevel::Trace2, filter));
+    trace3!("");
+    last(a, t(LogLevel::Trace3, filter));
 
     fn t(lvl: LogLevel, filter: LogLevelFilter) -> Option<LogLevel> {
         if lvl <= filter {Some(lvl)} else {None}
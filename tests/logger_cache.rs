@@ -0,0 +1,64 @@
+#![cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+
+#[macro_use] extern crate log;
+
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
+use log::{LogLevelFilter, Log, LogRecord, LogMetadata};
+
+// Records every message and flush, prefixed with the logger's name.
+struct Logger {
+    name: &'static str,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        self.lines.lock().unwrap().push(format!("{} {}", self.name, record.args()));
+    }
+
+    fn flush(&self) {
+        self.lines.lock().unwrap().push(format!("{} flush", self.name));
+    }
+}
+
+#[test]
+fn swap_and_shutdown_invalidate_cached_loggers() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let first = Logger { name: "first", lines: lines.clone() };
+    log::set_boxed_logger(|max| {
+        max.set(LogLevelFilter::Info);
+        Box::new(first)
+    }).unwrap();
+
+    // both threads have the first logger cached once they've logged
+    let (go, wait) = mpsc::channel();
+    let (done, waited) = mpsc::channel();
+    let other = thread::spawn(move || {
+        info!("a");
+        done.send(()).unwrap();
+        wait.recv().unwrap();
+        info!("c");
+    });
+    waited.recv().unwrap();
+    info!("b");
+
+    let second = Logger { name: "second", lines: lines.clone() };
+    assert!(log::swap_logger(|_| Box::new(second)).is_some());
+    info!("d");
+    go.send(()).unwrap();
+    other.join().unwrap();
+
+    // flush goes to the logger without checking the level, which shutdown
+    // sets to off
+    drop(log::shutdown_logger().unwrap());
+    log::flush();
+
+    assert_eq!(vec!["first a", "first b", "second d", "second c"],
+               *lines.lock().unwrap());
+}
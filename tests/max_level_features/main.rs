@@ -1,7 +1,7 @@
 #[macro_use] extern crate log;
 
 use std::sync::{Arc, Mutex};
-use log::{LogLevel, set_logger, LogLevelFilter, Log, LogRecord, LogMetadata};
+use log::{LogLevel, set_boxed_logger, LogLevelFilter, Log, LogRecord, LogMetadata};
 use log::MaxLogLevelFilter;
 
 struct State {
@@ -23,7 +23,7 @@ impl Log for Logger {
 
 fn main() {
     let mut a = None;
-    set_logger(|max| {
+    set_boxed_logger(|max| {
         let me = Arc::new(State {
             last_log: Mutex::new(None),
             filter: max,
@@ -34,19 +34,26 @@ fn main() {
     let a = a.unwrap();
 
     test(&a, LogLevelFilter::Off);
+    test(&a, LogLevelFilter::Critical);
     test(&a, LogLevelFilter::Error);
     test(&a, LogLevelFilter::Warn);
+    test(&a, LogLevelFilter::Notice);
     test(&a, LogLevelFilter::Info);
     test(&a, LogLevelFilter::Debug);
     test(&a, LogLevelFilter::Trace);
+    test(&a, LogLevelFilter::Trace3);
 }
 
 fn test(a: &State, filter: LogLevelFilter) {
     a.filter.set(filter);
+    critical!("");
+    last(&a, t(LogLevel::Critical, filter));
     error!("");
     last(&a, t(LogLevel::Error, filter));
     warn!("");
     last(&a, t(LogLevel::Warn, filter));
+    notice!("");
+    last(&a, t(LogLevel::Notice, filter));
     info!("");
     last(&a, t(LogLevel::Info, filter));
 
@@ -59,6 +66,8 @@ fn test(a: &State, filter: LogLevelFilter) {
 
     trace!("");
     last(&a, None);
+    trace3!("");
+    last(&a, None);
 
     fn t(lvl: LogLevel, filter: LogLevelFilter) -> Option<LogLevel> {
         if lvl <= filter {Some(lvl)} else {None}
@@ -0,0 +1,79 @@
+#![cfg(not(any(feature = "freestanding", feature = "leak_logger")))]
+
+#[macro_use] extern crate log;
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use log::{LogLevelFilter, Log, LogRecord, LogMetadata};
+
+// Records every message, and blocks inside the logger on "block" until
+// released.
+struct Logger {
+    lines: Mutex<Vec<String>>,
+    blocked: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Log for Logger {
+    fn enabled(&self, _: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let msg = record.args().to_string();
+        if msg == "block" {
+            let mut blocked = self.blocked.lock().unwrap();
+            *blocked = true;
+            self.cond.notify_all();
+            while *blocked {
+                blocked = self.cond.wait(blocked).unwrap();
+            }
+        }
+        self.lines.lock().unwrap().push(msg);
+    }
+}
+
+#[test]
+fn swap_times_out_with_shared_logger_in_use() {
+    let logger = Arc::new(Logger {
+        lines: Mutex::new(Vec::new()),
+        blocked: Mutex::new(false),
+        cond: Condvar::new(),
+    });
+    let shared = logger.clone();
+    log::set_arc_logger(|max| {
+        max.set(LogLevelFilter::Info);
+        shared
+    }).unwrap();
+
+    let inside = thread::spawn(|| info!("block"));
+    {
+        let mut blocked = logger.blocked.lock().unwrap();
+        while !*blocked {
+            blocked = logger.cond.wait(blocked).unwrap();
+        }
+    }
+
+    // the blocked thread keeps the old logger in use, so the swap is refused
+    // and the new logger is handed back
+    let new = log::swap_logger(|max| {
+        max.set(LogLevelFilter::Trace);
+        Box::new(log::NopLogger)
+    });
+    assert!(new.is_some());
+    assert_eq!(LogLevelFilter::Info, log::max_log_level());
+    assert_eq!(2, Arc::strong_count(&logger));
+    info!("after");
+
+    *logger.blocked.lock().unwrap() = false;
+    logger.cond.notify_all();
+    inside.join().unwrap();
+    assert_eq!(vec!["after", "block"], *logger.lines.lock().unwrap());
+
+    // with nothing inside the logger, the swap goes through
+    let old = log::swap_logger(|_| Box::new(log::NopLogger));
+    assert!(old.is_some());
+    assert!(log::logger_arc().is_none());
+    drop(old);
+    assert_eq!(1, Arc::strong_count(&logger));
+}